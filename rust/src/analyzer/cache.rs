@@ -0,0 +1,93 @@
+use crate::analyzer::model::{OutputRecord, ANALYZER_VERSION};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Records of every analyzed file from the previous run, keyed by the path
+/// relative to the project root, so unchanged files are not parsed again.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RecordCache {
+    files: BTreeMap<String, CachedFile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct CachedFile {
+    hash: String,
+    records: Vec<OutputRecord>,
+}
+
+impl RecordCache {
+    /// Load the manifest at `path`, starting empty if there is none yet or it
+    /// was written in an incompatible format
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cache: {}", path.display()))?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create cache directory: {}", dir.display()))?;
+        }
+        let content = serde_json::to_string(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write cache: {}", path.display()))
+    }
+
+    /// Hash of a file's content. The repo id ends up in every record, module
+    /// paths are relative to the crate root, and the analyzer version and
+    /// `options` (test handling, kind filter) decide which records exist, so
+    /// those are mixed in too.
+    pub fn hash(repo_id: &str, crate_root: &str, options: &str, content: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(ANALYZER_VERSION.as_bytes());
+        hasher.update([0x1f]);
+        hasher.update(repo_id.as_bytes());
+        hasher.update([0x1f]);
+        hasher.update(crate_root.as_bytes());
+        hasher.update([0x1f]);
+        hasher.update(options.as_bytes());
+        hasher.update([0x1f]);
+        hasher.update(content.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    /// Records cached for `rel_path`, if its content still hashes to `hash`
+    pub fn get(&self, rel_path: &str, hash: &str) -> Option<&[OutputRecord]> {
+        self.files
+            .get(rel_path)
+            .filter(|f| f.hash == hash)
+            .map(|f| f.records.as_slice())
+    }
+
+    pub fn insert(&mut self, rel_path: String, hash: String, records: Vec<OutputRecord>) {
+        self.files.insert(rel_path, CachedFile { hash, records });
+    }
+
+    pub fn remove(&mut self, rel_path: &str) {
+        self.files.remove(rel_path);
+    }
+
+    pub fn contains(&self, rel_path: &str) -> bool {
+        self.files.contains_key(rel_path)
+    }
+
+    /// Relative paths of all cached files
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.files.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
@@ -0,0 +1,89 @@
+use crate::analyzer::model::OutputRecord;
+use std::collections::HashMap;
+
+/// What it takes to bring a store holding one export up to date with another
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExportDiff {
+    /// Ids missing from the old export
+    pub added: Vec<OutputRecord>,
+    /// Ids in both exports whose fingerprint differs
+    pub changed: Vec<OutputRecord>,
+    /// Ids missing from the new export, in old export order
+    pub removed: Vec<String>,
+}
+
+/// How a record of the new export relates to the old one
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Changed,
+    Unchanged,
+}
+
+/// Classifies new records one at a time, so an export can be diffed while it
+/// streams; only ids and hashes of the old export are kept
+pub struct ExportDiffer {
+    old: HashMap<String, String>,
+    // Id and path of each old record, in old export order
+    order: Vec<(String, String)>,
+}
+
+impl ExportDiffer {
+    pub fn new(old: &[OutputRecord]) -> Self {
+        ExportDiffer {
+            old: old.iter().map(|r| (r.id.clone(), hash_of(r))).collect(),
+            order: old
+                .iter()
+                .map(|r| (r.id.clone(), r.payload.path.clone()))
+                .collect(),
+        }
+    }
+
+    pub fn classify(&mut self, record: &OutputRecord) -> Change {
+        match self.old.remove(&record.id) {
+            None => Change::Added,
+            Some(hash) if hash == hash_of(record) => Change::Unchanged,
+            Some(_) => Change::Changed,
+        }
+    }
+
+    /// Ids of the old export never passed to `classify`
+    pub fn removed(self) -> Vec<String> {
+        self.removed_in(|_| true)
+    }
+
+    /// Like `removed`, for old records whose path passes `keep`: when only
+    /// some files were analyzed, records of the others weren't removed
+    pub fn removed_in(self, keep: impl Fn(&str) -> bool) -> Vec<String> {
+        let old = self.old;
+        self.order
+            .into_iter()
+            .filter(|(id, path)| old.contains_key(id) && keep(path))
+            .map(|(id, _)| id)
+            .collect()
+    }
+}
+
+// Exports written before `fingerprint` existed get it computed
+fn hash_of(record: &OutputRecord) -> String {
+    if record.payload.fingerprint.is_empty() {
+        record.fingerprint()
+    } else {
+        record.payload.fingerprint.clone()
+    }
+}
+
+/// Compares two exports by id; see `ExportDiff`
+pub fn diff_exports(old: &[OutputRecord], new: &[OutputRecord]) -> ExportDiff {
+    let mut differ = ExportDiffer::new(old);
+    let mut diff = ExportDiff::default();
+    for record in new {
+        match differ.classify(record) {
+            Change::Added => diff.added.push(record.clone()),
+            Change::Changed => diff.changed.push(record.clone()),
+            Change::Unchanged => {}
+        }
+    }
+    diff.removed = differ.removed();
+    diff
+}
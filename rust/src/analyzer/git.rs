@@ -0,0 +1,45 @@
+use crate::complexity_analyzer::git::{changed_rust_files, FileStatus};
+use anyhow::{Context, Result};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// HEAD of the repository containing `dir`, if any
+pub fn head(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let head = String::from_utf8(output.stdout).ok()?;
+    Some(head.trim().to_string()).filter(|h| !h.is_empty())
+}
+
+/// Rust files under a directory that differ from a git ref, relative to that
+/// directory
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChangedFiles {
+    /// Added, modified or untracked since the ref, committed or not
+    pub modified: BTreeSet<PathBuf>,
+    /// In the ref but gone from the working tree
+    pub deleted: BTreeSet<PathBuf>,
+}
+
+/// `.rs` files under `dir` whose working tree content differs from `since`
+/// (any revision `git diff` takes), untracked files included. Renames count
+/// as a deletion and an addition.
+pub fn changed_files(dir: &Path, since: &str) -> Result<ChangedFiles> {
+    let files = changed_rust_files(dir, since, ".")
+        .with_context(|| format!("Failed to list files changed since {}", since))?;
+    let mut changes = ChangedFiles::default();
+    for (file, status) in files {
+        match status {
+            FileStatus::Deleted => changes.deleted.insert(file),
+            FileStatus::Added | FileStatus::Modified => changes.modified.insert(file),
+        };
+    }
+    Ok(changes)
+}
@@ -1,6 +1,21 @@
+pub mod cache;
+pub mod diff;
+pub mod git;
 pub mod model;
 pub mod scanner;
+pub mod sink;
 pub mod util;
+pub mod workspace;
 
-pub use model::{OutputPayload, OutputRecord, VectorFields};
-pub use scanner::{analyze_project, write_ndjson, AnalyzeConfig};
+pub use cache::RecordCache;
+pub use diff::{diff_exports, Change, ExportDiff, ExportDiffer};
+pub use model::{
+    OutputPayload, OutputRecord, VectorFields, ANALYZER_VERSION, LANGUAGE, SCHEMA_VERSION,
+};
+pub use scanner::{
+    analyze_file, analyze_project, analyze_project_streaming, analyze_project_with_stats,
+    analyze_source, read_ndjson, write_json_array, write_ndjson, AnalyzeConfig, AnalyzeStats,
+    DeletedFile, SkippedFile, RECORD_KINDS,
+};
+pub use sink::{QdrantSink, SinkStats};
+pub use workspace::{crate_units, CrateUnit};
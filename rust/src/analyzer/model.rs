@@ -1,6 +1,15 @@
-use serde::Serialize;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
-#[derive(Serialize, Debug, Clone)]
+/// Bumped whenever the record layout changes
+pub const SCHEMA_VERSION: u32 = 15;
+/// Source language of every record this analyzer emits
+pub const LANGUAGE: &str = "rust";
+/// Version of the analyzer that produced a record
+pub const ANALYZER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct VectorFields {
     pub signature: String,
     pub identifiers: String,
@@ -8,20 +17,182 @@ pub struct VectorFields {
     pub doc_comment: String,
 }
 
-#[derive(Serialize, Debug, Clone)]
+/// Placeholders an embedding template may use, one per `VectorFields` field
+pub const TEMPLATE_FIELDS: [&str; 4] = ["signature", "identifiers", "code_body", "doc_comment"];
+
+impl VectorFields {
+    /// Fills `{signature}`, `{identifiers}`, `{code_body}` and `{doc_comment}`
+    /// in `template`; `{{` and `}}` stand for literal braces. Check templates
+    /// with `validate_template` first: unknown placeholders are left as written.
+    pub fn render(&self, template: &str) -> String {
+        expand(template, |name| self.field(name)).0
+    }
+
+    /// Errors on placeholders `render` doesn't know and on unmatched braces
+    pub fn validate_template(template: &str) -> Result<()> {
+        let (_, unknown) = expand(template, |name| {
+            TEMPLATE_FIELDS.contains(&name).then_some("")
+        });
+        if let Some(name) = unknown.first() {
+            if name.starts_with(['{', '}']) {
+                bail!("Unmatched brace in embedding template at `{}`", name);
+            }
+            bail!(
+                "Unknown placeholder {{{}}} in embedding template, expected one of {}",
+                name,
+                TEMPLATE_FIELDS.map(|f| format!("{{{f}}}")).join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    fn field(&self, name: &str) -> Option<&str> {
+        match name {
+            "signature" => Some(&self.signature),
+            "identifiers" => Some(&self.identifiers),
+            "code_body" => Some(&self.code_body),
+            "doc_comment" => Some(&self.doc_comment),
+            _ => None,
+        }
+    }
+}
+
+// Expanded template and the placeholders `lookup` didn't know. An unmatched
+// brace counts as unknown, with the text after it as its name.
+fn expand<'a>(template: &str, lookup: impl Fn(&str) -> Option<&'a str>) -> (String, Vec<String>) {
+    let mut out = String::with_capacity(template.len());
+    let mut unknown = Vec::new();
+    let mut rest = template;
+    while let Some(i) = rest.find(['{', '}']) {
+        out.push_str(&rest[..i]);
+        let brace = &rest[i..i + 1];
+        let after = &rest[i + 1..];
+        if after.starts_with(brace) {
+            out.push_str(brace);
+            rest = &after[1..];
+            continue;
+        }
+        let end = match (brace, after.find(['{', '}'])) {
+            ("{", Some(end)) if after[end..].starts_with('}') => end,
+            _ => {
+                unknown.push(rest[i..].to_string());
+                out.push_str(&rest[i..]);
+                return (out, unknown);
+            }
+        };
+        let name = &after[..end];
+        match lookup(name) {
+            Some(value) => out.push_str(value),
+            None => {
+                unknown.push(name.to_string());
+                out.push_str(&rest[i..i + end + 2]);
+            }
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    (out, unknown)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OutputPayload {
     pub repo_id: String,
+    /// Package name of the crate the file belongs to, empty when unknown
+    pub crate_name: String,
+    /// Relative to the analyzed directory, the workspace root for workspaces
     pub path: String,
     pub kind: String,
     pub qual_symbol: String,
     pub start_line: usize,
     pub end_line: usize,
+    /// Byte range of `text` in the file, so `&content[start_byte..end_byte]`
+    /// is exactly `text`, line breaks included. A "uses" record whose use
+    /// items have other items between them spans all of it, while its text
+    /// keeps only the use items.
+    pub start_byte: usize,
+    pub end_byte: usize,
     pub text: String,
+    pub language: String,
+    pub analyzer_version: String,
+    /// `None` when no commit was given or detected
+    pub commit: Option<String>,
+    /// Rough token count of signature, code_body and doc_comment together
+    pub approx_tokens: usize,
+    /// Paths imported by the file's `use` items, only set on "uses" records
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edges: Vec<String>,
+    /// The subset of `edges` that is re-exported with `pub use`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reexports: Vec<String>,
+    /// Functions a "fn" record calls: joined paths for path calls, bare names
+    /// for method calls
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub references: Vec<String>,
+    /// Id of the oversized record this chunk was split from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<String>,
+    /// "pub", "pub(crate)", "pub(super)", "pub(in path)" or "private" on
+    /// struct, enum, free fn, inherent method and reexport records; empty
+    /// otherwise
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub visibility: String,
+    /// Non-doc attributes as written, e.g. `#[derive(Debug, Clone)]`, on
+    /// struct, enum, free fn and impl method records
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<String>,
+    /// `text` and `code_body` were cut to the configured size and end with a
+    /// `… [truncated N bytes]` marker; the byte range still covers the whole
+    /// original text
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Hash of `vector_fields` and the rest of the payload, see
+    /// `OutputRecord::fingerprint`
+    #[serde(default)]
+    pub fingerprint: String,
+    /// Hash of code_body and signature only, equal for identical code in any
+    /// repo or file
+    #[serde(default)]
+    pub content_hash: String,
+    /// Path a "reexport" record points at, as written in the `pub use`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub target: String,
+    /// How a method takes `self` ("self", "&self", "&mut self" or
+    /// "self: Box<Self>"); `None` for associated functions and non-methods
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub receiver: Option<String>,
 }
 
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct OutputRecord {
+    pub schema_version: u32,
     pub id: String,
     pub vector_fields: VectorFields,
+    /// `vector_fields` rendered with `AnalyzeConfig::embedding_template`,
+    /// for services that embed one string per record
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_text: Option<String>,
     pub payload: OutputPayload,
 }
+
+impl OutputRecord {
+    /// Changes exactly when something that is embedded or stored changes:
+    /// the vector fields or any payload field, positions included. Commit,
+    /// crate name and the values derived from the vector fields don't count.
+    pub fn fingerprint(&self) -> String {
+        let mut payload = serde_json::to_value(&self.payload).expect("payload serializes");
+        if let Some(fields) = payload.as_object_mut() {
+            for key in [
+                "commit",
+                "crate_name",
+                "fingerprint",
+                "approx_tokens",
+                "content_hash",
+            ] {
+                fields.remove(key);
+            }
+        }
+        let mut hasher = Sha256::new();
+        hasher.update(serde_json::json!([self.vector_fields, payload]).to_string());
+        format!("{:x}", hasher.finalize())
+    }
+}
@@ -1,241 +1,1235 @@
-use crate::analyzer::model::{OutputPayload, OutputRecord, VectorFields};
+use crate::analyzer::cache::RecordCache;
+use crate::analyzer::git;
+use crate::analyzer::model::{
+    OutputPayload, OutputRecord, VectorFields, ANALYZER_VERSION, LANGUAGE, SCHEMA_VERSION,
+};
 use crate::analyzer::util::*;
+use crate::analyzer::workspace::{crate_units, CrateUnit};
+use crate::complexity_analyzer::is_test_code;
+use crate::complexity_analyzer::walk::{is_generated, rust_files, FileSelection};
 use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use indicatif::ProgressBar;
 use quote::ToTokens;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::io::{BufWriter, Write};
+use std::io::{BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
 use syn::spanned::Spanned;
-use walkdir::WalkDir;
 
 #[derive(Clone, Debug)]
 pub struct AnalyzeConfig {
     pub path: PathBuf,
     pub repo_id: String,
+    /// Commit stamped on every record; takes precedence over `detect_git`
+    pub commit: Option<String>,
+    /// Ask git for HEAD of `path` when no commit is given
+    pub detect_git: bool,
+    /// Only analyze files that differ from this git ref, see
+    /// `git::changed_files`; deleted ones are listed in `AnalyzeStats::deleted`
+    pub changed_since: Option<String>,
+    /// Manifest of per-file hashes and records reused across runs
+    pub cache: Option<PathBuf>,
+    /// Files and directories matching one of these globs, relative to `path`,
+    /// are skipped on top of `target/` and `*.generated.rs`
+    pub exclude_globs: Vec<String>,
+    /// Honor `.gitignore`, `.ignore` and git exclude files
+    pub respect_gitignore: bool,
+    /// Worker threads for parsing; `None` uses one per CPU
+    pub threads: Option<usize>,
+    /// Keep `#[cfg(test)]` modules, `#[test]` functions and files under `tests/`
+    pub include_tests: bool,
+    /// Records whose text is longer than this many chars are split into chunks
+    pub max_chars: Option<usize>,
+    /// Lines repeated at the start of each chunk from the end of the previous one
+    pub chunk_overlap: usize,
+    /// Text and code_body longer than this many bytes are truncated, after chunking
+    pub max_text_bytes: Option<usize>,
+    /// Renders `vector_fields` into each record's `embedding_text`, see
+    /// `VectorFields::render`
+    pub embedding_template: Option<String>,
+    /// Only these workspace members (package names); empty analyzes all
+    pub members: Vec<String>,
+    /// Only records of these kinds (see `RECORD_KINDS`); `None` emits all
+    pub kinds: Option<HashSet<String>>,
+    /// Emit files in path order and each file's records in line order; off
+    /// keeps the filesystem walk order, which differs between machines
+    pub sort: bool,
+    /// Its length is set to the number of files found, then it advances once
+    /// per file analyzed
+    pub progress: Option<ProgressBar>,
 }
 
-fn is_excluded(p: &Path) -> bool {
-    let s = p.to_string_lossy();
-    s.contains("/target/") || s.ends_with(".generated.rs")
+/// Every `payload.kind` the scanner emits
+pub const RECORD_KINDS: [&str; 12] = [
+    "module",
+    "uses",
+    "struct",
+    "enum",
+    "impl",
+    "fn",
+    "trait",
+    "const",
+    "static",
+    "type_alias",
+    "macro",
+    "reexport",
+];
+
+impl AnalyzeConfig {
+    /// Every file under `path` except tests, all record kinds, no cache,
+    /// chunking or truncation, sorted output
+    pub fn new(path: impl Into<PathBuf>, repo_id: &str) -> Self {
+        AnalyzeConfig {
+            path: path.into(),
+            repo_id: repo_id.to_string(),
+            commit: None,
+            detect_git: false,
+            changed_since: None,
+            cache: None,
+            exclude_globs: Vec::new(),
+            respect_gitignore: true,
+            threads: None,
+            include_tests: false,
+            max_chars: None,
+            chunk_overlap: 0,
+            max_text_bytes: None,
+            embedding_template: None,
+            members: Vec::new(),
+            kinds: None,
+            sort: true,
+            progress: None,
+        }
+    }
+
+    /// Whether records of `kind` are wanted
+    pub fn emits(&self, kind: &str) -> bool {
+        self.kinds.as_ref().is_none_or(|kinds| kinds.contains(kind))
+    }
+
+    // Options that change which records a file produces, for cache keys
+    fn record_options(&self) -> String {
+        let mut kinds: Vec<&str> = match &self.kinds {
+            Some(kinds) => kinds.iter().map(String::as_str).collect(),
+            None => vec!["*"],
+        };
+        kinds.sort_unstable();
+        format!("tests={} kinds={}", self.include_tests, kinds.join(","))
+    }
+
+    // Prints above the progress bar, if any, instead of tearing it
+    fn warn(&self, message: &str) {
+        match &self.progress {
+            Some(progress) => progress.suspend(|| eprintln!("{message}")),
+            None => eprintln!("{message}"),
+        }
+    }
+
+    // Records the skip and warns about it
+    fn skip(&self, stats: &mut AnalyzeStats, path: String, reason: String) {
+        self.warn(&format!("[project_analyzer] Skipping {path}: {reason}"));
+        stats.skipped.push(SkippedFile { path, reason });
+    }
+}
+
+/// How each file's records were obtained in one run
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AnalyzeStats {
+    /// Rust files selected for analysis
+    pub files: usize,
+    /// Served from the cache without parsing
+    pub cached: usize,
+    /// Parsed because they were new, changed, or no cache was used
+    pub analyzed: usize,
+    /// In the cache but no longer on disk
+    pub removed: usize,
+    /// Could not be read or parsed, so they produced no records
+    pub skipped: Vec<SkippedFile>,
+    /// Relative paths of the files that differ from `changed_since`, whether
+    /// or not they could be analyzed
+    pub changed: Vec<String>,
+    /// Gone since `changed_since`
+    pub deleted: Vec<DeletedFile>,
+}
+
+/// A file left out of the output, and why
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SkippedFile {
+    /// Relative to the analyzed directory
+    pub path: String,
+    pub reason: String,
+}
+
+/// A file deleted since `changed_since`. Only ids that follow from the path
+/// alone are known: those of its module and uses records. Ids of the items it
+/// held need the previous export, see `ExportDiffer`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DeletedFile {
+    /// Relative to the analyzed directory
+    pub path: String,
+    /// Empty when the crate the file belonged to is gone too
+    pub ids: Vec<String>,
 }
 
 pub fn analyze_project(cfg: &AnalyzeConfig) -> Result<Vec<OutputRecord>> {
-    let mut out: Vec<OutputRecord> = Vec::new();
-    for entry in WalkDir::new(&cfg.path).into_iter().filter_map(|e| e.ok()) {
-        let path = entry.path();
-        if path.is_dir() || is_excluded(path) {
-            continue;
+    analyze_project_with_stats(cfg).map(|(records, _)| records)
+}
+
+/// Like `analyze_project`, also reporting how many files came from the cache
+pub fn analyze_project_with_stats(
+    cfg: &AnalyzeConfig,
+) -> Result<(Vec<OutputRecord>, AnalyzeStats)> {
+    let mut out = Vec::new();
+    let stats = analyze_project_streaming(cfg, |record| {
+        out.push(record);
+        Ok(())
+    })?;
+    Ok((out, stats))
+}
+
+/// Hands records to `sink` as soon as their file is analyzed, in the same
+/// order `analyze_project` returns them, so memory stays bounded by a small
+/// batch of files rather than the whole project.
+///
+/// With `cfg.sort`, records are ordered by `path` (byte-wise), then
+/// `start_line`, then `qual_symbol`; chunks follow each other in place of the
+/// record they were split from. Identical sources give identical output on
+/// any machine and with any number of threads. An error from `sink` stops
+/// the analysis. With a cache, every file's records are still kept for the
+/// manifest written at the end.
+pub fn analyze_project_streaming(
+    cfg: &AnalyzeConfig,
+    mut sink: impl FnMut(OutputRecord) -> Result<()>,
+) -> Result<AnalyzeStats> {
+    if let Some(kinds) = &cfg.kinds {
+        let mut unknown: Vec<&String> = kinds
+            .iter()
+            .filter(|k| !RECORD_KINDS.contains(&k.as_str()))
+            .collect();
+        unknown.sort();
+        if let Some(kind) = unknown.first() {
+            anyhow::bail!(
+                "Unknown record kind `{}`, expected one of: {}",
+                kind,
+                RECORD_KINDS.join(", ")
+            );
         }
-        if path.extension().and_then(|e| e.to_str()) != Some("rs") {
-            continue;
+    }
+    if let Some(template) = &cfg.embedding_template {
+        VectorFields::validate_template(template)?;
+    }
+    let previous = match &cfg.cache {
+        Some(path) => RecordCache::load(path)?,
+        None => RecordCache::default(),
+    };
+    let changes = match &cfg.changed_since {
+        Some(since) => Some(git::changed_files(&cfg.path, since)?),
+        None => None,
+    };
+    // Files outside the change set keep their cache entries
+    let mut cache = match &changes {
+        Some(changes) => {
+            let mut cache = previous.clone();
+            for rel in &changes.deleted {
+                cache.remove(&relative_path(&cfg.path, &cfg.path.join(rel)));
+            }
+            cache
+        }
+        None => RecordCache::default(),
+    };
+    let mut stats = AnalyzeStats::default();
+    let commit = match &cfg.commit {
+        Some(commit) => Some(commit.clone()),
+        None if cfg.detect_git => git::head(&cfg.path),
+        None => None,
+    };
+    let selection = FileSelection {
+        respect_ignore: cfg.respect_gitignore,
+        include: Vec::new(),
+        exclude: cfg.exclude_globs.clone(),
+    };
+    let (mut units, is_workspace) = crate_units(&cfg.path)?;
+    if !cfg.members.is_empty() {
+        for member in &cfg.members {
+            if !units.iter().any(|u| &u.name == member) {
+                let names: Vec<&str> = units.iter().map(|u| u.name.as_str()).collect();
+                anyhow::bail!(
+                    "Unknown workspace member `{}`, expected one of: {}",
+                    member,
+                    names.join(", ")
+                );
+            }
         }
-        match process_file(&cfg.path, path, &cfg.repo_id) {
-            Ok(mut v) => out.append(&mut v),
-            Err(e) => {
-                eprintln!("[project_analyzer] Skipping {}: {}", path.display(), e);
+        units.retain(|u| cfg.members.contains(&u.name));
+    }
+    // Files belong to the nearest crate; nested non-member crates are skipped
+    let owner = |file: &Path| -> Option<&CrateUnit> {
+        if !is_workspace {
+            return units.first();
+        }
+        let root = file
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&cfg.path))
+            .find(|dir| dir.join("Cargo.toml").is_file())?;
+        units.iter().find(|u| u.root == root)
+    };
+    let sources = rust_files(&cfg.path, &selection, None)?;
+    for (path, error) in &sources.errors {
+        cfg.skip(&mut stats, relative_path(&cfg.path, path), error.clone());
+    }
+    let mut files: Vec<(&Path, &CrateUnit, String)> = sources
+        .files
+        .iter()
+        // `target/` directories are skipped by the walk itself
+        .filter(|file| !is_generated(file))
+        .filter(|file| {
+            changes.as_ref().is_none_or(|changes| {
+                file.strip_prefix(&cfg.path)
+                    .is_ok_and(|rel| changes.modified.contains(rel))
+            })
+        })
+        .filter_map(|file| Some((file.as_path(), owner(file)?, relative_path(&cfg.path, file))))
+        .filter(|(file, unit, _)| {
+            // Integration tests live in a top-level tests/ directory of the crate
+            cfg.include_tests
+                || file
+                    .strip_prefix(&unit.root)
+                    .ok()
+                    .and_then(|rel| rel.components().next())
+                    .is_none_or(|first| first.as_os_str() != "tests")
+        })
+        .collect();
+    // Same output regardless of thread count or walk order
+    if cfg.sort {
+        files.sort_by(|a, b| a.2.cmp(&b.2));
+    }
+    if changes.is_some() {
+        stats.changed = files
+            .iter()
+            .map(|(_, _, rel_path)| rel_path.clone())
+            .collect();
+    }
+    for rel in changes.iter().flat_map(|changes| &changes.deleted) {
+        let file = cfg.path.join(rel);
+        let path = relative_path(&cfg.path, &file);
+        let ids = match owner(&file) {
+            Some(unit) => {
+                let module_path = rel_module_path(&unit.root, &file);
+                vec![
+                    sha256_id(&cfg.repo_id, &path, &module_path),
+                    sha256_id(&cfg.repo_id, &path, &format!("{}::use", module_path)),
+                ]
+            }
+            None => Vec::new(),
+        };
+        stats.deleted.push(DeletedFile { path, ids });
+    }
+    stats.files = files.len();
+    if let Some(progress) = &cfg.progress {
+        progress.set_length(files.len() as u64);
+    }
+    let pool = match cfg.threads {
+        Some(threads) => Some(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .context("Failed to start analysis threads")?,
+        ),
+        None => None,
+    };
+    let threads = pool
+        .as_ref()
+        .map_or_else(rayon::current_num_threads, |p| p.current_num_threads());
+
+    // Files are independent, so each batch is read, hashed and parsed in
+    // parallel; batches are small so records reach the sink early
+    for batch in files.chunks(threads * 4) {
+        let analyze = || -> Vec<_> {
+            batch
+                .par_iter()
+                .map(|(path, unit, rel_path)| {
+                    let crate_root = relative_path(&cfg.path, &unit.root);
+                    let result =
+                        fs::read_to_string(path)
+                            .context("Failed to read")
+                            .and_then(|content| {
+                                let hash = RecordCache::hash(
+                                    &cfg.repo_id,
+                                    &crate_root,
+                                    &cfg.record_options(),
+                                    &content,
+                                );
+                                match previous.get(rel_path, &hash) {
+                                    Some(records) => Ok((hash, records.to_vec(), true)),
+                                    None => process_file(cfg, &unit.root, path, &content)
+                                        .map(|records| (hash, records, false)),
+                                }
+                            });
+                    (unit, rel_path, result)
+                })
+                .collect()
+        };
+        let results = match &pool {
+            Some(pool) => pool.install(analyze),
+            None => analyze(),
+        };
+
+        for (unit, rel_path, result) in results {
+            if let Some(progress) = &cfg.progress {
+                progress.inc(1);
+            }
+            let (hash, records, cached) = match result {
+                Ok(analyzed) => analyzed,
+                Err(e) => {
+                    cfg.skip(&mut stats, rel_path.clone(), format!("{e:#}"));
+                    continue;
+                }
+            };
+            if cached {
+                stats.cached += 1;
+            } else {
+                stats.analyzed += 1;
+            }
+            if cfg.cache.is_some() {
+                cache.insert(rel_path.clone(), hash, records.clone());
+            }
+            for record in finish_file(cfg, records, &commit, unit) {
+                sink(record)?;
             }
         }
     }
-    Ok(out)
+    if let Some(path) = &cfg.cache {
+        stats.removed = previous.paths().filter(|p| !cache.contains(p)).count();
+        cache.save(path)?;
+    }
+    Ok(stats)
 }
 
-fn process_file(root: &Path, file: &Path, repo_id: &str) -> Result<Vec<OutputRecord>> {
-    let content =
-        fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
-    let parsed: syn::File =
-        syn::parse_file(&content).with_context(|| format!("Failed to parse {}", file.display()))?;
-    let module_path = rel_module_path(root, file);
-    let rel_path = pathdiff::diff_paths(file, root)
+// Per-record post-processing of one file's records, in output order. Ids
+// include the file path, so duplicates can only occur within a file.
+fn finish_file(
+    cfg: &AnalyzeConfig,
+    mut records: Vec<OutputRecord>,
+    commit: &Option<String>,
+    unit: &CrateUnit,
+) -> Vec<OutputRecord> {
+    for r in records.iter_mut() {
+        r.payload.commit = commit.clone();
+        r.payload.crate_name = unit.name.clone();
+    }
+    if cfg.sort {
+        records.sort_by(|a, b| {
+            (a.payload.start_line, &a.payload.qual_symbol)
+                .cmp(&(b.payload.start_line, &b.payload.qual_symbol))
+        });
+    }
+    uniquify_ids(cfg, &mut records);
+    if let Some(max_chars) = cfg.max_chars {
+        records = records
+            .into_iter()
+            .flat_map(|r| chunk_record(r, max_chars, cfg.chunk_overlap))
+            .collect();
+    }
+    if let Some(max_bytes) = cfg.max_text_bytes {
+        for r in records.iter_mut() {
+            if let Some(text) = truncate_text(&r.payload.text, max_bytes) {
+                r.payload.text = text;
+                r.payload.truncated = true;
+            }
+            if let Some(code_body) = truncate_text(&r.vector_fields.code_body, max_bytes) {
+                r.vector_fields.code_body = code_body;
+                r.payload.truncated = true;
+            }
+        }
+    }
+    for r in records.iter_mut() {
+        let fields = &r.vector_fields;
+        r.payload.approx_tokens = estimate_tokens(&fields.signature)
+            + estimate_tokens(&fields.code_body)
+            + estimate_tokens(&fields.doc_comment);
+        r.payload.fingerprint = r.fingerprint();
+        r.payload.content_hash = content_hash(&fields.signature, &fields.code_body);
+        r.embedding_text = cfg.embedding_template.as_ref().map(|t| fields.render(t));
+    }
+    records
+}
+
+// Vector stores upsert by id, so a duplicate would silently replace a record.
+// Later duplicates get a `#n` suffix on their qual_symbol; `out` is sorted, so
+// the numbering is stable across runs.
+fn uniquify_ids(cfg: &AnalyzeConfig, out: &mut [OutputRecord]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for r in out.iter_mut() {
+        let n = seen.entry(r.id.clone()).or_insert(0);
+        *n += 1;
+        if *n > 1 {
+            let qual = format!("{}#{}", r.payload.qual_symbol, n);
+            cfg.warn(&format!(
+                "[project_analyzer] Warning: duplicate id for {} in {}, using {}",
+                r.payload.qual_symbol, r.payload.path, qual
+            ));
+            r.id = sha256_id(&r.payload.repo_id, &r.payload.path, &qual);
+            r.payload.qual_symbol = qual;
+        }
+    }
+}
+
+/// Splits a record whose text exceeds `max_chars` into `::chunk-N` records of
+/// whole lines, each starting with the last `overlap` lines of the previous
+/// one. A single line longer than `max_chars` becomes a chunk of its own.
+fn chunk_record(record: OutputRecord, max_chars: usize, overlap: usize) -> Vec<OutputRecord> {
+    if record.payload.text.chars().count() <= max_chars {
+        return vec![record];
+    }
+    // Split on '\n' alone so chunks stay exact slices of CRLF text
+    let lines: Vec<&str> = record.payload.text.split('\n').collect();
+    let offsets: Vec<usize> = lines
+        .iter()
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len() + 1;
+            Some(start)
+        })
+        .collect();
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let mut end = start;
+        let mut len = 0;
+        while end < lines.len() {
+            let line_len = lines[end].chars().count() + usize::from(end > start);
+            if end > start && len + line_len > max_chars {
+                break;
+            }
+            len += line_len;
+            end += 1;
+        }
+        ranges.push((start, end));
+        if end == lines.len() {
+            break;
+        }
+        // Always advance by at least one line, whatever the overlap
+        start = end.saturating_sub(overlap).max(start + 1);
+    }
+
+    ranges
+        .iter()
+        .enumerate()
+        .map(|(i, &(start, end))| {
+            let from = offsets[start];
+            let to = offsets[end - 1] + lines[end - 1].trim_end_matches('\r').len();
+            let text = record.payload.text[from..to].to_string();
+            let qual = format!("{}::chunk-{}", record.payload.qual_symbol, i + 1);
+            let mut chunk = record.clone();
+            chunk.id = sha256_id(&record.payload.repo_id, &record.payload.path, &qual);
+            chunk.vector_fields.code_body = compact_whitespace(&strip_comments(&text));
+            chunk.payload.qual_symbol = qual;
+            chunk.payload.start_line = record.payload.start_line + start;
+            chunk.payload.end_line = record.payload.start_line + end - 1;
+            chunk.payload.start_byte = record.payload.start_byte + from;
+            chunk.payload.end_byte = record.payload.start_byte + to;
+            chunk.payload.text = text;
+            chunk.payload.parent_id = Some(record.id.clone());
+            chunk
+        })
+        .collect()
+}
+
+fn relative_path(root: &Path, file: &Path) -> String {
+    pathdiff::diff_paths(file, root)
         .unwrap_or_else(|| file.to_path_buf())
         .to_string_lossy()
-        .to_string();
+        .to_string()
+}
+
+/// Records of a single source file, as `analyze_project` produces them for
+/// `root.join(rel_path)` with default options. `content` doesn't have to be on
+/// disk, e.g. an unsaved editor buffer. `root` is the crate root module paths
+/// are derived from; `crate_name` and `commit` are left empty.
+pub fn analyze_source(
+    root: &Path,
+    rel_path: &str,
+    content: &str,
+    repo_id: &str,
+) -> Result<Vec<OutputRecord>> {
+    let cfg = AnalyzeConfig::new(root, repo_id);
+    let unit = CrateUnit {
+        name: String::new(),
+        root: root.to_path_buf(),
+    };
+    let module_path = rel_module_path(root, &root.join(rel_path));
+    let records = process_source(&cfg, &module_path, rel_path, content)?;
+    Ok(finish_file(&cfg, records, &None, &unit))
+}
+
+/// Reads `file` and analyzes it with `analyze_source`, relative to `root`
+pub fn analyze_file(root: &Path, file: &Path, repo_id: &str) -> Result<Vec<OutputRecord>> {
+    let content =
+        fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    analyze_source(root, &relative_path(root, file), &content, repo_id)
+}
+
+// Module paths are relative to `crate_root`, file paths to the analyzed directory
+fn process_file(
+    cfg: &AnalyzeConfig,
+    crate_root: &Path,
+    file: &Path,
+    content: &str,
+) -> Result<Vec<OutputRecord>> {
+    let module_path = rel_module_path(crate_root, file);
+    process_source(cfg, &module_path, &relative_path(&cfg.path, file), content)
+}
+
+fn process_source(
+    cfg: &AnalyzeConfig,
+    module_path: &str,
+    rel_path: &str,
+    content: &str,
+) -> Result<Vec<OutputRecord>> {
+    let parsed: syn::File = syn::parse_file(content).context("Failed to parse")?;
+    let module_path = module_path.to_string();
+    let rel_path = rel_path.to_string();
 
     let mut records = Vec::new();
-    for item in parsed.items.iter() {
+    let module_doc = merge_doc_comments(&parsed.attrs);
+    if !module_doc.is_empty() && cfg.emits("module") {
+        // Text is the //! block itself, from the first inner doc line to the last
+        let spans = parsed
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("doc"))
+            .filter_map(|a| span_start_end(a.span()));
+        let start_line = spans.clone().map(|((line, _), _)| line).min().unwrap_or(1);
+        let end_line = spans.map(|(_, (line, _))| line).max().unwrap_or(start_line);
+        let text = source_lines(content, start_line, end_line);
+        records.push(base_record(
+            cfg,
+            &rel_path,
+            "module",
+            module_path.clone(),
+            (start_line, end_line, text),
+            VectorFields {
+                signature: module_path.clone(),
+                identifiers: String::new(),
+                code_body: String::new(),
+                doc_comment: module_doc,
+            },
+        ));
+    }
+    let uses = cfg
+        .emits("uses")
+        .then(|| uses_record(cfg, content, &parsed, &module_path, &rel_path));
+    if let Some(record) = uses.flatten() {
+        records.push(record);
+    }
+    process_items(
+        cfg,
+        content,
+        &parsed.items,
+        &module_path,
+        &rel_path,
+        &mut records,
+    );
+    // Every text is whole lines of `content`, so its bytes follow from its lines
+    for r in records.iter_mut() {
+        let (start_byte, end_byte) = line_bytes(content, r.payload.start_line, r.payload.end_line);
+        r.payload.start_byte = start_byte;
+        r.payload.end_byte = end_byte;
+    }
+    Ok(records)
+}
+
+/// One "uses" record per file listing the paths imported by its top-level
+/// `use` items in `payload.edges`, with `pub use` re-exports repeated in
+/// `payload.reexports`. `code_body` holds the same paths for text search.
+/// Imports inside inline modules are not included.
+/// `text` holds just the use items, so when other items sit between them it
+/// is not the `start_byte..end_byte` slice, which runs from the first to the
+/// last.
+fn uses_record(
+    cfg: &AnalyzeConfig,
+    content: &str,
+    parsed: &syn::File,
+    module_path: &str,
+    rel_path: &str,
+) -> Option<OutputRecord> {
+    let uses: Vec<&syn::ItemUse> = parsed
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Use(u) if cfg.include_tests || !is_test_code(&u.attrs) => Some(u),
+            _ => None,
+        })
+        .collect();
+    let first = uses.first()?;
+    let mut edges: Vec<String> = Vec::new();
+    let mut reexports: Vec<String> = Vec::new();
+    for u in &uses {
+        let reexported = !matches!(u.vis, syn::Visibility::Inherited);
+        for path in flatten_use_paths(u) {
+            if reexported && !reexports.contains(&path) {
+                reexports.push(path.clone());
+            }
+            if !edges.contains(&path) {
+                edges.push(path);
+            }
+        }
+    }
+    // Only the use items themselves: runs of them separated by blank lines
+    // stay as written, other items in between are left out
+    let lines: Vec<&str> = content.lines().collect();
+    let mut blocks: Vec<(usize, usize)> = Vec::new();
+    for u in &uses {
+        let (start, end) = (u.span().start().line, u.span().end().line);
+        match blocks.last_mut() {
+            Some((_, last_end))
+                if start <= *last_end + 1
+                    || lines[*last_end..start - 1]
+                        .iter()
+                        .all(|line| line.trim().is_empty()) =>
+            {
+                *last_end = end.max(*last_end);
+            }
+            _ => blocks.push((start, end)),
+        }
+    }
+    let start_line = first.span().start().line;
+    let end_line = blocks.last().map_or(start_line, |&(_, end)| end);
+    let text = blocks
+        .iter()
+        .map(|&(start, end)| source_lines(content, start, end))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let qual = format!("{}::use", module_path);
+    let mut record = base_record(
+        cfg,
+        rel_path,
+        "uses",
+        qual,
+        (start_line, end_line, text),
+        VectorFields {
+            signature: format!("uses {}", module_path),
+            identifiers: collect_idents(&uses.iter().map(|u| u.to_token_stream()).collect()),
+            code_body: edges.join(" "),
+            doc_comment: String::new(),
+        },
+    );
+    record.payload.edges = edges;
+    record.payload.reexports = reexports;
+    Some(record)
+}
+
+// Record of `kind` with every payload field a kind may set left empty; each
+// kind fills in its own. Bytes, commit and hashes are set after the whole
+// file is processed.
+fn base_record(
+    cfg: &AnalyzeConfig,
+    rel_path: &str,
+    kind: &str,
+    qual: String,
+    (start_line, end_line, text): (usize, usize, String),
+    vector_fields: VectorFields,
+) -> OutputRecord {
+    OutputRecord {
+        schema_version: SCHEMA_VERSION,
+        id: sha256_id(&cfg.repo_id, rel_path, &qual),
+        vector_fields,
+        embedding_text: None,
+        payload: OutputPayload {
+            repo_id: cfg.repo_id.clone(),
+            crate_name: String::new(),
+            path: rel_path.to_string(),
+            kind: kind.to_string(),
+            qual_symbol: qual,
+            start_line,
+            end_line,
+            start_byte: 0,
+            end_byte: 0,
+            text,
+            language: LANGUAGE.to_string(),
+            analyzer_version: ANALYZER_VERSION.to_string(),
+            commit: None,
+            approx_tokens: 0,
+            edges: Vec::new(),
+            reexports: Vec::new(),
+            references: Vec::new(),
+            parent_id: None,
+            visibility: String::new(),
+            attributes: Vec::new(),
+            truncated: false,
+            fingerprint: String::new(),
+            content_hash: String::new(),
+            target: String::new(),
+            receiver: None,
+        },
+    }
+}
+
+// Walks items at one module level, descending into inline `mod name { ... }` blocks
+fn process_items(
+    cfg: &AnalyzeConfig,
+    content: &str,
+    items: &[syn::Item],
+    module_path: &str,
+    rel_path: &str,
+    records: &mut Vec<OutputRecord>,
+) {
+    let include_tests = cfg.include_tests;
+    // Inherent impls seen per self type, to number the second and later ones
+    let mut inherent_impls: HashMap<String, usize> = HashMap::new();
+    for item in items.iter() {
+        if !include_tests && is_test_code(item_attrs(item)) {
+            continue;
+        }
         match item {
-            syn::Item::Struct(s) => {
+            syn::Item::Struct(s) if cfg.emits("struct") => {
                 let qual = format!("{}::{}", module_path, s.ident);
-                let (start, end, text) = locate_item_text(&content, &s.ident.to_string(), "struct");
+                let (start_line, end_line, text) = item_text(content, s.span());
                 let doc = merge_doc_comments(&s.attrs);
                 let signature = format_struct_signature(s);
                 let identifiers = collect_idents(&s.to_token_stream());
                 let code_body = compact_whitespace(&strip_comments(&text));
-                let id = sha256_id(repo_id, &rel_path, &qual);
-                records.push(OutputRecord {
-                    id,
-                    vector_fields: VectorFields {
+                let mut record = base_record(
+                    cfg,
+                    rel_path,
+                    "struct",
+                    qual,
+                    (start_line, end_line, text),
+                    VectorFields {
                         signature,
                         identifiers,
                         code_body,
                         doc_comment: doc,
                     },
-                    payload: OutputPayload {
-                        repo_id: repo_id.to_string(),
-                        path: rel_path.clone(),
-                        kind: "struct".to_string(),
-                        qual_symbol: qual,
-                        start_line: start,
-                        end_line: end,
-                        text,
-                    },
-                });
+                );
+                record.payload.visibility = format_visibility(&s.vis);
+                record.payload.attributes = attribute_texts(content, &s.attrs);
+                records.push(record);
             }
-            syn::Item::Impl(im) => {
-                // Impl block
-                let ty = im.self_ty.to_token_stream().to_string();
-                let qual = format!("{}::{}", module_path, ty);
-                let (_, _, text) = locate_item_text(&content, &ty, "impl");
-                let doc = merge_doc_comments(&im.attrs);
-                let signature = format_impl_signature(im);
-                let identifiers = collect_idents(&im.to_token_stream());
+            syn::Item::Enum(e) if cfg.emits("enum") => {
+                let qual = format!("{}::{}", module_path, e.ident);
+                let (start_line, end_line, text) = item_text(content, e.span());
+                let doc = merge_doc_comments(&e.attrs);
+                let signature = format_enum_signature(e);
+                let identifiers = collect_idents(&e.to_token_stream());
                 let code_body = compact_whitespace(&strip_comments(&text));
-                let id = sha256_id(repo_id, &rel_path, &qual);
-                // Line numbers best-effort: use span if available
-                let start_line = im.span().start().line as usize;
-                let end_line = im.span().end().line as usize;
-                records.push(OutputRecord {
-                    id,
-                    vector_fields: VectorFields {
+                let mut record = base_record(
+                    cfg,
+                    rel_path,
+                    "enum",
+                    qual,
+                    (start_line, end_line, text),
+                    VectorFields {
                         signature,
                         identifiers,
                         code_body,
                         doc_comment: doc,
                     },
-                    payload: OutputPayload {
-                        repo_id: repo_id.to_string(),
-                        path: rel_path.clone(),
-                        kind: "impl".to_string(),
-                        qual_symbol: qual.clone(),
-                        start_line,
-                        end_line,
-                        text,
-                    },
-                });
+                );
+                record.payload.visibility = format_visibility(&e.vis);
+                record.payload.attributes = attribute_texts(content, &e.attrs);
+                records.push(record);
+            }
+            syn::Item::Impl(im) => {
+                // Impl block; trait impls are told apart by their trait
+                let ty = normalize_type_name(&im.self_ty);
+                let qual = match &im.trait_ {
+                    Some((_, path, _)) => {
+                        let trait_path = normalize_type_name(path);
+                        format!("{}::{}#impl-{}", module_path, ty, trait_path)
+                    }
+                    None => {
+                        let n = inherent_impls.entry(ty.clone()).or_insert(0);
+                        *n += 1;
+                        // Never the bare type path, which is the struct's own record
+                        if *n == 1 {
+                            format!("{}::{}#impl", module_path, ty)
+                        } else {
+                            format!("{}::{}#impl-{}", module_path, ty, n)
+                        }
+                    }
+                };
+                if cfg.emits("impl") {
+                    let (start_line, end_line, text) = item_text(content, im.span());
+                    let doc = merge_doc_comments(&im.attrs);
+                    let signature = format_impl_signature(im);
+                    let identifiers = collect_idents(&im.to_token_stream());
+                    let code_body = compact_whitespace(&strip_comments(&text));
+                    records.push(base_record(
+                        cfg,
+                        rel_path,
+                        "impl",
+                        qual.clone(),
+                        (start_line, end_line, text),
+                        VectorFields {
+                            signature,
+                            identifiers,
+                            code_body,
+                            doc_comment: doc,
+                        },
+                    ));
+                }
 
                 // Methods inside impl
                 for it in im.items.iter() {
                     if let syn::ImplItem::Fn(m) = it {
+                        if !cfg.emits("fn") || (!include_tests && is_test_code(&m.attrs)) {
+                            continue;
+                        }
                         let m_name = m.sig.ident.to_string();
-                        let qual_m = format!("{}::{}::{}", module_path, ty, m_name);
-                        let signature = m.sig.to_token_stream().to_string();
-                        let identifiers = collect_idents(&m.to_token_stream());
-                        let doc = merge_doc_comments(&m.attrs);
-                        let text = m.to_token_stream().to_string();
-                        let code_body = if let Some(block) = &m.block.stmts.first() {
-                            compact_whitespace(&strip_comments(
-                                &m.block.to_token_stream().to_string(),
-                            ))
+                        // Trait methods hang off their impl so `fmt` of Display and Debug differ
+                        let qual_m = if im.trait_.is_some() {
+                            format!("{}::{}", qual, m_name)
                         } else {
-                            String::new()
+                            format!("{}::{}::{}", module_path, ty, m_name)
                         };
-                        let id = sha256_id(repo_id, &rel_path, &qual_m);
-                        let start_line = m.span().start().line as usize;
-                        let end_line = m.span().end().line as usize;
-                        records.push(OutputRecord {
-                            id,
-                            vector_fields: VectorFields {
+                        let signature = format_method_signature(&m.sig);
+                        let identifiers = collect_idents(&m.to_token_stream());
+                        let doc = merge_doc_comments(&m.attrs);
+                        let (start_line, end_line, text) = item_text(content, m.span());
+                        // Like free functions: the block as written, braces included
+                        let code_body = compact_whitespace(&strip_comments(&span_source(
+                            content,
+                            m.block.span(),
+                        )));
+                        let mut record = base_record(
+                            cfg,
+                            rel_path,
+                            "fn",
+                            qual_m,
+                            (start_line, end_line, text),
+                            VectorFields {
                                 signature,
                                 identifiers,
                                 code_body,
                                 doc_comment: doc,
                             },
-                            payload: OutputPayload {
-                                repo_id: repo_id.to_string(),
-                                path: rel_path.clone(),
-                                kind: "fn".to_string(),
-                                qual_symbol: qual_m,
-                                start_line,
-                                end_line,
-                                text,
+                        );
+                        record.payload.references = collect_references(&m.block);
+                        // Trait impl methods take the trait's visibility
+                        record.payload.visibility = match im.trait_ {
+                            Some(_) => String::new(),
+                            None => format_visibility(&m.vis),
+                        };
+                        record.payload.attributes = attribute_texts(content, &m.attrs);
+                        record.payload.receiver = format_receiver(&m.sig);
+                        records.push(record);
+                    }
+                }
+            }
+            syn::Item::Trait(t) => {
+                let name = t.ident.to_string();
+                let qual = format!("{}::{}", module_path, name);
+                if cfg.emits("trait") {
+                    let (start_line, end_line, text) = item_text(content, t.span());
+                    let doc = merge_doc_comments(&t.attrs);
+                    let signature = format_trait_signature(t);
+                    // Associated types and consts are covered by the token stream
+                    let identifiers = collect_idents(&t.to_token_stream());
+                    let code_body = compact_whitespace(&strip_comments(&text));
+                    records.push(base_record(
+                        cfg,
+                        rel_path,
+                        "trait",
+                        qual,
+                        (start_line, end_line, text),
+                        VectorFields {
+                            signature,
+                            identifiers,
+                            code_body,
+                            doc_comment: doc,
+                        },
+                    ));
+                }
+
+                // Methods declared in the trait, with or without a default body
+                for it in t.items.iter() {
+                    if let syn::TraitItem::Fn(m) = it {
+                        if !cfg.emits("fn") || (!include_tests && is_test_code(&m.attrs)) {
+                            continue;
+                        }
+                        let qual_m = format!("{}::{}::{}", module_path, name, m.sig.ident);
+                        let signature = format_method_signature(&m.sig);
+                        let identifiers = collect_idents(&m.to_token_stream());
+                        let doc = merge_doc_comments(&m.attrs);
+                        let (start_line, end_line, text) = item_text(content, m.span());
+                        let code_body = match &m.default {
+                            Some(block) if !block.stmts.is_empty() => compact_whitespace(
+                                &strip_comments(&span_source(content, block.span())),
+                            ),
+                            _ => String::new(),
+                        };
+                        let mut record = base_record(
+                            cfg,
+                            rel_path,
+                            "fn",
+                            qual_m,
+                            (start_line, end_line, text),
+                            VectorFields {
+                                signature,
+                                identifiers,
+                                code_body,
+                                doc_comment: doc,
                             },
-                        });
+                        );
+                        record.payload.references = m
+                            .default
+                            .as_ref()
+                            .map(collect_references)
+                            .unwrap_or_default();
+                        record.payload.receiver = format_receiver(&m.sig);
+                        records.push(record);
                     }
                 }
             }
-            syn::Item::Fn(f) => {
+            syn::Item::Const(_) | syn::Item::Static(_) | syn::Item::Type(_) => {
+                let (kind, ident, attrs, signature, code_body) = match item {
+                    syn::Item::Const(c) => (
+                        "const",
+                        &c.ident,
+                        &c.attrs,
+                        format_const_signature(c),
+                        c.expr.to_token_stream().to_string(),
+                    ),
+                    syn::Item::Static(st) => (
+                        "static",
+                        &st.ident,
+                        &st.attrs,
+                        format_static_signature(st),
+                        st.expr.to_token_stream().to_string(),
+                    ),
+                    syn::Item::Type(ty) => (
+                        "type_alias",
+                        &ty.ident,
+                        &ty.attrs,
+                        format_type_alias_signature(ty),
+                        ty.ty.to_token_stream().to_string(),
+                    ),
+                    _ => unreachable!(),
+                };
+                if !cfg.emits(kind) {
+                    continue;
+                }
+                let qual = format!("{}::{}", module_path, ident);
+                let identifiers = collect_idents(&item.to_token_stream());
+                let doc = merge_doc_comments(attrs);
+                let (start_line, end_line, text) = item_text(content, item.span());
+                let code_body = compact_whitespace(&strip_comments(&code_body));
+                records.push(base_record(
+                    cfg,
+                    rel_path,
+                    kind,
+                    qual,
+                    (start_line, end_line, text),
+                    VectorFields {
+                        signature,
+                        identifiers,
+                        code_body,
+                        doc_comment: doc,
+                    },
+                ));
+            }
+            syn::Item::Macro(m) if cfg.emits("macro") => {
+                // Only macro_rules! definitions; invocations at item level are skipped
+                let (Some(name), Some(signature)) = (&m.ident, format_macro_signature(m)) else {
+                    continue;
+                };
+                let qual = format!("{}::{}!", module_path, name);
+                let doc = merge_doc_comments(&m.attrs);
+                // Rule matchers are left raw in the token text
+                let identifiers = collect_idents(&m.mac.tokens);
+                let (start_line, end_line, text) = item_text(content, m.span());
+                let code_body = compact_whitespace(&strip_comments(&m.mac.tokens.to_string()));
+                records.push(base_record(
+                    cfg,
+                    rel_path,
+                    "macro",
+                    qual,
+                    (start_line, end_line, text),
+                    VectorFields {
+                        signature,
+                        identifiers,
+                        code_body,
+                        doc_comment: doc,
+                    },
+                ));
+            }
+            syn::Item::Fn(f) if cfg.emits("fn") => {
                 let qual = format!("{}::{}", module_path, f.sig.ident);
                 let signature = format_fn_signature(f);
                 let identifiers = collect_idents(&f.to_token_stream());
                 let doc = merge_doc_comments(&f.attrs);
-                let text = f.to_token_stream().to_string();
-                let code_body = match &f.block {
-                    b => compact_whitespace(&strip_comments(&b.to_token_stream().to_string())),
-                };
-                let id = sha256_id(repo_id, &rel_path, &qual);
-                let start_line = f.span().start().line as usize;
-                let end_line = f.span().end().line as usize;
-                records.push(OutputRecord {
-                    id,
-                    vector_fields: VectorFields {
+                let (start_line, end_line, text) = item_text(content, f.span());
+                let code_body =
+                    compact_whitespace(&strip_comments(&span_source(content, f.block.span())));
+                let mut record = base_record(
+                    cfg,
+                    rel_path,
+                    "fn",
+                    qual,
+                    (start_line, end_line, text),
+                    VectorFields {
                         signature,
                         identifiers,
                         code_body,
                         doc_comment: doc,
                     },
-                    payload: OutputPayload {
-                        repo_id: repo_id.to_string(),
-                        path: rel_path.clone(),
-                        kind: "fn".to_string(),
-                        qual_symbol: qual,
-                        start_line,
-                        end_line,
-                        text,
-                    },
-                });
+                );
+                record.payload.references = collect_references(&f.block);
+                record.payload.visibility = format_visibility(&f.vis);
+                record.payload.attributes = attribute_texts(content, &f.attrs);
+                records.push(record);
+            }
+            // One record per re-exported name, found at its new location
+            syn::Item::Use(u)
+                if cfg.emits("reexport") && !matches!(u.vis, syn::Visibility::Inherited) =>
+            {
+                let visibility = format_visibility(&u.vis);
+                let (start_line, end_line, text) = item_text(content, u.span());
+                let doc = merge_doc_comments(&u.attrs);
+                let identifiers = collect_idents(&u.to_token_stream());
+                for (target, name) in use_bindings(u) {
+                    let qual = format!("{}::{}", module_path, name);
+                    let rename = match target.rsplit("::").next() {
+                        Some(last) if last != name => format!(" as {}", name),
+                        _ => String::new(),
+                    };
+                    let signature = format!("{} use {}{}", visibility, target, rename);
+                    let mut record = base_record(
+                        cfg,
+                        rel_path,
+                        "reexport",
+                        qual,
+                        (start_line, end_line, text.clone()),
+                        VectorFields {
+                            signature,
+                            identifiers: identifiers.clone(),
+                            code_body: target.clone(),
+                            doc_comment: doc.clone(),
+                        },
+                    );
+                    record.payload.visibility = visibility.clone();
+                    record.payload.target = target;
+                    records.push(record);
+                }
+            }
+            syn::Item::Mod(m) => {
+                if let Some((_, nested)) = &m.content {
+                    let nested_path = format!("{}::{}", module_path, m.ident);
+                    process_items(cfg, content, nested, &nested_path, rel_path, records);
+                }
             }
             _ => {}
         }
     }
-    Ok(records)
 }
 
-// Best-effort fallback to get raw-ish text and line numbers using simple search
-fn locate_item_text(content: &str, ident: &str, keyword: &str) -> (usize, usize, String) {
-    let mut start_line = 1usize;
-    let mut end_line = 1usize;
-    let mut text = String::new();
-    let needle = format!("{} {}", keyword, ident);
-    if let Some(pos) = content.find(&needle) {
-        start_line = content[..pos].matches('\n').count() + 1;
-        // Find end: either ';' or balanced braces { ... }
-        let rest = &content[pos..];
-        if let Some(sc) = rest.find(';') {
-            // assume ends at semicolon when before a '{'
-            let brace_pos = rest.find('{');
-            if brace_pos.is_none() || sc < brace_pos.unwrap() {
-                let frag = &rest[..=sc];
-                text = frag.to_string();
-                end_line = start_line + frag.matches('\n').count();
-                return (start_line, end_line, text);
-            }
-        }
-        // Brace matching
-        if let Some(b) = rest.find('{') {
-            let mut depth = 0i32;
-            let mut i = b;
-            let bytes = rest.as_bytes();
-            while i < rest.len() {
-                match bytes[i] as char {
-                    '{' => depth += 1,
-                    '}' => {
-                        depth -= 1;
-                        if depth == 0 {
-                            let frag = &rest[..=i];
-                            text = frag.to_string();
-                            end_line = start_line + frag.matches('\n').count();
-                            break;
-                        }
-                    }
-                    _ => {}
-                }
-                i += 1;
-            }
+fn item_attrs(item: &syn::Item) -> &[syn::Attribute] {
+    match item {
+        syn::Item::Const(i) => &i.attrs,
+        syn::Item::Enum(i) => &i.attrs,
+        syn::Item::Fn(i) => &i.attrs,
+        syn::Item::Impl(i) => &i.attrs,
+        syn::Item::Macro(i) => &i.attrs,
+        syn::Item::Mod(i) => &i.attrs,
+        syn::Item::Static(i) => &i.attrs,
+        syn::Item::Struct(i) => &i.attrs,
+        syn::Item::Trait(i) => &i.attrs,
+        syn::Item::Type(i) => &i.attrs,
+        syn::Item::Use(i) => &i.attrs,
+        _ => &[],
+    }
+}
+
+// Original source lines covered by an item's span, including its attributes
+fn item_text(content: &str, span: proc_macro2::Span) -> (usize, usize, String) {
+    let Some(((start_line, _), (end_line, _))) = span_start_end(span) else {
+        return (1, 1, String::new());
+    };
+    (
+        start_line,
+        end_line,
+        source_lines(content, start_line, end_line),
+    )
+}
+
+// Lines `start..=end` (1-based) of the original file, as written
+fn source_lines(content: &str, start: usize, end: usize) -> String {
+    let (start_byte, end_byte) = line_bytes(content, start, end);
+    content[start_byte..end_byte].to_string()
+}
+
+// Byte range of lines `start..=end` (1-based), up to but excluding the line
+// break ending the last one; `\r\n` inside the range is kept
+fn line_bytes(content: &str, start: usize, end: usize) -> (usize, usize) {
+    let mut offset = 0;
+    let mut start_byte = content.len();
+    for (i, line) in content.split_inclusive('\n').enumerate() {
+        if i + 1 == start {
+            start_byte = offset;
+        }
+        if i + 1 == end {
+            let line = line.strip_suffix('\n').unwrap_or(line);
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            return (start_byte, offset + line.len());
         }
+        offset += line.len();
     }
-    (start_line, end_line, text)
+    (start_byte.min(content.len()), content.len())
 }
 
+// Non-doc attributes as written, each on one line
+fn attribute_texts(content: &str, attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|a| !a.path().is_ident("doc"))
+        .map(|a| compact_whitespace(&span_source(content, a.span())))
+        .collect()
+}
+
+// Exact source covered by a span, down to the column
+fn span_source(content: &str, span: proc_macro2::Span) -> String {
+    let Some(((start_line, start_col), (end_line, end_col))) = span_start_end(span) else {
+        return String::new();
+    };
+    let lines: Vec<&str> = content
+        .lines()
+        .skip(start_line - 1)
+        .take(end_line + 1 - start_line)
+        .collect();
+    let last = lines.len().saturating_sub(1);
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            // Span columns count chars, not bytes
+            let end = if i == last { end_col } else { usize::MAX };
+            let start = if i == 0 { start_col } else { 0 };
+            line.chars().take(end).skip(start).collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One JSON object per line, each terminated by a newline
 pub fn write_ndjson(records: &[OutputRecord], out: &mut dyn Write) -> Result<()> {
+    let mut buf = BufWriter::new(out);
+    for r in records {
+        serde_json::to_writer(&mut buf, r)?;
+        buf.write_all(b"\n")?;
+    }
+    buf.flush()?;
+    Ok(())
+}
+
+// First two bytes of every gzip member
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// All records as a single JSON array on one line
+pub fn write_json_array(records: &[OutputRecord], out: &mut dyn Write) -> Result<()> {
     let mut buf = BufWriter::new(out);
     buf.write_all(b"[")?;
     let mut first = true;
@@ -250,3 +1244,31 @@ pub fn write_ndjson(records: &[OutputRecord], out: &mut dyn Write) -> Result<()>
     buf.flush()?;
     Ok(())
 }
+
+/// Parses records written by `write_ndjson`, or the legacy single-array format,
+/// either of them optionally gzipped
+pub fn read_ndjson(reader: &mut dyn Read) -> Result<Vec<OutputRecord>> {
+    let mut raw = Vec::new();
+    reader
+        .read_to_end(&mut raw)
+        .context("Failed to read records")?;
+    let mut input = String::new();
+    if raw.starts_with(&GZIP_MAGIC) {
+        MultiGzDecoder::new(raw.as_slice())
+            .read_to_string(&mut input)
+            .context("Failed to decompress records")?;
+    } else {
+        input = String::from_utf8(raw).context("Records are not valid UTF-8")?;
+    }
+    if input.trim_start().starts_with('[') {
+        return serde_json::from_str(&input).context("Invalid JSON array of records");
+    }
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            serde_json::from_str(line).with_context(|| format!("Invalid record on line {}", i + 1))
+        })
+        .collect()
+}
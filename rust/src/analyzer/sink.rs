@@ -0,0 +1,190 @@
+use crate::analyzer::model::OutputRecord;
+use anyhow::{anyhow, Context, Result};
+use indicatif::ProgressBar;
+use reqwest::blocking::Client;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use std::thread;
+use std::time::Duration;
+
+/// Named vectors of the collection, one per `VectorFields` entry, as
+/// `indexer/qdrant_utils.py` creates them
+pub const VECTOR_NAMES: [&str; 4] = ["signature", "identifiers", "code_body", "doc_comment"];
+
+/// Longest wait between two attempts at a batch
+pub const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Upserts records into a Qdrant collection over its REST API, `batch_size`
+/// points per request. Points carry the same payload as the Python indexer:
+/// `vector_fields` and `meta` (the record payload), plus the record id.
+pub struct QdrantSink {
+    pub base_url: String,
+    pub collection: String,
+    /// Sent as the `api-key` header
+    pub api_key: Option<String>,
+    pub batch_size: usize,
+    /// Send an empty vector per name; off, points have no `vector` and the
+    /// collection is expected to embed server-side
+    pub placeholder_vectors: bool,
+    /// Extra attempts for a batch after a 429, a 5xx or a connection error
+    pub max_retries: usize,
+    /// Wait before the first retry, doubled for each one after up to
+    /// `MAX_RETRY_DELAY`
+    pub retry_delay: Duration,
+    /// Bar to keep warnings from drawing over
+    pub progress: Option<ProgressBar>,
+    client: Client,
+    pending: Vec<Value>,
+    stats: SinkStats,
+}
+
+/// Points upserted and points in batches that failed after every retry
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SinkStats {
+    pub upserted: usize,
+    pub failed: usize,
+}
+
+impl QdrantSink {
+    pub fn new(base_url: &str, collection: &str) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()
+            .context("Failed to build HTTP client")?;
+        Ok(QdrantSink {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            collection: collection.to_string(),
+            api_key: None,
+            batch_size: 256,
+            placeholder_vectors: false,
+            max_retries: 3,
+            retry_delay: Duration::from_millis(500),
+            progress: None,
+            client,
+            pending: Vec::new(),
+            stats: SinkStats::default(),
+        })
+    }
+
+    /// Queues a record, sending the batch once it is full
+    pub fn push(&mut self, record: &OutputRecord) {
+        let point = self.point(record);
+        self.pending.push(point);
+        if self.pending.len() >= self.batch_size.max(1) {
+            self.flush();
+        }
+    }
+
+    /// Sends whatever is queued. A batch that still fails after the retries
+    /// is counted in `failed` and dropped, so one bad batch doesn't stop the
+    /// export.
+    pub fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let points = std::mem::take(&mut self.pending);
+        let count = points.len();
+        match self.upsert(&json!({ "points": points })) {
+            Ok(()) => self.stats.upserted += count,
+            Err(e) => {
+                let message =
+                    format!("[project_analyzer] Warning: dropped {count} points for Qdrant: {e:#}");
+                match &self.progress {
+                    Some(progress) => progress.suspend(|| eprintln!("{message}")),
+                    None => eprintln!("{message}"),
+                }
+                self.stats.failed += count;
+            }
+        }
+    }
+
+    /// Sends the last partial batch and returns the final counts
+    pub fn finish(mut self) -> SinkStats {
+        self.flush();
+        self.stats
+    }
+
+    fn point(&self, record: &OutputRecord) -> Value {
+        let mut point = json!({
+            "id": point_id(&record.id),
+            "payload": {
+                "record_id": record.id,
+                "schema_version": record.schema_version,
+                "vector_fields": record.vector_fields,
+                "meta": record.payload,
+            },
+        });
+        if self.placeholder_vectors {
+            let vectors: serde_json::Map<String, Value> = VECTOR_NAMES
+                .iter()
+                .map(|name| (name.to_string(), json!([])))
+                .collect();
+            point["vector"] = Value::Object(vectors);
+        }
+        point
+    }
+
+    fn upsert(&self, body: &Value) -> Result<()> {
+        let url = format!(
+            "{}/collections/{}/points?wait=true",
+            self.base_url, self.collection
+        );
+        let mut attempt = 0;
+        loop {
+            let mut request = self.client.put(&url).json(body);
+            if let Some(key) = &self.api_key {
+                request = request.header("api-key", key);
+            }
+            let error = match request.send() {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let text = response.text().unwrap_or_default();
+                    let error = anyhow!("{} returned {}: {}", url, status, text.trim());
+                    if !retryable(status) {
+                        return Err(error);
+                    }
+                    error
+                }
+                Err(e) => anyhow!(e).context(format!("Failed to reach {}", url)),
+            };
+            if attempt >= self.max_retries {
+                return Err(error);
+            }
+            thread::sleep(backoff(self.retry_delay, attempt));
+            attempt += 1;
+        }
+    }
+}
+
+// `delay` doubled `attempt` times, capped at `MAX_RETRY_DELAY`
+fn backoff(delay: Duration, attempt: usize) -> Duration {
+    let factor = u32::try_from(attempt)
+        .ok()
+        .and_then(|attempt| 2u32.checked_pow(attempt))
+        .unwrap_or(u32::MAX);
+    delay.saturating_mul(factor).min(MAX_RETRY_DELAY)
+}
+
+fn retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Qdrant ids are integers or UUIDs; the record id is a sha256 in hex, so its
+/// first 128 bits are written as a UUID
+pub fn point_id(record_id: &str) -> String {
+    let hex: String = record_id
+        .chars()
+        .filter(char::is_ascii_hexdigit)
+        .chain(std::iter::repeat('0'))
+        .take(32)
+        .collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
@@ -1,57 +1,125 @@
+use crate::complexity_analyzer::walk::path_parts;
 use proc_macro2::Span;
 use quote::ToTokens;
 use regex::Regex;
 use sha2::{Digest, Sha256};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use syn::visit::Visit;
-use syn::{Attribute, Ident, ItemFn, ItemImpl, ItemStruct};
+use syn::{
+    Attribute, Expr, ExprLit, ItemConst, ItemEnum, ItemFn, ItemImpl, ItemMacro, ItemStatic,
+    ItemStruct, ItemTrait, ItemType, ItemUse, Lit, Meta, Signature, UseTree, Visibility,
+};
+
+/// Hash of a fragment's code alone, without repo or path, so identical code
+/// in forks or copies hashes the same. `code_body` is compacted again so it
+/// doesn't matter how it was normalized.
+pub fn content_hash(signature: &str, code_body: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(compact_whitespace(code_body).as_bytes());
+    hasher.update([0x1f]);
+    hasher.update(signature.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
 
 pub fn sha256_id(repo_id: &str, rel_path: &str, qual_symbol: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(repo_id.as_bytes());
-    hasher.update(&[0x1f]);
+    hasher.update([0x1f]);
     hasher.update(rel_path.as_bytes());
-    hasher.update(&[0x1f]);
+    hasher.update([0x1f]);
     hasher.update(qual_symbol.as_bytes());
     let digest = hasher.finalize();
     format!("{:x}", digest)
 }
 
+/// Joins `///`, `/** */` and `#[doc = "..."]` attributes, one trimmed line each.
 pub fn merge_doc_comments(attrs: &[Attribute]) -> String {
-    let mut out = String::new();
+    let mut lines: Vec<String> = Vec::new();
     for attr in attrs {
-        let mut added = false;
-        // Try structured parsing
-        let _ = attr.parse_nested_meta(|meta| {
-            if meta.path.is_ident("doc") {
-                let lit: syn::LitStr = meta.value()?.parse()?;
-                if !out.is_empty() {
-                    out.push('\n');
-                }
-                out.push_str(lit.value().trim());
-                added = true;
-            }
-            Ok(())
-        });
-        // Fallback regex on token stream for #[doc = "..."]
-        if !added && attr.path().is_ident("doc") {
-            let ts = attr.to_token_stream().to_string();
-            let re = Regex::new("doc\\s*=\\s*\"([^\"]*)\"").unwrap();
-            if let Some(c) = re.captures(&ts) {
-                if !out.is_empty() {
-                    out.push('\n');
-                }
-                out.push_str(c.get(1).unwrap().as_str());
+        let Meta::NameValue(nv) = &attr.meta else {
+            continue;
+        };
+        if !nv.path.is_ident("doc") {
+            continue;
+        }
+        if let Expr::Lit(ExprLit {
+            lit: Lit::Str(s), ..
+        }) = &nv.value
+        {
+            let value = s.value();
+            let value = value.trim_matches('\n');
+            lines.extend(value.lines().map(|l| l.trim().to_string()));
+        }
+    }
+    lines.join("\n").trim().to_string()
+}
+
+/// True for items marked `#[doc(hidden)]`.
+pub fn is_doc_hidden(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        let mut hidden = false;
+        if let Meta::List(_) = &attr.meta {
+            if attr.path().is_ident("doc") {
+                let _ = attr.parse_nested_meta(|meta| {
+                    hidden |= meta.path.is_ident("hidden");
+                    Ok(())
+                });
             }
         }
+        hidden
+    })
+}
+
+/// `pub`, `pub(crate)`, `pub(super)`, `pub(in path)`, or `private` when no
+/// visibility is written
+pub fn format_visibility(vis: &Visibility) -> String {
+    match vis {
+        Visibility::Public(_) => "pub".to_string(),
+        Visibility::Restricted(r) if r.in_token.is_some() => {
+            format!("pub(in {})", path_to_string(&r.path))
+        }
+        Visibility::Restricted(r) => format!("pub({})", path_to_string(&r.path)),
+        Visibility::Inherited => "private".to_string(),
     }
-    out
 }
 
 pub fn format_struct_signature(item: &ItemStruct) -> String {
     item.to_token_stream().to_string()
 }
 
+pub fn format_enum_signature(item: &ItemEnum) -> String {
+    // Header plus variants (with fields and discriminants), attributes dropped
+    let mut tokens = String::new();
+    let vis = item.vis.to_token_stream().to_string();
+    if !vis.is_empty() {
+        tokens.push_str(&vis);
+        tokens.push(' ');
+    }
+    tokens.push_str("enum ");
+    tokens.push_str(&item.ident.to_string());
+    tokens.push_str(&item.generics.to_token_stream().to_string());
+    if let Some(g) = &item.generics.where_clause {
+        tokens.push(' ');
+        tokens.push_str(&g.to_token_stream().to_string());
+    }
+    let variants: Vec<String> = item
+        .variants
+        .iter()
+        .map(|v| {
+            let mut variant = v.clone();
+            variant.attrs.clear();
+            for field in variant.fields.iter_mut() {
+                field.attrs.clear();
+            }
+            variant.to_token_stream().to_string()
+        })
+        .collect();
+    tokens.push_str(" { ");
+    tokens.push_str(&variants.join(", "));
+    tokens.push_str(" }");
+    tokens
+}
+
 pub fn format_impl_signature(item: &ItemImpl) -> String {
     // Only the "impl ... for ... where ..." header
     let mut tokens = String::from("impl ");
@@ -72,38 +140,430 @@ pub fn format_impl_signature(item: &ItemImpl) -> String {
     tokens
 }
 
+pub fn format_trait_signature(item: &ItemTrait) -> String {
+    // Only the "trait Name<T>: Bounds where ..." header
+    let mut tokens = String::new();
+    if item.unsafety.is_some() {
+        tokens.push_str("unsafe ");
+    }
+    if item.auto_token.is_some() {
+        tokens.push_str("auto ");
+    }
+    tokens.push_str("trait ");
+    tokens.push_str(&item.ident.to_string());
+    tokens.push_str(&item.generics.to_token_stream().to_string());
+    if !item.supertraits.is_empty() {
+        tokens.push_str(": ");
+        tokens.push_str(&item.supertraits.to_token_stream().to_string());
+    }
+    if let Some(g) = &item.generics.where_clause {
+        tokens.push(' ');
+        tokens.push_str(&g.to_token_stream().to_string());
+    }
+    tokens
+}
+
+pub fn format_const_signature(item: &ItemConst) -> String {
+    // "const NAME: Type", value left to code_body
+    let mut tokens = String::new();
+    let vis = item.vis.to_token_stream().to_string();
+    if !vis.is_empty() {
+        tokens.push_str(&vis);
+        tokens.push(' ');
+    }
+    tokens.push_str("const ");
+    tokens.push_str(&item.ident.to_string());
+    tokens.push_str(": ");
+    tokens.push_str(&item.ty.to_token_stream().to_string());
+    tokens
+}
+
+pub fn format_static_signature(item: &ItemStatic) -> String {
+    // "static [mut] NAME: Type", value left to code_body
+    let mut tokens = String::new();
+    let vis = item.vis.to_token_stream().to_string();
+    if !vis.is_empty() {
+        tokens.push_str(&vis);
+        tokens.push(' ');
+    }
+    tokens.push_str("static ");
+    if matches!(item.mutability, syn::StaticMutability::Mut(_)) {
+        tokens.push_str("mut ");
+    }
+    tokens.push_str(&item.ident.to_string());
+    tokens.push_str(": ");
+    tokens.push_str(&item.ty.to_token_stream().to_string());
+    tokens
+}
+
+pub fn format_type_alias_signature(item: &ItemType) -> String {
+    let mut alias = item.clone();
+    alias.attrs.clear();
+    alias.to_token_stream().to_string()
+}
+
+pub fn format_macro_signature(item: &ItemMacro) -> Option<String> {
+    // "macro_rules! name", prefixed with #[macro_export] when exported
+    let ident = item.ident.as_ref()?;
+    if !item.mac.path.is_ident("macro_rules") {
+        return None;
+    }
+    let mut tokens = String::new();
+    if item.attrs.iter().any(|a| a.path().is_ident("macro_export")) {
+        tokens.push_str("#[macro_export] ");
+    }
+    tokens.push_str("macro_rules! ");
+    tokens.push_str(&ident.to_string());
+    Some(tokens)
+}
+
 pub fn format_fn_signature(item: &ItemFn) -> String {
     item.sig.to_token_stream().to_string()
 }
 
+/// How a method takes `self`: `self`, `&self`, `&mut self`, or `self: Type`
+/// for typed receivers like `self: Box<Self>`. `None` for associated
+/// functions. Lifetimes and `mut` bindings don't change the kind.
+pub fn format_receiver(sig: &Signature) -> Option<String> {
+    let receiver = sig.receiver()?;
+    Some(match (&receiver.colon_token, &receiver.reference) {
+        (Some(_), _) => format!("self: {}", normalize_type_name(&receiver.ty)),
+        (None, Some(_)) if receiver.mutability.is_some() => "&mut self".to_string(),
+        (None, Some(_)) => "&self".to_string(),
+        (None, None) => "self".to_string(),
+    })
+}
+
+/// Method signature with the receiver written as `format_receiver` names it,
+/// e.g. `fn sum (&self) -> i32` rather than `fn sum (& self) -> i32`
+pub fn format_method_signature(sig: &Signature) -> String {
+    let signature = sig.to_token_stream().to_string();
+    let (Some(receiver), Some(normalized)) = (sig.receiver(), format_receiver(sig)) else {
+        return signature;
+    };
+    let written = format!("({}", receiver.to_token_stream());
+    signature.replacen(&written, &format!("({}", normalized), 1)
+}
+
+/// Every path a `use` item imports, one per leaf: `use a::{b, c::*, d as e}`
+/// gives `a::b`, `a::c::*` and `a::d`. Renames keep the original path and
+/// `self` in a group stands for the group's prefix.
+pub fn flatten_use_paths(item: &ItemUse) -> Vec<String> {
+    use_bindings(item)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Like `flatten_use_paths`, paired with the name each path is bound to in
+/// the importing module: `use a::{b, d as e, c::*}` gives `(a::b, b)`,
+/// `(a::d, e)` and `(a::c::*, *)`.
+pub fn use_bindings(item: &ItemUse) -> Vec<(String, String)> {
+    fn walk(tree: &UseTree, prefix: &mut Vec<String>, out: &mut Vec<(String, String)>) {
+        let last = |prefix: &[String]| prefix.last().cloned().unwrap_or_default();
+        match tree {
+            UseTree::Path(p) => {
+                prefix.push(p.ident.to_string());
+                walk(&p.tree, prefix, out);
+                prefix.pop();
+            }
+            UseTree::Name(n) if n.ident == "self" => out.push((prefix.join("::"), last(prefix))),
+            UseTree::Name(n) => {
+                out.push((join_path(prefix, &n.ident.to_string()), n.ident.to_string()))
+            }
+            UseTree::Rename(r) if r.ident == "self" => {
+                out.push((prefix.join("::"), r.rename.to_string()))
+            }
+            UseTree::Rename(r) => out.push((
+                join_path(prefix, &r.ident.to_string()),
+                r.rename.to_string(),
+            )),
+            UseTree::Glob(_) => out.push((join_path(prefix, "*"), "*".to_string())),
+            UseTree::Group(g) => {
+                for tree in &g.items {
+                    walk(tree, prefix, out);
+                }
+            }
+        }
+    }
+    fn join_path(prefix: &[String], last: &str) -> String {
+        prefix
+            .iter()
+            .map(String::as_str)
+            .chain([last])
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    let mut prefix = Vec::new();
+    let mut out = Vec::new();
+    walk(&item.tree, &mut prefix, &mut out);
+    if item.leading_colon.is_some() {
+        for (path, _) in &mut out {
+            path.insert_str(0, "::");
+        }
+    }
+    out
+}
+
+/// `a::b::c` without generic arguments, keeping a leading `::` if present
+pub fn path_to_string(path: &syn::Path) -> String {
+    let joined = path
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::");
+    if path.leading_colon.is_some() {
+        format!("::{}", joined)
+    } else {
+        joined
+    }
+}
+
+/// Functions called in `block`, deduplicated in first-call order: path calls
+/// by their joined path (`Point::new`), method calls by name only (`sum`).
+pub fn collect_references(block: &syn::Block) -> Vec<String> {
+    struct V {
+        refs: Vec<String>,
+    }
+    impl V {
+        fn push(&mut self, name: String) {
+            if !self.refs.contains(&name) {
+                self.refs.push(name);
+            }
+        }
+    }
+    impl<'ast> Visit<'ast> for V {
+        fn visit_expr_call(&mut self, expr: &'ast syn::ExprCall) {
+            if let Expr::Path(path_expr) = &*expr.func {
+                self.push(path_to_string(&path_expr.path));
+            }
+            syn::visit::visit_expr_call(self, expr);
+        }
+
+        fn visit_expr_method_call(&mut self, expr: &'ast syn::ExprMethodCall) {
+            // Receiver first, matching evaluation order
+            syn::visit::visit_expr_method_call(self, expr);
+            self.push(expr.method.to_string());
+        }
+    }
+    let mut v = V { refs: Vec::new() };
+    v.visit_block(block);
+    v.refs
+}
+
+/// Type or path tokens as they'd be written by hand, without lifetimes:
+/// `DataProcessor < 'a , T >` becomes `DataProcessor<T>`, `& 'a mut str`
+/// becomes `&mut str`.
+pub fn normalize_type_name(ty: &impl ToTokens) -> String {
+    let tokens = ty.to_token_stream().to_string();
+    let lifetime = Regex::new(r"(\+\s*)?'\s*\w+\s*,?").unwrap();
+    let without_lifetimes = lifetime.replace_all(&tokens, "");
+    let empty_generics = Regex::new(r"<\s*>").unwrap();
+    let tokens = empty_generics.replace_all(&without_lifetimes, "");
+    let mut out = String::new();
+    let mut glue_next = true;
+    for token in tokens.split_whitespace() {
+        // `Fn (u8)` is a call-like path, `-> (A, B)` a tuple
+        let glue_prev = matches!(token, "<" | ">" | "," | "::" | ")" | "]" | ";")
+            || token.starts_with(['>', ',', ')', ']', ';'])
+            || (token.starts_with('(') && out.ends_with(|c: char| c.is_alphanumeric() || c == '_'));
+        if !(out.is_empty() || glue_next || glue_prev) {
+            out.push(' ');
+        }
+        out.push_str(token);
+        glue_next = matches!(token, "<" | "::" | "&" | "(" | "[" | "*");
+    }
+    // `, >` is left behind when a trailing lifetime argument is removed
+    out.replace(",>", ">")
+}
+
+/// `text` cut to at most `max_bytes` at a char boundary, followed by a
+/// `… [truncated N bytes]` marker, or `None` when it already fits
+pub fn truncate_text(text: &str, max_bytes: usize) -> Option<String> {
+    if text.len() <= max_bytes {
+        return None;
+    }
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(format!(
+        "{}… [truncated {} bytes]",
+        &text[..end],
+        text.len() - end
+    ))
+}
+
+/// Cheap token estimate: every punctuation char counts as one token and every
+/// run of letters, digits and `_` as one per 4 chars, rounded up. Lands close
+/// to BPE tokenizers on code without needing one.
+pub fn estimate_tokens(text: &str) -> usize {
+    let mut tokens = 0;
+    let mut word: usize = 0;
+    for c in text.chars() {
+        if c.is_alphanumeric() || c == '_' {
+            word += 1;
+            continue;
+        }
+        tokens += word.div_ceil(4);
+        word = 0;
+        if !c.is_whitespace() {
+            tokens += 1;
+        }
+    }
+    tokens + word.div_ceil(4)
+}
+
+/// Collapses each run of whitespace in code to one space, keeping string,
+/// raw string and char literals exactly as written
 pub fn compact_whitespace(s: &str) -> String {
-    let re = Regex::new(r"\s+").unwrap();
-    re.replace_all(s.trim(), " ").to_string()
+    let chars: Vec<char> = s.trim().chars().collect();
+    let mut out = String::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some(end) = literal_end(&chars, i) {
+            out.extend(&chars[i..end]);
+            i = end;
+        } else if chars[i].is_whitespace() {
+            out.push(' ');
+            while i < chars.len() && chars[i].is_whitespace() {
+                i += 1;
+            }
+        } else {
+            out.push(chars[i]);
+            i += 1;
+        }
+    }
+    out
 }
 
+/// Removes `//` line comments and `/* */` block comments, including nested
+/// ones, leaving string, raw string and char literals untouched
 pub fn strip_comments(src: &str) -> String {
-    // Remove // line comments
-    let re_line = Regex::new(r"//.*").unwrap();
-    let tmp = re_line.replace_all(src, "");
-    // Remove /* block */ comments (naive, no nesting)
-    let re_block = Regex::new(r"/\*[^*]*\*+(?:[^/*][^*]*\*+)*/").unwrap();
-    let tmp2 = re_block.replace_all(&tmp, "");
-    tmp2.to_string()
+    let chars: Vec<char> = src.chars().collect();
+    let mut out = String::with_capacity(src.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if let Some(end) = literal_end(&chars, i) {
+            out.extend(&chars[i..end]);
+            i = end;
+            continue;
+        }
+        match (chars[i], chars.get(i + 1)) {
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                let mut depth = 0;
+                while i < chars.len() {
+                    match (chars[i], chars.get(i + 1)) {
+                        ('/', Some('*')) => {
+                            depth += 1;
+                            i += 2;
+                        }
+                        ('*', Some('/')) => {
+                            depth -= 1;
+                            i += 2;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => i += 1,
+                    }
+                }
+            }
+            (c, _) => {
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
 }
 
-pub fn collect_idents(tokens: &proc_macro2::TokenStream) -> String {
-    struct V<'a> {
-        idents: &'a mut Vec<String>,
+// End (exclusive) of the string, raw string or char literal starting at `i`,
+// or of the source if it is unterminated. Prefixes such as `b` or `c` are
+// ordinary characters to the caller; lifetimes are not literals.
+fn literal_end(chars: &[char], i: usize) -> Option<usize> {
+    let is_ident = |j: usize| chars[j].is_alphanumeric() || chars[j] == '_';
+    match chars[i] {
+        '"' => {
+            let mut j = i + 1;
+            while j < chars.len() {
+                match chars[j] {
+                    '\\' => j += 2,
+                    '"' => return Some(j + 1),
+                    _ => j += 1,
+                }
+            }
+            Some(chars.len())
+        }
+        'r' => {
+            // `r` must start a token, or follow a `b`/`c` prefix that does
+            let starts_token = match i {
+                0 => true,
+                1 => !is_ident(0) || matches!(chars[0], 'b' | 'c'),
+                _ => !is_ident(i - 1) || (matches!(chars[i - 1], 'b' | 'c') && !is_ident(i - 2)),
+            };
+            if !starts_token {
+                return None;
+            }
+            let hashes = chars[i + 1..].iter().take_while(|&&c| c == '#').count();
+            let open = i + 1 + hashes;
+            if chars.get(open) != Some(&'"') {
+                return None;
+            }
+            let mut j = open + 1;
+            while j < chars.len() {
+                if chars[j] == '"'
+                    && chars[j + 1..].iter().take_while(|&&c| c == '#').count() >= hashes
+                {
+                    return Some(j + 1 + hashes);
+                }
+                j += 1;
+            }
+            Some(chars.len())
+        }
+        '\'' => match (chars.get(i + 1), chars.get(i + 2)) {
+            (Some('\\'), _) => {
+                let mut j = i + 3;
+                while j < chars.len() && chars[j] != '\'' {
+                    j += 1;
+                }
+                Some((j + 1).min(chars.len()))
+            }
+            (Some(_), Some('\'')) => Some(i + 3),
+            _ => None,
+        },
+        _ => None,
     }
-    impl<'a, 'ast> Visit<'ast> for V<'a> {
-        fn visit_ident(&mut self, i: &'ast Ident) {
-            self.idents.push(i.to_string());
+}
+
+/// Distinct identifiers in `tokens`, in order of first appearance. Walks the
+/// token trees directly, so any stream works (items, expressions, macro
+/// bodies). Keywords and `_` are skipped; `self`, `Self`, `crate` and `super` are
+/// path segments and kept.
+pub fn collect_idents(tokens: &proc_macro2::TokenStream) -> String {
+    fn walk(tokens: proc_macro2::TokenStream, idents: &mut Vec<String>) {
+        for tt in tokens {
+            match tt {
+                proc_macro2::TokenTree::Ident(i) => {
+                    let ident = i.to_string();
+                    if !KEYWORDS.contains(&ident.as_str()) {
+                        idents.push(ident);
+                    }
+                }
+                proc_macro2::TokenTree::Group(g) => walk(g.stream(), idents),
+                _ => {}
+            }
         }
     }
     let mut list = Vec::new();
-    let mut v = V { idents: &mut list };
-    let file: syn::File = syn::parse_quote! { #tokens };
-    v.visit_file(&file);
+    walk(tokens.clone(), &mut list);
     // Deduplicate while preserving order
     let mut seen = std::collections::HashSet::new();
     list.into_iter()
@@ -112,41 +572,54 @@ pub fn collect_idents(tokens: &proc_macro2::TokenStream) -> String {
         .join(" ")
 }
 
+// Strict and reserved keywords, minus the ones that appear as path segments,
+// and the `_` placeholder
+const KEYWORDS: &[&str] = &[
+    "_", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+    "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in",
+    "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized",
+    "use", "virtual", "where", "while", "yield",
+];
+
+/// Maps a source file to its module path, relative to `root`.
+///
+/// Layouts handled:
+/// - `src/lib.rs`, `src/main.rs` -> `crate`
+/// - `src/foo.rs`, `src/foo/mod.rs` -> `crate::foo`
+/// - `src/foo/bar.rs`, `src/foo/bar/mod.rs` -> `crate::foo::bar`
+/// - `src/bin/tool.rs`, `src/bin/tool/main.rs` -> `crate::bin::tool`
+///
+/// Binaries are separate crates, but keeping them under `crate::bin::` keeps
+/// their symbols distinct from the library's. A leading `src` is optional and
+/// both `/` and `\` separators are accepted.
 pub fn rel_module_path(root: &Path, file: &Path) -> String {
     let rel = pathdiff::diff_paths(file, root).unwrap_or_else(|| file.to_path_buf());
-    let mut comps: Vec<String> = Vec::new();
-    for comp in rel.components() {
-        let s = comp.as_os_str().to_string_lossy().to_string();
-        comps.push(s);
-    }
+    let mut comps: Vec<String> = path_parts(&rel).collect();
     // Remove src/ prefix if present
-    let comps = if comps.first().map(|s| s == "src").unwrap_or(false) {
-        comps.into_iter().skip(1).collect::<Vec<_>>()
-    } else {
-        comps
-    };
-    if comps.is_empty() {
-        return "crate".to_string();
+    if comps.first().map(|s| s == "src").unwrap_or(false) {
+        comps.remove(0);
     }
-    let mut parts = Vec::new();
-    for (i, part) in comps.iter().enumerate() {
-        if i == comps.len() - 1 {
-            // file name
-            let p = PathBuf::from(part);
-            let stem = p.file_stem().unwrap().to_string_lossy().to_string();
-            if stem == "mod" || stem == "lib" || stem == "main" {
-                // use directory as module (already added)
-            } else {
-                parts.push(stem);
-            }
-        } else {
-            parts.push(part.clone());
-        }
+    let Some(file_name) = comps.pop() else {
+        return "crate".to_string();
+    };
+    let stem = Path::new(&file_name)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or(file_name);
+    let is_root_file = match stem.as_str() {
+        "mod" => true,
+        "lib" => comps.is_empty(),
+        "main" => comps.is_empty() || (comps.len() == 2 && comps[0] == "bin"),
+        _ => false,
+    };
+    if !is_root_file {
+        comps.push(stem);
     }
-    if parts.is_empty() {
+    if comps.is_empty() {
         "crate".to_string()
     } else {
-        format!("crate::{}", parts.join("::"))
+        format!("crate::{}", comps.join("::"))
     }
 }
 
@@ -155,10 +628,7 @@ pub fn span_start_end(span: Span) -> Option<((usize, usize), (usize, usize))> {
     let start = span.start();
     let end = span.end();
     if start.line > 0 && end.line > 0 {
-        Some((
-            (start.line as usize, start.column as usize),
-            (end.line as usize, end.column as usize),
-        ))
+        Some(((start.line, start.column), (end.line, end.column)))
     } else {
         None
     }
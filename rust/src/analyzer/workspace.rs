@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use globset::Glob;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A crate analyzed on its own: module paths are relative to `root`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateUnit {
+    /// `package.name` from the crate's manifest, empty when there is none
+    pub name: String,
+    pub root: PathBuf,
+}
+
+fn read_manifest(dir: &Path) -> Result<Option<toml::Table>> {
+    let file = dir.join("Cargo.toml");
+    if !file.is_file() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(&file)
+        .with_context(|| format!("Failed to read {}", file.display()))?;
+    let table =
+        toml::from_str(&content).with_context(|| format!("Failed to parse {}", file.display()))?;
+    Ok(Some(table))
+}
+
+fn package_name(manifest: &toml::Table) -> Option<String> {
+    manifest
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn string_list(table: &toml::Value, key: &str) -> Vec<String> {
+    table
+        .get(key)
+        .and_then(|v| v.as_array())
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Crates to analyze below `path`. For a `[workspace]` manifest these are its
+/// members (globs like `crates/*` expanded, `exclude` honored) plus the root
+/// package if it has one, and `true` is returned alongside. Anything else is a
+/// single crate rooted at `path`, named after the nearest package manifest at
+/// or above it.
+pub fn crate_units(path: &Path) -> Result<(Vec<CrateUnit>, bool)> {
+    let manifest = read_manifest(path)?;
+    let Some(workspace) = manifest.as_ref().and_then(|m| m.get("workspace")) else {
+        let name = path
+            .ancestors()
+            .find_map(|dir| {
+                read_manifest(dir)
+                    .ok()
+                    .flatten()
+                    .and_then(|m| package_name(&m))
+            })
+            .unwrap_or_default();
+        let unit = CrateUnit {
+            name,
+            root: path.to_path_buf(),
+        };
+        return Ok((vec![unit], false));
+    };
+
+    let excluded: Vec<PathBuf> = string_list(workspace, "exclude")
+        .iter()
+        .map(|e| path.join(e))
+        .collect();
+    let mut units = Vec::new();
+    if let Some(name) = manifest.as_ref().and_then(package_name) {
+        units.push(CrateUnit {
+            name,
+            root: path.to_path_buf(),
+        });
+    }
+    for pattern in string_list(workspace, "members") {
+        let pattern = pattern.trim_end_matches('/');
+        let matcher = Glob::new(pattern)
+            .with_context(|| format!("Invalid workspace member `{}`", pattern))?
+            .compile_matcher();
+        let depth = Path::new(pattern).components().count();
+        let dirs = WalkDir::new(path)
+            .min_depth(depth)
+            .max_depth(depth)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+            .filter(|e| matcher.is_match(e.path().strip_prefix(path).unwrap_or(e.path())));
+        for dir in dirs {
+            let root = dir.into_path();
+            if excluded.iter().any(|e| root.starts_with(e)) {
+                continue;
+            }
+            let Some(manifest) = read_manifest(&root)? else {
+                continue;
+            };
+            if units.iter().any(|u: &CrateUnit| u.root == root) {
+                continue;
+            }
+            units.push(CrateUnit {
+                name: package_name(&manifest).unwrap_or_default(),
+                root,
+            });
+        }
+    }
+    Ok((units, true))
+}
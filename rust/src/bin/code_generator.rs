@@ -5,7 +5,11 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use proc_macro2::TokenStream;
 use quote::quote;
-use rust_copartner::complexity_analyzer::{ComplexityAnalyzer, FunctionComplexity};
+use rust_copartner::cli_style::{self, icon};
+use rust_copartner::complexity_analyzer::{
+    refactor::{find_candidates, HeuristicConfig},
+    ComplexityAnalyzer, ComplexitySummary, FunctionComplexity,
+};
 use std::{fs, path::PathBuf};
 
 #[derive(Parser)]
@@ -23,6 +27,10 @@ struct Cli {
     /// Type of code to generate
     #[arg(short, long, value_enum)]
     generate: GenerationType,
+
+    /// Plain output without emoji (also set by NO_COLOR)
+    #[arg(long)]
+    no_color: bool,
 }
 
 #[derive(clap::ValueEnum, Clone)]
@@ -39,9 +47,11 @@ enum GenerationType {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    cli_style::init(cli.no_color);
 
     println!(
-        "🚀 Generating {} for: {}",
+        "{} Generating {} for: {}",
+        icon("🚀", "*"),
         match cli.generate {
             GenerationType::Tests => "unit tests",
             GenerationType::Benchmarks => "benchmarks",
@@ -71,8 +81,16 @@ fn main() -> Result<()> {
     fs::write(&cli.output, generated_code.to_string())
         .with_context(|| format!("Failed to write to: {}", cli.output.display()))?;
 
-    println!("✅ Generated code written to: {}", cli.output.display());
-    println!("📊 Processed {} functions", functions.len());
+    println!(
+        "{} Generated code written to: {}",
+        icon("✅", "[ok]"),
+        cli.output.display()
+    );
+    println!(
+        "{} Processed {} functions",
+        icon("📊", "*"),
+        functions.len()
+    );
 
     Ok(())
 }
@@ -82,9 +100,10 @@ fn generate_tests(functions: &[FunctionComplexity]) -> Result<TokenStream> {
 
     for func in functions {
         let func_name = &func.name;
-        let test_name = format!("test_{}", func_name);
+        let test_name = format!("test_{}", ident_fragment(func_name));
         let test_ident = syn::Ident::new(&test_name, proc_macro2::Span::call_site());
-        let func_ident = syn::Ident::new(func_name, proc_macro2::Span::call_site());
+        let func_path: syn::ExprPath = syn::parse_str(func_name)
+            .with_context(|| format!("Invalid function path: {}", func_name))?;
 
         let complexity_comment = format!(
             "Test for {} (complexity: {})",
@@ -93,20 +112,20 @@ fn generate_tests(functions: &[FunctionComplexity]) -> Result<TokenStream> {
 
         let test_fn = if func.parameter_count == 0 {
             quote! {
+                #[doc = #complexity_comment]
                 #[test]
                 fn #test_ident() {
-                    // #complexity_comment
                     // TODO: Add proper test implementation
                     // Function has no parameters, test direct call
-                    let result = #func_ident();
+                    let result = #func_path();
                     // Add assertions here based on expected behavior
                 }
             }
         } else {
             quote! {
+                #[doc = #complexity_comment]
                 #[test]
                 fn #test_ident() {
-                    // #complexity_comment
                     // TODO: Add proper test implementation with parameters
                     // Function has #(func.parameter_count) parameter(s)
                     // Create appropriate test inputs and verify outputs
@@ -137,9 +156,10 @@ fn generate_benchmarks(functions: &[FunctionComplexity]) -> Result<TokenStream>
 
     for func in functions {
         let func_name = &func.name;
-        let bench_name = format!("bench_{}", func_name);
+        let bench_name = format!("bench_{}", ident_fragment(func_name));
         let bench_ident = syn::Ident::new(&bench_name, proc_macro2::Span::call_site());
-        let func_ident = syn::Ident::new(func_name, proc_macro2::Span::call_site());
+        let func_path: syn::ExprPath = syn::parse_str(func_name)
+            .with_context(|| format!("Invalid function path: {}", func_name))?;
 
         let complexity_comment = format!(
             "Benchmark for {} (cyclomatic: {}, cognitive: {})",
@@ -147,13 +167,13 @@ fn generate_benchmarks(functions: &[FunctionComplexity]) -> Result<TokenStream>
         );
 
         let bench_fn = quote! {
+            #[doc = #complexity_comment]
             #[bench]
             fn #bench_ident(b: &mut Bencher) {
-                // #complexity_comment
                 b.iter(|| {
                     // TODO: Add appropriate benchmark setup
                     // High complexity functions may need performance monitoring
-                    #func_ident(/* add parameters as needed */)
+                    #func_path(/* add parameters as needed */)
                 });
             }
         };
@@ -192,6 +212,8 @@ fn generate_docs(functions: &[FunctionComplexity]) -> Result<TokenStream> {
 - **Parameters**: {}
 - **Rating**: {:?}
 - **Max Nesting Depth**: {}
+- **Signature Complexity**: {}
+- **Trait Bounds**: {}
 
 ## Details
 - If statements: {}
@@ -209,6 +231,8 @@ fn generate_docs(functions: &[FunctionComplexity]) -> Result<TokenStream> {
             func.parameter_count,
             rating,
             func.details.max_nesting_depth,
+            func.details.signature_complexity,
+            func.details.bound_count,
             func.details.if_statements,
             func.details.match_arms,
             func.details.loops,
@@ -234,27 +258,10 @@ fn generate_docs(functions: &[FunctionComplexity]) -> Result<TokenStream> {
 }
 
 fn generate_reports(functions: &[FunctionComplexity]) -> Result<TokenStream> {
-    let total_functions = functions.len();
-    let high_complexity_count = functions
-        .iter()
-        .filter(|f| {
-            matches!(
-                f.return_complexity,
-                rust_copartner::complexity_analyzer::ComplexityRating::High
-                    | rust_copartner::complexity_analyzer::ComplexityRating::VeryHigh
-            )
-        })
-        .count();
-
-    let avg_cyclomatic: f64 = if total_functions > 0 {
-        functions
-            .iter()
-            .map(|f| f.cyclomatic_complexity)
-            .sum::<usize>() as f64
-            / total_functions as f64
-    } else {
-        0.0
-    };
+    let summary = ComplexitySummary::from_functions(functions);
+    let total_functions = summary.total_functions;
+    let high_complexity_count = summary.high_complexity_count();
+    let avg_cyclomatic = summary.average_cyclomatic;
 
     let generated = quote! {
         //! # Complexity Analysis Report
@@ -307,27 +314,25 @@ fn generate_reports(functions: &[FunctionComplexity]) -> Result<TokenStream> {
     Ok(generated)
 }
 
-fn get_complexity_recommendations(func: &FunctionComplexity) -> String {
-    let mut recommendations = Vec::new();
-
-    if func.cyclomatic_complexity > 10 {
-        recommendations.push("Consider breaking this function into smaller functions");
-    }
+/// Turn a qualified name like `<Point as Display>::fmt` into `point_as_display_fmt`
+fn ident_fragment(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
 
-    if func.details.max_nesting_depth > 4 {
-        recommendations.push("Reduce nesting depth using early returns or helper functions");
-    }
+fn get_complexity_recommendations(func: &FunctionComplexity) -> String {
+    let mut recommendations: Vec<String> =
+        find_candidates(std::slice::from_ref(func), &HeuristicConfig::default())
+            .into_iter()
+            .flat_map(|candidate| candidate.suggestions)
+            .map(|suggestion| suggestion.to_string())
+            .collect();
 
     if func.details.unsafe_blocks > 0 {
-        recommendations.push("Review unsafe code blocks for safety guarantees");
-    }
-
-    if func.parameter_count > 5 {
-        recommendations.push("Consider using a struct to group related parameters");
-    }
-
-    if func.details.function_calls > 10 {
-        recommendations.push("High number of function calls may indicate doing too much");
+        recommendations.push("Review unsafe code blocks for safety guarantees".to_string());
     }
 
     if recommendations.is_empty() {
@@ -1,319 +1,1702 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use colored::*;
+use regex::Regex;
+use rust_copartner::cli_style::{self, icon};
 use rust_copartner::complexity_analyzer::{
-    ComplexityAnalyzer, ComplexityRating, FunctionComplexity,
+    diff::MetricSnapshot,
+    git::{self, FileDiff, FileStatus},
+    lint::{lint_functions, LintConfig},
+    report,
+    sarif::to_sarif,
+    walk, AnalysisCache, AnalyzeOptions, Baseline, BaselineStatus, ComplexityAnalyzer,
+    ComplexityRating, ComplexityReportExt, ComplexityScore, ComplexityThresholds, CopartnerConfig,
+    FileSelection, FunctionComplexity, ImplComplexity, Metric, ProjectComplexity, ScoreWeights,
+    TypeComplexity,
+};
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
-use std::{fs, path::PathBuf};
-use walkdir::WalkDir;
 
 #[derive(Parser)]
 #[command(name = "complexity-analyzer")]
 #[command(about = "A CLI tool to analyze Rust function complexity")]
 #[command(version = "1.0")]
 struct Cli {
+    /// Settings file to use instead of the nearest copartner.toml above the analyzed path
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Plain output without colors or emoji (also set by NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Colored, human-readable text
+    Pretty,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+/// Where results and progress messages go. In the machine-readable formats
+/// stdout carries only the serialized records and everything else moves to
+/// stderr, so the output can be piped straight into `jq`.
+#[derive(Args)]
+struct Output {
+    /// Output format; json and ndjson print only records to stdout [default: pretty]
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+}
+
+impl Output {
+    fn format(&self) -> OutputFormat {
+        self.format.unwrap_or(OutputFormat::Pretty)
+    }
+
+    fn is_pretty(&self) -> bool {
+        self.format() == OutputFormat::Pretty
+    }
+
+    fn fill(&mut self, config: &CopartnerConfig) -> Result<()> {
+        if self.format.is_none() {
+            if let Some(format) = &config.output.format {
+                self.format = Some(config_value("output.format", format)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Progress, warnings and totals
+    fn status(&self, line: impl Display) {
+        if self.is_pretty() {
+            println!("{}", line);
+        } else {
+            eprintln!("{}", line);
+        }
+    }
+
+    /// Print each record with `pretty`, or serialize them all
+    fn records<T: Serialize>(&self, records: &[T], pretty: impl FnMut(&T)) -> Result<()> {
+        match self.format() {
+            OutputFormat::Pretty => records.iter().for_each(pretty),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(records)?),
+            OutputFormat::Ndjson => {
+                for record in records {
+                    println!("{}", serde_json::to_string(record)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Options shared by every subcommand that runs the analyzer
+#[derive(Args)]
+struct AnalysisArgs {
+    /// Count each `?` operator as a decision point
+    #[arg(long)]
+    count_try: bool,
+
+    /// Analyze #[test] functions and #[cfg(test)] modules
+    #[arg(long)]
+    include_tests: bool,
+
+    /// Highest cyclomatic score rated Low [default: 5]
+    #[arg(long)]
+    low_max: Option<usize>,
+
+    /// Highest cyclomatic score rated Medium [default: 10]
+    #[arg(long)]
+    medium_max: Option<usize>,
+
+    /// Highest cyclomatic score rated High [default: 20]
+    #[arg(long)]
+    high_max: Option<usize>,
+}
+
+impl AnalysisArgs {
+    fn fill(&mut self, config: &CopartnerConfig) {
+        let thresholds = &config.thresholds;
+        self.low_max = self.low_max.or(thresholds.low_max);
+        self.medium_max = self.medium_max.or(thresholds.medium_max);
+        self.high_max = self.high_max.or(thresholds.high_max);
+    }
+
+    fn options(&self) -> Result<AnalyzeOptions> {
+        let defaults = ComplexityThresholds::default();
+        let thresholds = ComplexityThresholds {
+            low_max: self.low_max.unwrap_or(defaults.low_max),
+            medium_max: self.medium_max.unwrap_or(defaults.medium_max),
+            high_max: self.high_max.unwrap_or(defaults.high_max),
+        };
+        thresholds.validate()?;
+
+        Ok(AnalyzeOptions {
+            count_try: self.count_try,
+            include_tests: self.include_tests,
+            thresholds,
+            max_depth: None,
+            selection: FileSelection::default(),
+        })
+    }
+}
+
+/// Which files directory walks pick up
+#[derive(Args)]
+struct WalkArgs {
+    /// Skip files and directories matching this glob, relative to the path (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Only analyze files matching this glob, relative to the path (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Also analyze files listed in .gitignore and .ignore files
+    #[arg(long)]
+    no_ignore: bool,
+}
+
+impl WalkArgs {
+    /// Globs from the command line replace the configured ones
+    fn fill(&mut self, config: &CopartnerConfig) {
+        let files = &config.files;
+        if self.exclude.is_empty() {
+            self.exclude = files.exclude.clone();
+        }
+        if self.include.is_empty() {
+            self.include = files.include.clone();
+        }
+        if files.respect_ignore == Some(false) {
+            self.no_ignore = true;
+        }
+    }
+
+    fn options(&self, analysis: &AnalysisArgs) -> Result<AnalyzeOptions> {
+        Ok(AnalyzeOptions {
+            selection: FileSelection {
+                respect_ignore: !self.no_ignore,
+                include: self.include.clone(),
+                exclude: self.exclude.clone(),
+            },
+            ..analysis.options()?
+        })
+    }
+}
+
+/// Narrow down which functions `file` and `dir` list
+#[derive(Args)]
+struct FilterArgs {
+    /// Show only high complexity functions
+    #[arg(long)]
+    high_only: bool,
+
+    /// Show only functions that can panic (unwrap, expect, panic!, todo!, ...)
+    #[arg(long)]
+    panics_only: bool,
+
+    /// Show only functions rated above Low that have no doc comment
+    #[arg(long)]
+    undocumented_only: bool,
+
+    /// Show only plain `pub` functions
+    #[arg(long)]
+    public_only: bool,
+
+    /// Show only `async` functions
+    #[arg(long)]
+    async_only: bool,
+
+    /// Only consider functions whose qualified name matches this regex;
+    /// unlike the other filters this also narrows totals and limit checks
+    #[arg(long, value_name = "REGEX")]
+    name: Option<String>,
+}
+
+impl FilterArgs {
+    /// Drop functions outside `--name` before anything is counted
+    fn scope(&self, functions: Vec<FunctionComplexity>) -> Result<Vec<FunctionComplexity>> {
+        let Some(pattern) = &self.name else {
+            return Ok(functions);
+        };
+        let regex =
+            Regex::new(pattern).with_context(|| format!("Invalid --name pattern `{}`", pattern))?;
+        Ok(functions.filter_name(&regex))
+    }
+
+    fn apply(&self, mut functions: Vec<FunctionComplexity>) -> Vec<FunctionComplexity> {
+        if self.high_only || self.panics_only || self.undocumented_only {
+            functions.retain(|f| !f.suppressed);
+        }
+        if self.high_only {
+            functions = functions.filter_rating(ComplexityRating::High);
+        }
+        if self.panics_only {
+            functions.retain(|f| f.details.panic_points() > 0);
+        }
+        if self.undocumented_only {
+            functions = functions.filter_rating(ComplexityRating::Medium);
+            functions.retain(|f| !f.has_doc_comment);
+        }
+        if self.public_only {
+            functions.retain(|f| f.is_public());
+        }
+        if self.async_only {
+            functions.retain(|f| f.is_async);
+        }
+        functions
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SortKey {
+    Cyclomatic,
+    Cognitive,
+    Lines,
+    Params,
+    Name,
+    File,
+}
+
+/// Order and number of the functions `dir` lists
+#[derive(Args)]
+struct OrderArgs {
+    /// Metrics sort highest first, name and file alphabetically
+    #[arg(long, value_enum, default_value_t = SortKey::Cyclomatic)]
+    sort: SortKey,
+
+    /// Reverse the sort order
+    #[arg(long)]
+    reverse: bool,
+
+    /// List at most N functions
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+}
+
+impl OrderArgs {
+    fn sort(&self, functions: Vec<FunctionComplexity>) -> Vec<FunctionComplexity> {
+        let mut functions = match self.sort {
+            SortKey::Cyclomatic => functions.sort_by_metric(Metric::Cyclomatic),
+            SortKey::Cognitive => functions.sort_by_metric(Metric::Cognitive),
+            SortKey::Lines => functions.sort_by_metric(Metric::Lines),
+            SortKey::Params => functions.sort_by_metric(Metric::Parameters),
+            SortKey::Name => {
+                let mut functions = functions;
+                functions.sort_by(|a, b| a.name.cmp(&b.name));
+                functions
+            }
+            SortKey::File => {
+                let mut functions = functions;
+                functions.sort_by(|a, b| (&a.file, a.start_line).cmp(&(&b.file, b.start_line)));
+                functions
+            }
+        };
+        if self.reverse {
+            functions.reverse();
+        }
+        functions
+    }
+
+    /// The first `--limit` of already sorted functions
+    fn limit<'a>(&self, functions: &'a [FunctionComplexity]) -> &'a [FunctionComplexity] {
+        let n = self.limit.unwrap_or(functions.len()).min(functions.len());
+        &functions[..n]
+    }
+}
+
+/// Limits for the findings written by `--export-sarif`
+#[derive(Args)]
+struct LintArgs {
+    /// Report functions above this cyclomatic complexity [default: --medium-max]
+    #[arg(long)]
+    max_cyclomatic: Option<usize>,
+
+    /// Report functions above this cognitive complexity
+    #[arg(long)]
+    max_cognitive: Option<usize>,
+
+    /// Report functions with more parameters than this
+    #[arg(long)]
+    max_params: Option<usize>,
+
+    /// Report functions nested deeper than this
+    #[arg(long)]
+    max_nesting: Option<usize>,
+
+    /// Report functions longer than this many lines
+    #[arg(long)]
+    max_lines: Option<usize>,
+}
+
+impl LintArgs {
+    fn config(&self, thresholds: &ComplexityThresholds) -> LintConfig {
+        LintConfig {
+            // Anything rated High or worse is a finding unless told otherwise
+            max_cyclomatic: Some(self.max_cyclomatic.unwrap_or(thresholds.medium_max)),
+            max_cognitive: self.max_cognitive,
+            max_params: self.max_params,
+            max_nesting: self.max_nesting,
+            max_lines: self.max_lines,
+        }
+    }
+}
+
+/// Exit code when a `--fail-on-*` limit is violated; 1 is left for
+/// operational errors such as unreadable paths
+const EXIT_LIMIT_VIOLATED: u8 = 2;
+
+/// CI gating: exit with code 2 when any listed function violates a limit
+#[derive(Args)]
+struct FailOnArgs {
+    /// Fail if any function's cyclomatic complexity exceeds N
+    #[arg(long, value_name = "N")]
+    fail_on_cyclomatic: Option<usize>,
+
+    /// Fail if any function's cognitive complexity exceeds N
+    #[arg(long, value_name = "N")]
+    fail_on_cognitive: Option<usize>,
+
+    /// Fail if any function is rated this or worse
+    #[arg(long, value_enum)]
+    fail_on_rating: Option<FailRating>,
+
+    /// Only fail on functions missing from this baseline file or worse than recorded in it
+    #[arg(long, value_name = "PATH")]
+    baseline: Option<PathBuf>,
+
+    /// Record the functions currently over the limits in the baseline file instead of failing
+    #[arg(long, requires = "baseline")]
+    update_baseline: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum FailRating {
+    Medium,
+    High,
+    VeryHigh,
+}
+
+impl From<FailRating> for ComplexityRating {
+    fn from(rating: FailRating) -> Self {
+        match rating {
+            FailRating::Medium => ComplexityRating::Medium,
+            FailRating::High => ComplexityRating::High,
+            FailRating::VeryHigh => ComplexityRating::VeryHigh,
+        }
+    }
+}
+
+impl FailOnArgs {
+    fn fill(&mut self, config: &CopartnerConfig) -> Result<()> {
+        let fail_on = &config.fail_on;
+        self.fail_on_cyclomatic = self.fail_on_cyclomatic.or(fail_on.cyclomatic);
+        self.fail_on_cognitive = self.fail_on_cognitive.or(fail_on.cognitive);
+        if self.fail_on_rating.is_none() {
+            if let Some(rating) = &fail_on.rating {
+                self.fail_on_rating = Some(config_value("fail_on.rating", rating)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// One line per violation, suppressed functions excepted
+    fn violations<'a>(
+        &self,
+        functions: &'a [FunctionComplexity],
+    ) -> Vec<(&'a FunctionComplexity, String)> {
+        let config = LintConfig {
+            max_cyclomatic: self.fail_on_cyclomatic,
+            max_cognitive: self.fail_on_cognitive,
+            ..Default::default()
+        };
+        let mut violations = Vec::new();
+        for func in functions {
+            for violation in lint_functions(std::slice::from_ref(func), &config) {
+                violations.push((func, violation.to_string()));
+            }
+        }
+
+        if let Some(min) = self.fail_on_rating.map(ComplexityRating::from) {
+            for func in functions.iter().filter(|f| !f.suppressed) {
+                if func.return_complexity >= min {
+                    let message = format!(
+                        "{}: `{}` is rated {} (fail on {})",
+                        func.location(),
+                        func.name,
+                        func.return_complexity,
+                        min
+                    );
+                    violations.push((func, message));
+                }
+            }
+        }
+        violations
+    }
+
+    /// Report violations on stderr and pick the exit code. With a baseline,
+    /// functions recorded in it only fail when one of their metrics went up;
+    /// `root` is what baseline paths are relative to.
+    fn check(&self, functions: &[FunctionComplexity], root: &Path) -> Result<ExitCode> {
+        let mut violations = self.violations(functions);
+        if let Some(path) = &self.baseline {
+            let mut failing: Vec<&FunctionComplexity> = Vec::new();
+            for (func, _) in &violations {
+                if !failing.iter().any(|f| std::ptr::eq(*f, *func)) {
+                    failing.push(func);
+                }
+            }
+
+            if self.update_baseline {
+                Baseline::from_functions(failing.iter().copied(), root).save(path)?;
+                eprintln!(
+                    "{}",
+                    format!(
+                        "{} Recorded {} function(s) in baseline {}",
+                        icon("✓", "[ok]"),
+                        failing.len(),
+                        path.display()
+                    )
+                    .green()
+                );
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let baseline = Baseline::load(path)?;
+            let fixed = baseline.fixed(&failing, root);
+            if !fixed.is_empty() {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "{} function(s) no longer exceed the limits and can be removed from the baseline (--update-baseline):",
+                        fixed.len()
+                    )
+                    .yellow()
+                );
+                for entry in fixed {
+                    eprintln!("  {}: `{}`", entry.file.display(), entry.function);
+                }
+            }
+
+            violations = violations
+                .into_iter()
+                .filter_map(|(func, message)| match baseline.status(func, root) {
+                    BaselineStatus::Accepted => None,
+                    BaselineStatus::New => Some((func, format!("{} (not in baseline)", message))),
+                    BaselineStatus::Regressed(regressions) => {
+                        let regressions: Vec<String> =
+                            regressions.iter().map(|r| r.to_string()).collect();
+                        let message =
+                            format!("{} (regressed: {})", message, regressions.join(", "));
+                        Some((func, message))
+                    }
+                })
+                .collect();
+        }
+
+        if violations.is_empty() {
+            return Ok(ExitCode::SUCCESS);
+        }
+        eprintln!(
+            "{}",
+            format!(
+                "{} {} complexity limit violation(s):",
+                icon("✗", "[x]"),
+                violations.len()
+            )
+            .bold()
+            .red()
+        );
+        for (_, violation) in &violations {
+            eprintln!("  {}", violation);
+        }
+        Ok(ExitCode::from(EXIT_LIMIT_VIOLATED))
+    }
+}
+
+#[derive(Args)]
+struct FileArgs {
+    /// Path to the Rust file, or `-` to read stdin
+    #[arg(short, long)]
+    path: PathBuf,
+
+    /// Show detailed breakdown
+    #[arg(short, long)]
+    detailed: bool,
+
+    /// Filter by complexity threshold
+    #[arg(long)]
+    threshold: Option<usize>,
+
+    /// Analyze the items that parse even if the file has syntax errors
+    #[arg(long)]
+    lenient: bool,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+
+    #[command(flatten)]
+    fail_on: FailOnArgs,
+
+    /// TOML file overriding the score weights
+    #[arg(long)]
+    weights: Option<PathBuf>,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(Args)]
+struct DirArgs {
+    /// Directory path
+    #[arg(short, long)]
+    path: PathBuf,
+
+    /// Include subdirectories
+    #[arg(short, long)]
+    recursive: bool,
+
+    #[command(flatten)]
+    walk: WalkArgs,
+
+    #[command(flatten)]
+    filters: FilterArgs,
+
+    #[command(flatten)]
+    order: OrderArgs,
+
+    /// List functions under their file, hottest file first
+    #[arg(long)]
+    group_by_file: bool,
+
+    #[command(flatten)]
+    fail_on: FailOnArgs,
+
+    /// TOML file overriding the score weights
+    #[arg(long)]
+    weights: Option<PathBuf>,
+
+    /// Export results to JSON (`-` for stdout)
+    #[arg(long)]
+    export: Option<PathBuf>,
+
+    /// Write functions exceeding the limits as a SARIF 2.1.0 file (`-` for stdout)
+    #[arg(long)]
+    export_sarif: Option<PathBuf>,
+
+    #[command(flatten)]
+    limits: LintArgs,
+
+    /// Reuse results for unchanged files from this cache directory
+    #[arg(long, num_args = 0..=1, default_missing_value = AnalysisCache::DEFAULT_DIR)]
+    cache: Option<PathBuf>,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Directory path
+    #[arg(short, long)]
+    path: PathBuf,
+
+    /// Descend at most N directories below the path [default: no limit]
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    #[command(flatten)]
+    walk: WalkArgs,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    /// Flag files whose average maintainability index is below this value
+    #[arg(long, default_value = "20")]
+    mi_floor: f64,
+
+    /// Write the summary as JSON (`-` for stdout)
+    #[arg(long)]
+    export: Option<PathBuf>,
+
+    /// Reuse results for unchanged files from this cache directory
+    #[arg(long, num_args = 0..=1, default_missing_value = AnalysisCache::DEFAULT_DIR)]
+    cache: Option<PathBuf>,
+
+    #[command(flatten)]
+    output: Output,
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Analyze a single file
-    File {
-        /// Path to the Rust file
+    File(FileArgs),
+    /// Analyze all Rust files in a directory
+    Dir(Box<DirArgs>),
+    /// Show complexity statistics
+    Stats(StatsArgs),
+    /// Analyze struct and enum definitions
+    Types {
+        /// Path to a Rust file or directory
         #[arg(short, long)]
         path: PathBuf,
 
-        /// Show detailed breakdown
-        #[arg(short, long)]
-        detailed: bool,
+        #[command(flatten)]
+        walk: WalkArgs,
 
-        /// Filter by complexity threshold
+        /// Export results to JSON (`-` for stdout)
         #[arg(long)]
-        threshold: Option<usize>,
+        export: Option<PathBuf>,
+
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+
+        #[command(flatten)]
+        output: Output,
     },
-    /// Analyze all Rust files in a directory
-    Dir {
-        /// Directory path
+    /// Aggregate method complexity per impl block
+    Impls {
+        /// Path to a Rust file or directory
         #[arg(short, long)]
         path: PathBuf,
 
-        /// Include subdirectories
-        #[arg(short, long)]
-        recursive: bool,
+        #[command(flatten)]
+        walk: WalkArgs,
 
-        /// Show only high complexity functions
-        #[arg(long)]
-        high_only: bool,
+        #[command(flatten)]
+        analysis: AnalysisArgs,
 
-        /// Export results to JSON
-        #[arg(long)]
-        export: Option<PathBuf>,
+        #[command(flatten)]
+        output: Output,
     },
-    /// Show complexity statistics
-    Stats {
+    /// Write a shareable Markdown or HTML report
+    Report {
         /// Directory path
         #[arg(short, long)]
         path: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = ReportFormat::Markdown)]
+        format: ReportFormat,
+
+        /// Report file; printed to stdout when omitted
+        #[arg(long)]
+        out: Option<PathBuf>,
+
+        /// Number of functions in the worst-functions table
+        #[arg(long, default_value_t = report::DEFAULT_WORST_N)]
+        top: usize,
+
+        #[command(flatten)]
+        walk: WalkArgs,
+
+        #[command(flatten)]
+        analysis: AnalysisArgs,
+    },
+    /// Compare function complexity with a git revision
+    Diff(DiffArgs),
+    /// Manage copartner.toml
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Write a commented default copartner.toml
+    Init {
+        /// Where to write the file
+        #[arg(default_value = "copartner.toml")]
+        path: PathBuf,
+
+        /// Overwrite an existing file
+        #[arg(long)]
+        force: bool,
     },
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+impl Commands {
+    /// What the subcommand analyzes; config discovery starts there
+    fn target(&self) -> Option<&Path> {
+        match self {
+            // Piped sources pick up the config of the working directory
+            Commands::File(args) if is_stdio(&args.path) => Some(Path::new(".")),
+            Commands::File(args) => Some(&args.path),
+            Commands::Dir(args) => Some(&args.path),
+            Commands::Diff(args) => Some(&args.path),
+            Commands::Stats(args) => Some(&args.path),
+            Commands::Types { path, .. }
+            | Commands::Impls { path, .. }
+            | Commands::Report { path, .. } => Some(path),
+            Commands::Config { .. } => None,
+        }
+    }
+
+    /// Take every option not given on the command line from `config`
+    fn apply_config(&mut self, config: &CopartnerConfig) -> Result<()> {
+        match self {
+            Commands::File(args) => {
+                args.analysis.fill(config);
+                args.fail_on.fill(config)?;
+                args.output.fill(config)?;
+            }
+            Commands::Dir(args) => {
+                args.analysis.fill(config);
+                args.walk.fill(config);
+                args.fail_on.fill(config)?;
+                args.output.fill(config)?;
+                if args.export.is_none() {
+                    args.export = config.output.export.clone();
+                }
+            }
+            Commands::Diff(args) => {
+                args.analysis.fill(config);
+                args.output.fill(config)?;
+            }
+            Commands::Stats(args) => {
+                args.analysis.fill(config);
+                args.walk.fill(config);
+                args.output.fill(config)?;
+            }
+            Commands::Types {
+                walk,
+                analysis,
+                output,
+                ..
+            }
+            | Commands::Impls {
+                walk,
+                analysis,
+                output,
+                ..
+            } => {
+                analysis.fill(config);
+                walk.fill(config);
+                output.fill(config)?;
+            }
+            Commands::Report { walk, analysis, .. } => {
+                analysis.fill(config);
+                walk.fill(config);
+            }
+            Commands::Config { .. } => {}
+        }
+        Ok(())
+    }
+}
+
+/// Parse a config value the way clap parses the matching flag
+fn config_value<T: ValueEnum>(key: &str, value: &str) -> Result<T> {
+    T::from_str(value, true).map_err(|_| {
+        let expected: Vec<String> = T::value_variants()
+            .iter()
+            .filter_map(|v| v.to_possible_value())
+            .map(|v| format!("\"{}\"", v.get_name()))
+            .collect();
+        anyhow::anyhow!(
+            "Invalid value \"{}\" for `{}` in config, expected one of {}",
+            value,
+            key,
+            expected.join(", ")
+        )
+    })
+}
+
+#[derive(Args)]
+struct DiffArgs {
+    /// Directory or file inside a git work tree
+    #[arg(short, long, default_value = ".")]
+    path: PathBuf,
+
+    /// Revision to compare the working tree with, e.g. `main` or `HEAD~1`
+    #[arg(long, value_name = "REF")]
+    against: String,
+
+    /// Exit with code 2 when a function's cyclomatic complexity grew by more than N
+    #[arg(long, value_name = "N")]
+    fail_on_increase: Option<usize>,
+
+    #[command(flatten)]
+    analysis: AnalysisArgs,
+
+    #[command(flatten)]
+    output: Output,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    Markdown,
+    Html,
+}
+
+fn main() -> Result<ExitCode> {
+    let mut cli = Cli::parse();
+    cli_style::init(cli.no_color);
+    let config_path = match &cli.config {
+        Some(path) => Some(path.clone()),
+        None => cli.command.target().and_then(CopartnerConfig::discover),
+    };
+    if let Some(path) = config_path {
+        let config = CopartnerConfig::load(&path)?;
+        cli.command
+            .apply_config(&config)
+            .with_context(|| format!("Invalid config: {}", path.display()))?;
+    }
 
     match cli.command {
-        Commands::File {
+        // The only subcommands with `--fail-on-*` limits, so they pick the exit code
+        Commands::File(args) => return analyze_single_file(&args, &args.output),
+        Commands::Dir(args) => return analyze_directory(&args, &args.output),
+        Commands::Diff(args) => return diff_against_revision(&args, &args.output),
+        Commands::Config {
+            action: ConfigAction::Init { path, force },
+        } => init_config(&path, force)?,
+        Commands::Stats(args) => show_statistics(&args, &args.output)?,
+        Commands::Types {
             path,
-            detailed,
-            threshold,
+            walk,
+            export,
+            analysis,
+            output,
         } => {
-            analyze_single_file(path, detailed, threshold)?;
+            analyze_types(path, export, &walk.options(&analysis)?, &output)?;
+        }
+        Commands::Impls {
+            path,
+            walk,
+            analysis,
+            output,
+        } => {
+            analyze_impls(path, &walk.options(&analysis)?, &output)?;
+        }
+        Commands::Report {
+            path,
+            format,
+            out,
+            top,
+            walk,
+            analysis,
+        } => {
+            write_report(
+                &path,
+                format,
+                out.as_deref(),
+                top,
+                &walk.options(&analysis)?,
+            )?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn init_config(path: &Path, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists, pass --force to overwrite it",
+            path.display()
+        );
+    }
+    fs::write(path, CopartnerConfig::TEMPLATE)
+        .with_context(|| format!("Failed to write config: {}", path.display()))?;
+    println!(
+        "{}",
+        format!("{} Wrote {}", icon("✓", "[ok]"), path.display()).green()
+    );
+    Ok(())
+}
+
+/// The listed functions of one file, for `dir --group-by-file`
+#[derive(Serialize)]
+struct FileGroup<'a> {
+    /// Relative to the analyzed directory
+    file: &'a Path,
+    function_count: usize,
+    average_cyclomatic: f64,
+    max_cyclomatic: usize,
+    functions: Vec<&'a FunctionComplexity>,
+}
+
+impl<'a> FileGroup<'a> {
+    /// Group `functions` by file, keeping their order within each file;
+    /// files with the highest max complexity come first
+    fn collect(functions: &'a [FunctionComplexity], root: &Path) -> Vec<Self> {
+        let mut files: BTreeMap<&Path, Vec<&FunctionComplexity>> = BTreeMap::new();
+        for func in functions {
+            let file = func.file.as_deref().unwrap_or(Path::new(""));
+            files
+                .entry(file.strip_prefix(root).unwrap_or(file))
+                .or_default()
+                .push(func);
+        }
+        let mut groups: Vec<Self> = files
+            .into_iter()
+            .map(|(file, functions)| {
+                let total: usize = functions.iter().map(|f| f.cyclomatic_complexity).sum();
+                FileGroup {
+                    file,
+                    function_count: functions.len(),
+                    average_cyclomatic: total as f64 / functions.len() as f64,
+                    max_cyclomatic: functions
+                        .iter()
+                        .map(|f| f.cyclomatic_complexity)
+                        .max()
+                        .unwrap_or(0),
+                    functions,
+                }
+            })
+            .collect();
+        groups.sort_by_key(|group| std::cmp::Reverse(group.max_cyclomatic));
+        groups
+    }
+
+    fn export(&self) -> serde_json::Value {
+        serde_json::json!({
+            "file": self.file.display().to_string(),
+            "function_count": self.function_count,
+            "average_cyclomatic": (self.average_cyclomatic * 100.0).round() / 100.0,
+            "max_cyclomatic": self.max_cyclomatic,
+            "functions": self.functions.iter().map(|f| export_record(f)).collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn print_file_group(group: &FileGroup) {
+    println!(
+        "{} {} {}",
+        icon("📁", "-"),
+        group.file.display().to_string().bold(),
+        format!(
+            "({} functions, average cyclomatic {:.2}, max {})",
+            group.function_count, group.average_cyclomatic, group.max_cyclomatic
+        )
+        .dimmed()
+    );
+    for func in &group.functions {
+        let color = match func.return_complexity {
+            ComplexityRating::Low => "green",
+            ComplexityRating::Medium => "yellow",
+            ComplexityRating::High => "red",
+            ComplexityRating::VeryHigh => "bright_red",
+        };
+        println!(
+            "    {} {} cyclomatic {}, cognitive {}, {}",
+            func.name.color(color).bold(),
+            format!("line {}", func.start_line).dimmed(),
+            func.cyclomatic_complexity,
+            func.cognitive_complexity,
+            func.return_complexity
+        );
+    }
+    println!();
+}
+
+/// One function whose complexity differs from the revision
+#[derive(Serialize)]
+struct DiffRecord<'a> {
+    file: &'a Path,
+    file_status: FileStatus,
+    /// `changed`, `renamed`, `added` or `removed`
+    change: &'static str,
+    name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_name: Option<&'a str>,
+    before: Option<&'a MetricSnapshot>,
+    after: Option<&'a MetricSnapshot>,
+    cyclomatic_delta: isize,
+    cognitive_delta: isize,
+}
+
+impl<'a> DiffRecord<'a> {
+    fn collect(file_diff: &'a FileDiff) -> Vec<Self> {
+        let record = |change, name, old_name, before, after| {
+            let metric = |m: Option<&MetricSnapshot>, f: fn(&MetricSnapshot) -> usize| {
+                m.map_or(0, f) as isize
+            };
+            DiffRecord {
+                file: &file_diff.file,
+                file_status: file_diff.status,
+                change,
+                name,
+                old_name,
+                before,
+                after,
+                cyclomatic_delta: metric(after, |m| m.cyclomatic_complexity)
+                    - metric(before, |m| m.cyclomatic_complexity),
+                cognitive_delta: metric(after, |m| m.cognitive_complexity)
+                    - metric(before, |m| m.cognitive_complexity),
+            }
+        };
+        let diff = &file_diff.diff;
+        let mut records = Vec::new();
+        for (kind, changes) in [("changed", &diff.changed), ("renamed", &diff.renamed)] {
+            for c in changes {
+                let old_name = (c.old_name != c.new_name).then_some(c.old_name.as_str());
+                let r = record(kind, &c.new_name, old_name, Some(&c.before), Some(&c.after));
+                // Line or parameter count changes alone are not listed
+                if r.cyclomatic_delta != 0 || r.cognitive_delta != 0 {
+                    records.push(r);
+                }
+            }
         }
-        Commands::Dir {
-            path,
-            recursive,
-            high_only,
-            export,
-        } => {
-            analyze_directory(path, recursive, high_only, export)?;
+        for entry in &diff.added {
+            records.push(record(
+                "added",
+                &entry.name,
+                None,
+                None,
+                Some(&entry.metrics),
+            ));
         }
-        Commands::Stats { path } => {
-            show_statistics(path)?;
+        for entry in &diff.removed {
+            records.push(record(
+                "removed",
+                &entry.name,
+                None,
+                Some(&entry.metrics),
+                None,
+            ));
         }
+        records
     }
+}
 
-    Ok(())
+fn colored_delta(delta: isize) -> ColoredString {
+    let text = format!("{:+}", delta);
+    match delta.cmp(&0) {
+        std::cmp::Ordering::Greater => text.red(),
+        std::cmp::Ordering::Less => text.green(),
+        std::cmp::Ordering::Equal => text.dimmed(),
+    }
 }
 
-fn analyze_single_file(path: PathBuf, detailed: bool, threshold: Option<usize>) -> Result<()> {
-    println!(
+fn print_diff_record(record: &DiffRecord) {
+    let name = match record.old_name {
+        Some(old) => format!("{} (was {})", record.name, old),
+        None => record.name.to_string(),
+    };
+    match (record.before, record.after) {
+        (Some(before), Some(after)) => println!(
+            "  ~ {}: cyclomatic {} → {} ({}), cognitive {} → {} ({})",
+            name.bold(),
+            before.cyclomatic_complexity,
+            after.cyclomatic_complexity,
+            colored_delta(record.cyclomatic_delta),
+            before.cognitive_complexity,
+            after.cognitive_complexity,
+            colored_delta(record.cognitive_delta)
+        ),
+        (None, Some(after)) => println!(
+            "  {} {}: cyclomatic {}, cognitive {}",
+            "+".red(),
+            name.bold(),
+            after.cyclomatic_complexity,
+            after.cognitive_complexity
+        ),
+        (Some(before), None) => println!(
+            "  {} {}: cyclomatic {}, cognitive {}",
+            "-".green(),
+            name.bold(),
+            before.cyclomatic_complexity,
+            before.cognitive_complexity
+        ),
+        (None, None) => {}
+    }
+}
+
+fn diff_against_revision(args: &DiffArgs, out: &Output) -> Result<ExitCode> {
+    out.status(
+        format!("Comparing {} with {}", args.path.display(), args.against)
+            .bold()
+            .blue(),
+    );
+    let options = args.analysis.options()?;
+    let file_diffs = git::diff_against_revision(&args.path, &args.against, &options)?;
+    let records: Vec<DiffRecord> = file_diffs.iter().flat_map(DiffRecord::collect).collect();
+
+    let mut last_file = None;
+    out.records(&records, |record| {
+        if last_file != Some(record.file) {
+            let status = match record.file_status {
+                FileStatus::Added => " (new)",
+                FileStatus::Deleted => " (deleted)",
+                FileStatus::Modified => "",
+            };
+            println!(
+                "{}{}",
+                record.file.display().to_string().bold(),
+                status.dimmed()
+            );
+            last_file = Some(record.file);
+        }
+        print_diff_record(record);
+    })?;
+    out.status(format!("Functions with changed complexity: {}", records.len()).green());
+
+    // Growth only makes sense for functions present on both sides
+    let Some(limit) = args.fail_on_increase else {
+        return Ok(ExitCode::SUCCESS);
+    };
+    let grown: Vec<&DiffRecord> = records
+        .iter()
+        .filter(|r| r.before.is_some() && r.after.is_some() && r.cyclomatic_delta > limit as isize)
+        .collect();
+    if grown.is_empty() {
+        return Ok(ExitCode::SUCCESS);
+    }
+    eprintln!(
         "{}",
-        format!("Analyzing file: {}", path.display()).bold().blue()
+        format!(
+            "{} {} function(s) grew by more than {}:",
+            icon("✗", "[x]"),
+            grown.len(),
+            limit
+        )
+        .bold()
+        .red()
     );
+    for record in grown {
+        eprintln!(
+            "  {}: `{}` cyclomatic complexity {:+}",
+            record.file.display(),
+            record.name,
+            record.cyclomatic_delta
+        );
+    }
+    Ok(ExitCode::from(EXIT_LIMIT_VIOLATED))
+}
 
-    let content = fs::read_to_string(&path)
-        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+fn write_report(
+    path: &Path,
+    format: ReportFormat,
+    out: Option<&Path>,
+    top: usize,
+    options: &AnalyzeOptions,
+) -> Result<()> {
+    let project = ComplexityAnalyzer::analyze_project(path, options, None)?;
+    let content = match format {
+        ReportFormat::Markdown => report::render_markdown(&project, top),
+        ReportFormat::Html => report::render_html(&project, top),
+    };
 
-    let functions = ComplexityAnalyzer::analyze_file(&content)?;
+    match out {
+        Some(out) => {
+            fs::write(out, content)
+                .with_context(|| format!("Failed to write report: {}", out.display()))?;
+            println!(
+                "{}",
+                format!("Report written to: {}", out.display()).green()
+            );
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
 
-    if functions.is_empty() {
-        println!("{}", "No functions found in the file.".yellow());
-        return Ok(());
+fn load_weights(path: Option<&Path>) -> Result<ScoreWeights> {
+    match path {
+        Some(path) => ScoreWeights::load(path),
+        None => Ok(ScoreWeights::default()),
     }
+}
 
-    for func in &functions {
-        if let Some(thresh) = threshold {
-            if func.cyclomatic_complexity < thresh {
-                continue;
-            }
+fn analyze_single_file(args: &FileArgs, out: &Output) -> Result<ExitCode> {
+    let path = &args.path;
+    let options = &args.analysis.options()?;
+    let weights = load_weights(args.weights.as_deref())?;
+    out.status(format!("Analyzing file: {}", path.display()).bold().blue());
+
+    let (content, label) = read_source(path)?;
+    let mut functions = if args.lenient {
+        let analysis = ComplexityAnalyzer::analyze_file_lenient_with(&content, options);
+        for (start, end) in &analysis.unparsed {
+            out.status(
+                format!(
+                    "{}  Could not parse lines {}-{}",
+                    icon("⚠️", "[!]"),
+                    start,
+                    end
+                )
+                .yellow(),
+            );
         }
+        analysis.functions
+    } else {
+        ComplexityAnalyzer::analyze_file_with(&content, options)
+            .with_context(|| format!("Failed to analyze {}", label.display()))?
+    };
+    for func in &mut functions {
+        func.file = Some(label.clone());
+    }
 
-        print_function_complexity(func, detailed);
-        println!();
+    if functions.is_empty() && out.is_pretty() {
+        out.status("No functions found in the file.".yellow());
+        return Ok(ExitCode::SUCCESS);
     }
 
-    println!(
-        "{}",
-        format!("Total functions analyzed: {}", functions.len()).green()
-    );
-    Ok(())
+    let functions = args.filters.scope(functions)?;
+    let total = functions.len();
+    let functions = args.filters.apply(functions);
+    let shown: Vec<&FunctionComplexity> = match args.threshold {
+        Some(threshold) => functions
+            .iter()
+            .filter(|f| !f.suppressed)
+            .collect::<Vec<_>>()
+            .filter_min(Metric::Cyclomatic, threshold),
+        None => functions.iter().collect(),
+    };
+    out.records(&shown, |func| {
+        print_function_complexity(func, args.detailed, &weights);
+        println!();
+    })?;
+
+    out.status(format!("Total functions analyzed: {}", total).green());
+    args.fail_on
+        .check(&functions, path.parent().unwrap_or(Path::new("")))
 }
 
-fn analyze_directory(
-    path: PathBuf,
-    recursive: bool,
-    high_only: bool,
-    export: Option<PathBuf>,
-) -> Result<()> {
-    println!(
-        "{}",
+fn analyze_directory(args: &DirArgs, out: &Output) -> Result<ExitCode> {
+    let path = &args.path;
+    let weights = load_weights(args.weights.as_deref())?;
+    out.status(
         format!("Analyzing directory: {}", path.display())
             .bold()
-            .blue()
+            .blue(),
     );
 
-    let mut all_functions = Vec::new();
-    let mut file_count = 0;
-
-    let walker = if recursive {
-        WalkDir::new(&path).follow_links(true)
-    } else {
-        WalkDir::new(&path).max_depth(1)
+    let options = AnalyzeOptions {
+        max_depth: if args.recursive { None } else { Some(1) },
+        ..args.walk.options(&args.analysis)?
     };
+    let lint_config = args.limits.config(&options.thresholds);
+    let project = analyze_project_cached(path, &options, args.cache.as_deref(), out)?;
 
-    for entry in walker {
-        let entry = entry.context("Failed to read directory entry")?;
-        let path = entry.path();
-
-        if path.extension().map_or(false, |ext| ext == "rs") {
-            let content = match fs::read_to_string(path) {
-                Ok(content) => content,
-                Err(_) => continue,
-            };
-
-            match ComplexityAnalyzer::analyze_file(&content) {
-                Ok(functions) => {
-                    println!("  📁 {}: {} functions", path.display(), functions.len());
-                    all_functions.extend(functions);
-                    file_count += 1;
-                }
-                Err(e) => {
-                    println!("  ⚠️  Failed to analyze {}: {}", path.display(), e);
-                }
-            }
-        }
+    for (file, summary) in &project.files {
+        out.status(format!(
+            "  {} {}: {} functions",
+            icon("📁", "-"),
+            path.join(file).display(),
+            summary.functions.len()
+        ));
+    }
+    for (file, reason) in &project.skipped {
+        out.status(format!(
+            "  {}  Failed to analyze {}: {}",
+            icon("⚠️", "[!]"),
+            file.display(),
+            reason
+        ));
     }
 
-    // Filter and sort results
-    if high_only {
-        all_functions.retain(|f| {
-            matches!(
-                f.return_complexity,
-                ComplexityRating::High | ComplexityRating::VeryHigh
+    let file_count = project.files.len();
+    let functions = args.filters.scope(project.into_functions())?;
+
+    // Findings cover every function in scope, regardless of the display filters
+    if let Some(sarif_path) = &args.export_sarif {
+        let violations = lint_functions(&functions, &lint_config);
+        let sarif = serde_json::to_string_pretty(&to_sarif(&violations))?;
+        write_export(sarif_path, &sarif)?;
+        out.status(
+            format!(
+                "SARIF with {} findings written to: {}",
+                violations.len(),
+                export_target(sarif_path)
             )
-        });
+            .green(),
+        );
     }
 
-    all_functions.sort_by(|a, b| b.cyclomatic_complexity.cmp(&a.cyclomatic_complexity));
+    // Filter and sort results; `--limit` only shortens the listing, limits
+    // are still checked on every matching function
+    let matching = args.order.sort(args.filters.apply(functions));
+    let listed = args.order.limit(&matching);
 
-    println!("\n{}", "=== Analysis Results ===".bold().green());
+    out.status(format!("\n{}", "=== Analysis Results ===".bold().green()));
 
-    for func in &all_functions {
-        print_function_complexity(func, false);
-        println!();
+    if args.group_by_file {
+        let groups = FileGroup::collect(listed, path);
+        out.records(&groups, print_file_group)?;
+        if let Some(export_path) = &args.export {
+            let groups: Vec<serde_json::Value> = groups.iter().map(FileGroup::export).collect();
+            write_json_export(&groups, export_path, out)?;
+        }
+    } else {
+        out.records(listed, |func| {
+            print_function_complexity(func, false, &weights);
+            println!();
+        })?;
+        if let Some(export_path) = &args.export {
+            let records: Vec<serde_json::Value> = listed.iter().map(export_record).collect();
+            write_json_export(&records, export_path, out)?;
+        }
     }
 
-    // Export if requested
-    if let Some(export_path) = export {
-        export_to_json(&all_functions, export_path)?;
+    let mut found = format!(
+        "Files processed: {}, Functions found: {}",
+        file_count,
+        matching.len()
+    );
+    if listed.len() < matching.len() {
+        found.push_str(&format!(" (showing {})", listed.len()));
     }
+    out.status(found.green());
+    args.fail_on.check(&matching, path)
+}
 
-    println!(
-        "{}",
+/// Run the project analysis, going through the cache in `cache_dir` if given
+fn analyze_project_cached(
+    path: &Path,
+    options: &AnalyzeOptions,
+    cache_dir: Option<&Path>,
+    out: &Output,
+) -> Result<ProjectComplexity> {
+    let Some(dir) = cache_dir else {
+        return ComplexityAnalyzer::analyze_project(path, options, None);
+    };
+
+    let mut cache = AnalysisCache::load(dir)?;
+    let project = ComplexityAnalyzer::analyze_project(path, options, Some(&mut cache))?;
+    cache.prune_unused();
+    cache.save(dir)?;
+    out.status(
         format!(
-            "Files processed: {}, Functions found: {}",
-            file_count,
-            all_functions.len()
+            "  {} Cache: {} hits, {} misses",
+            icon("💾", "-"),
+            cache.hits,
+            cache.misses
         )
-        .green()
+        .dimmed(),
     );
-    Ok(())
+    Ok(project)
 }
 
-fn show_statistics(path: PathBuf) -> Result<()> {
-    println!(
-        "{}",
+fn show_statistics(args: &StatsArgs, out: &Output) -> Result<()> {
+    let path = &args.path;
+    let mi_floor = args.mi_floor;
+    out.status(
         format!("Generating statistics for: {}", path.display())
             .bold()
-            .blue()
+            .blue(),
     );
 
-    let mut all_functions = Vec::new();
-
-    for entry in WalkDir::new(&path).follow_links(true) {
-        let entry = entry.context("Failed to read directory entry")?;
-        let path = entry.path();
-
-        if path.extension().map_or(false, |ext| ext == "rs") {
-            let content = match fs::read_to_string(path) {
-                Ok(content) => content,
-                Err(_) => continue,
-            };
+    // Depth 1 is the directory's own files, as for `dir` without --recursive
+    let options = AnalyzeOptions {
+        max_depth: args.max_depth.map(|depth| depth + 1),
+        ..args.walk.options(&args.analysis)?
+    };
+    let project = analyze_project_cached(path, &options, args.cache.as_deref(), out)?;
+    for (file, reason) in &project.skipped {
+        out.status(format!(
+            "  {}  Skipped {}: {}",
+            icon("⚠️", "[!]"),
+            file.display(),
+            reason
+        ));
+    }
 
-            if let Ok(functions) = ComplexityAnalyzer::analyze_file(&content) {
-                all_functions.extend(functions);
-            }
-        }
+    if let Some(export_path) = &args.export {
+        let summary = serde_json::to_string_pretty(&project.summary())?;
+        write_export(export_path, &summary)?;
+        out.status(format!("Summary exported to: {}", export_target(export_path)).green());
+    }
+    if !out.is_pretty() {
+        // The summary is the single record of this subcommand
+        return out.records(&[project.summary()], |_| {});
     }
 
-    if all_functions.is_empty() {
+    let mut low_mi_files: Vec<_> = project
+        .files
+        .iter()
+        .filter(|(_, summary)| {
+            !summary.functions.is_empty() && summary.maintainability_index < mi_floor
+        })
+        .map(|(file, summary)| (path.join(file), summary.maintainability_index))
+        .collect();
+
+    if project.total_functions == 0 {
         println!("{}", "No functions found.".yellow());
         return Ok(());
     }
 
-    // Calculate statistics
-    let total = all_functions.len();
-    let low = all_functions
-        .iter()
-        .filter(|f| f.return_complexity == ComplexityRating::Low)
-        .count();
-    let medium = all_functions
-        .iter()
-        .filter(|f| f.return_complexity == ComplexityRating::Medium)
-        .count();
-    let high = all_functions
-        .iter()
-        .filter(|f| f.return_complexity == ComplexityRating::High)
-        .count();
-    let very_high = all_functions
-        .iter()
-        .filter(|f| f.return_complexity == ComplexityRating::VeryHigh)
-        .count();
-
-    let avg_cyclomatic: f64 = all_functions
-        .iter()
-        .map(|f| f.cyclomatic_complexity)
-        .sum::<usize>() as f64
-        / total as f64;
-    let avg_cognitive: f64 = all_functions
-        .iter()
-        .map(|f| f.cognitive_complexity)
-        .sum::<usize>() as f64
-        / total as f64;
+    let summary = project.summary();
+    let total = summary.total_functions;
 
     println!("\n{}", "=== Complexity Statistics ===".bold().green());
     println!("Total functions: {}", total.to_string().bold());
     println!(
-        "Average Cyclomatic Complexity: {:.2}",
-        format!("{:.2}", avg_cyclomatic).yellow()
+        "Average Cyclomatic Complexity: {}",
+        format!("{:.2}", summary.average_cyclomatic).yellow()
+    );
+    println!(
+        "Average Cognitive Complexity: {}",
+        format!("{:.2}", summary.average_cognitive).yellow()
+    );
+    println!(
+        "Average Maintainability Index: {}",
+        format!("{:.2}", summary.average_maintainability_index).yellow()
     );
     println!(
-        "Average Cognitive Complexity: {:.2}",
-        format!("{:.2}", avg_cognitive).yellow()
+        "Doc Coverage: {}",
+        format!("{:.1}%", summary.doc_coverage).yellow()
     );
+    if summary.suppressed > 0 {
+        println!(
+            "Suppressed functions: {}",
+            summary.suppressed.to_string().dimmed()
+        );
+    }
     println!();
 
+    let distribution = &summary.distribution;
     println!("{}", "Complexity Distribution:".bold());
     println!(
         "  {} Low:       {} ({:.1}%)",
-        "🟢".green(),
-        low,
-        (low as f64 / total as f64) * 100.0
+        icon("🟢", "[L]").green(),
+        distribution.low,
+        distribution.percentage(&ComplexityRating::Low)
     );
     println!(
         "  {} Medium:    {} ({:.1}%)",
-        "🟡".yellow(),
-        medium,
-        (medium as f64 / total as f64) * 100.0
+        icon("🟡", "[M]").yellow(),
+        distribution.medium,
+        distribution.percentage(&ComplexityRating::Medium)
     );
     println!(
         "  {} High:      {} ({:.1}%)",
-        "🟠".red(),
-        high,
-        (high as f64 / total as f64) * 100.0
+        icon("🟠", "[H]").red(),
+        distribution.high,
+        distribution.percentage(&ComplexityRating::High)
     );
     println!(
         "  {} Very High: {} ({:.1}%)",
-        "🔴".red(),
-        very_high,
-        (very_high as f64 / total as f64) * 100.0
+        icon("🔴", "[VH]").red(),
+        distribution.very_high,
+        distribution.percentage(&ComplexityRating::VeryHigh)
     );
 
+    if !low_mi_files.is_empty() {
+        low_mi_files.sort_by(|a, b| a.1.total_cmp(&b.1));
+        println!(
+            "\n{}",
+            format!("Files below maintainability floor ({:.1}):", mi_floor)
+                .bold()
+                .red()
+        );
+        for (file, mi) in &low_mi_files {
+            println!("  {} (MI: {})", file.display(), format!("{:.2}", mi).red());
+        }
+    }
+
     // Show most complex functions
-    all_functions.sort_by(|a, b| b.cyclomatic_complexity.cmp(&a.cyclomatic_complexity));
-    println!("\n{}", "Top 5 Most Complex Functions:".bold().red());
-    for func in all_functions.iter().take(5) {
+    println!(
+        "\n{}",
+        format!(
+            "Top {} Most Complex Functions:",
+            summary.top_functions.len()
+        )
+        .bold()
+        .red()
+    );
+    for func in &summary.top_functions {
         println!(
-            "  {} (complexity: {})",
+            "  {} (complexity: {}) {}",
             func.name.bright_white(),
-            func.cyclomatic_complexity.to_string().red()
+            func.cyclomatic_complexity.to_string().red(),
+            func.location().dimmed()
         );
     }
 
     Ok(())
 }
 
-fn print_function_complexity(func: &FunctionComplexity, detailed: bool) {
+/// A type together with the file it was found in, relative to the analyzed root
+#[derive(Serialize)]
+struct TypeRecord {
+    file: PathBuf,
+    #[serde(flatten)]
+    ty: TypeComplexity,
+}
+
+fn analyze_types(
+    path: PathBuf,
+    export: Option<PathBuf>,
+    options: &AnalyzeOptions,
+    out: &Output,
+) -> Result<()> {
+    out.status(
+        format!("Analyzing types in: {}", path.display())
+            .bold()
+            .blue(),
+    );
+
+    let files: Vec<PathBuf> = if path.is_dir() {
+        walk::rust_files(&path, &options.selection, options.max_depth)?.files
+    } else {
+        vec![path.clone()]
+    };
+
+    let mut all_types: Vec<TypeRecord> = Vec::new();
+    for file in files {
+        let content = fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read file: {}", file.display()))?;
+        match ComplexityAnalyzer::analyze_types_with(&content, options) {
+            Ok(types) => {
+                let relative = file.strip_prefix(&path).unwrap_or(&file).to_path_buf();
+                all_types.extend(types.into_iter().map(|ty| TypeRecord {
+                    file: relative.clone(),
+                    ty,
+                }));
+            }
+            Err(e) => out.status(format!(
+                "{} Failed to analyze {}: {}",
+                icon("⚠️", "[!]"),
+                file.display(),
+                e
+            )),
+        }
+    }
+
+    all_types.sort_by_key(|record| std::cmp::Reverse(record.ty.score()));
+
+    out.records(&all_types, |record| {
+        print_type_complexity(&record.file, &record.ty);
+        println!();
+    })?;
+
+    if let Some(export_path) = export {
+        export_types_to_json(&all_types, export_path, out)?;
+    }
+
+    out.status(format!("Total types analyzed: {}", all_types.len()).green());
+    Ok(())
+}
+
+fn analyze_impls(path: PathBuf, options: &AnalyzeOptions, out: &Output) -> Result<()> {
+    out.status(
+        format!("Analyzing impl blocks in: {}", path.display())
+            .bold()
+            .blue(),
+    );
+
+    let functions = if path.is_dir() {
+        let project = ComplexityAnalyzer::analyze_project(&path, options, None)?;
+        for (file, error) in &project.skipped {
+            out.status(format!(
+                "{} Failed to analyze {}: {}",
+                icon("⚠️", "[!]"),
+                file.display(),
+                error
+            ));
+        }
+        project.into_functions()
+    } else {
+        ComplexityAnalyzer::analyze_file_at_with(&path, options)?
+    };
+
+    let impls = ImplComplexity::group(&functions, &options.thresholds);
+    out.records(&impls, print_impl_complexity)?;
+
+    out.status(format!("Total impl blocks analyzed: {}", impls.len()).green());
+    Ok(())
+}
+
+fn print_impl_complexity(imp: &ImplComplexity) {
+    let color = match imp.rating {
+        ComplexityRating::Low => "green",
+        ComplexityRating::Medium => "yellow",
+        ComplexityRating::High => "red",
+        ComplexityRating::VeryHigh => "bright_red",
+    };
+    let location = imp
+        .file
+        .as_ref()
+        .map(|f| f.display().to_string())
+        .unwrap_or_default();
+
+    println!(
+        "{} {}",
+        imp.display_name().color(color).bold(),
+        location.dimmed()
+    );
+    println!(
+        "  {} {}  {} {}  {} {:.1}  {} {}",
+        "Methods:".bright_blue(),
+        imp.method_count,
+        "Total:".bright_blue(),
+        imp.total_cyclomatic,
+        "Average:".bright_blue(),
+        imp.average_cyclomatic(),
+        "Max:".bright_blue(),
+        imp.max_cyclomatic
+    );
+    println!(
+        "  {} {}",
+        "Rating:".bright_blue(),
+        format!("{}", imp.rating).color(color)
+    );
+    println!();
+}
+
+fn print_type_complexity(file: &Path, ty: &TypeComplexity) {
+    let color = match ty.rating {
+        ComplexityRating::Low => "green",
+        ComplexityRating::Medium => "yellow",
+        ComplexityRating::High => "red",
+        ComplexityRating::VeryHigh => "bright_red",
+    };
+    let location = if file.as_os_str().is_empty() {
+        format!("line {}", ty.start_line)
+    } else {
+        format!("{}:{}", file.display(), ty.start_line)
+    };
+
+    println!(
+        "{} {} {}",
+        format!("{}:", ty.kind).bold(),
+        ty.name.color(color).bold(),
+        location.dimmed()
+    );
+    println!("  {} {}", "Fields:".bright_blue(), ty.field_count);
+    if ty.variant_count > 0 {
+        println!("  {} {}", "Variants:".bright_blue(), ty.variant_count);
+    }
+    println!(
+        "  {} {}",
+        "Generic params:".bright_blue(),
+        ty.generic_parameters
+    );
+    println!("  {} {}", "Derives:".bright_blue(), ty.derive_count);
+    println!(
+        "  {} {}",
+        "Rating:".bright_blue(),
+        format!("{}", ty.rating).color(color)
+    );
+}
+
+fn export_types_to_json(types: &[TypeRecord], path: PathBuf, out: &Output) -> Result<()> {
+    write_export(&path, &serde_json::to_string_pretty(types)?)?;
+    out.status(format!("Results exported to: {}", export_target(&path)).green());
+    Ok(())
+}
+
+fn print_function_complexity(func: &FunctionComplexity, detailed: bool, weights: &ScoreWeights) {
     let color = match func.return_complexity {
         ComplexityRating::Low => "green",
         ComplexityRating::Medium => "yellow",
@@ -321,7 +1704,20 @@ fn print_function_complexity(func: &FunctionComplexity, detailed: bool) {
         ComplexityRating::VeryHigh => "bright_red",
     };
 
-    println!("{} {}", "Function:".bold(), func.name.color(color).bold());
+    let recursion_marker = if func.details.is_recursive {
+        icon(" ♻", " (recursive)")
+    } else {
+        ""
+    };
+    let suppressed_marker = if func.suppressed { " (suppressed)" } else { "" };
+    println!(
+        "{} {}{} {}{}",
+        "Function:".bold(),
+        func.name.color(color).bold(),
+        recursion_marker,
+        func.location().dimmed(),
+        suppressed_marker.dimmed()
+    );
 
     println!(
         "  {} {}",
@@ -333,7 +1729,13 @@ fn print_function_complexity(func: &FunctionComplexity, detailed: bool) {
         "Cognitive Complexity:".bright_blue(),
         func.cognitive_complexity
     );
+    println!("  {} {}", "Lines:".bright_blue(), func.line_count);
     println!("  {} {}", "Parameters:".bright_blue(), func.parameter_count);
+    println!(
+        "  {} {}",
+        "Score:".bright_blue(),
+        ComplexityScore::compute(func, weights).value
+    );
     println!(
         "  {} {}",
         "Rating:".bright_blue(),
@@ -341,12 +1743,60 @@ fn print_function_complexity(func: &FunctionComplexity, detailed: bool) {
     );
 
     if detailed {
-        println!("  {} {}", "Details:".bright_cyan().bold(), "");
+        println!("  {}", "Details:".bright_cyan().bold());
+        println!("    Physical lines: {}", func.line_count);
+        println!("    Logical lines: {}", func.logical_lines);
         println!("    If statements: {}", func.details.if_statements);
+        println!("    Let-else branches: {}", func.details.let_else);
         println!("    Match arms: {}", func.details.match_arms);
+        println!("    Match guards: {}", func.details.match_guards);
         println!("    Loops: {}", func.details.loops);
+        println!("    Boolean operators: {}", func.details.boolean_operators);
+        println!("    Try operators: {}", func.details.try_operators);
+        println!("    Await points: {}", func.details.await_points);
+        println!("    Early returns: {}", func.details.early_returns);
+        println!("    Unwrap calls: {}", func.details.unwrap_calls);
+        println!("    Expect calls: {}", func.details.expect_calls);
+        println!("    Panic macros: {}", func.details.panic_macros);
+        let qualifiers: Vec<&str> = [
+            (func.is_const, "const"),
+            (func.is_async, "async"),
+            (func.is_unsafe, "unsafe"),
+        ]
+        .into_iter()
+        .filter_map(|(set, keyword)| set.then_some(keyword))
+        .collect();
+        if !func.details.attributes.is_empty() {
+            println!(
+                "    Attributes: {}",
+                func.details
+                    .attributes
+                    .iter()
+                    .map(|attr| format!("#[{}]", attr))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            );
+        }
+        println!(
+            "    Visibility: {}{}",
+            func.visibility,
+            if qualifiers.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", qualifiers.join(" "))
+            }
+        );
+        println!(
+            "    Documented: {}",
+            if func.has_doc_comment { "yes" } else { "no" }
+        );
+        println!("    Comment density: {:.1}%", func.comment_density * 100.0);
         println!("    Function calls: {}", func.details.function_calls);
         println!("    Max nesting depth: {}", func.details.max_nesting_depth);
+        println!(
+            "    Max expression depth: {}",
+            func.details.max_expression_depth
+        );
 
         // Advanced analysis data
         println!("    Unsafe blocks: {}", func.details.unsafe_blocks);
@@ -354,6 +1804,23 @@ fn print_function_complexity(func: &FunctionComplexity, detailed: bool) {
             "    Generic parameters: {}",
             func.details.generic_parameters
         );
+        println!(
+            "    Signature complexity: {}",
+            func.details.signature_complexity
+        );
+        println!("    Trait bounds: {}", func.details.bound_count);
+
+        println!(
+            "    Halstead: volume {:.1}, difficulty {:.1}, effort {:.1}",
+            func.halstead.volume, func.halstead.difficulty, func.halstead.effort
+        );
+        println!(
+            "      Operators: {} distinct / {} total, Operands: {} distinct / {} total",
+            func.halstead.distinct_operators,
+            func.halstead.total_operators,
+            func.halstead.distinct_operands,
+            func.halstead.total_operands
+        );
 
         if !func.details.function_call_chain.is_empty() {
             println!(
@@ -384,70 +1851,92 @@ fn print_function_complexity(func: &FunctionComplexity, detailed: bool) {
     }
 }
 
-fn export_to_json(functions: &[FunctionComplexity], path: PathBuf) -> Result<()> {
-    use std::io::Write;
+/// Fields of a function in `--export` files
+fn export_record(func: &FunctionComplexity) -> serde_json::Value {
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    let mut record = serde_json::json!({
+        "name": func.name,
+        "start_line": func.start_line,
+        "end_line": func.end_line,
+        "cyclomatic_complexity": func.cyclomatic_complexity,
+        "cognitive_complexity": func.cognitive_complexity,
+        "legacy_cognitive": func.legacy_cognitive,
+        "parameter_count": func.parameter_count,
+        "is_async": func.is_async,
+        "is_const": func.is_const,
+        "is_unsafe": func.is_unsafe,
+        "visibility": func.visibility,
+        "unsafe_blocks": func.details.unsafe_blocks,
+        "generic_parameters": func.details.generic_parameters,
+        "halstead": {
+            "distinct_operators": func.halstead.distinct_operators,
+            "distinct_operands": func.halstead.distinct_operands,
+            "total_operators": func.halstead.total_operators,
+            "total_operands": func.halstead.total_operands,
+            "volume": round(func.halstead.volume),
+            "difficulty": round(func.halstead.difficulty),
+            "effort": round(func.halstead.effort),
+        },
+        "rating": func.return_complexity.to_string(),
+    });
+    if let Some(source) = &func.file {
+        record["file"] = source.display().to_string().into();
+    }
+    // Advanced analysis data
+    for (key, values) in [
+        ("function_call_chain", &func.details.function_call_chain),
+        ("macro_invocations", &func.details.macro_invocations),
+        ("module_dependencies", &func.details.module_dependencies),
+    ] {
+        if !values.is_empty() {
+            record[key] = values.join(", ").into();
+        }
+    }
+    record
+}
+
+fn write_json_export(records: &[serde_json::Value], path: &Path, out: &Output) -> Result<()> {
+    write_export(path, &serde_json::to_string_pretty(records)?)?;
+    out.status(format!("Results exported to: {}", export_target(path)).green());
+    Ok(())
+}
 
-    let mut file = fs::File::create(&path)
-        .with_context(|| format!("Failed to create export file: {}", path.display()))?;
+/// `-` in place of a path means stdin for sources and stdout for exports
+const STDIO_PATH: &str = "-";
 
-    writeln!(file, "[")?;
+fn is_stdio(path: &Path) -> bool {
+    path == Path::new(STDIO_PATH)
+}
 
-    for (i, func) in functions.iter().enumerate() {
-        let comma = if i == functions.len() - 1 { "" } else { "," };
-        writeln!(file, "  {{")?;
-        writeln!(file, "    \"name\": \"{}\",", func.name)?;
-        writeln!(
-            file,
-            "    \"cyclomatic_complexity\": {},",
-            func.cyclomatic_complexity
-        )?;
-        writeln!(
-            file,
-            "    \"cognitive_complexity\": {},",
-            func.cognitive_complexity
-        )?;
-        writeln!(file, "    \"parameter_count\": {},", func.parameter_count)?;
-        // Advanced analysis data
-        writeln!(
-            file,
-            "    \"unsafe_blocks\": {},",
-            func.details.unsafe_blocks
-        )?;
-        writeln!(
-            file,
-            "    \"generic_parameters\": {},",
-            func.details.generic_parameters
-        )?;
-        if !func.details.function_call_chain.is_empty() {
-            writeln!(
-                file,
-                "    \"function_call_chain\": \"{}\",",
-                func.details.function_call_chain.join(", ")
-            )?;
-        }
-        if !func.details.macro_invocations.is_empty() {
-            writeln!(
-                file,
-                "    \"macro_invocations\": \"{}\",",
-                func.details.macro_invocations.join(", ")
-            )?;
-        }
-        if !func.details.module_dependencies.is_empty() {
-            writeln!(
-                file,
-                "    \"module_dependencies\": \"{}\",",
-                func.details.module_dependencies.join(", ")
-            )?;
-        }
-        writeln!(file, "    \"rating\": \"{}\"", func.return_complexity)?;
-        writeln!(file, "  }}{}", comma)?;
+/// The source at `path` and the name to report it under
+fn read_source(path: &Path) -> Result<(String, PathBuf)> {
+    use std::io::Read;
+
+    if is_stdio(path) {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .context("Failed to read stdin")?;
+        return Ok((content, PathBuf::from("<stdin>")));
     }
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read file: {}", path.display()))?;
+    Ok((content, path.to_path_buf()))
+}
 
-    writeln!(file, "]")?;
+fn write_export(path: &Path, content: &str) -> Result<()> {
+    if is_stdio(path) {
+        println!("{}", content);
+        return Ok(());
+    }
+    fs::write(path, content)
+        .with_context(|| format!("Failed to create export file: {}", path.display()))
+}
 
-    println!(
-        "{}",
-        format!("Results exported to: {}", path.display()).green()
-    );
-    Ok(())
+fn export_target(path: &Path) -> String {
+    if is_stdio(path) {
+        "stdout".to_string()
+    } else {
+        path.display().to_string()
+    }
 }
@@ -1,16 +1,21 @@
 // Performance analysis tool: combines complexity analysis with flamegraph
 // This tool demonstrates how to combine complexity analysis results with performance analysis
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use clap::Parser;
-use rust_copartner::complexity_analyzer::{ComplexityAnalyzer, ComplexityRating};
-use std::{fs, path::PathBuf, time::Instant};
+use rust_copartner::cli_style::{self, icon};
+use rust_copartner::complexity_analyzer::{
+    refactor::{find_candidates, HeuristicConfig, Suggestion},
+    AnalyzeOptions, ComplexityAnalyzer, ComplexityRating, ComplexityScore, ComplexitySummary,
+    ScoreWeights,
+};
+use std::{path::PathBuf, time::Instant};
 
 #[derive(Parser)]
 #[command(name = "performance-analyzer")]
 #[command(about = "Analyze performance of functions based on complexity")]
 struct Cli {
-    /// Path to analyze
+    /// File or directory to analyze
     #[arg(short, long)]
     path: PathBuf,
 
@@ -21,74 +26,118 @@ struct Cli {
     /// Minimum complexity threshold for analysis
     #[arg(long, default_value = "5")]
     threshold: usize,
+
+    /// TOML file overriding the performance impact score weights
+    #[arg(long)]
+    weights: Option<PathBuf>,
+
+    /// Plain output without emoji (also set by NO_COLOR)
+    #[arg(long)]
+    no_color: bool,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    cli_style::init(cli.no_color);
+    let weights = match &cli.weights {
+        Some(path) => ScoreWeights::load(path)?,
+        None => ScoreWeights::default(),
+    };
 
-    println!("🔥 Performance Analysis Tool");
+    println!("{} Performance Analysis Tool", icon("🔥", "*"));
     println!("Analyzing: {}", cli.path.display());
     println!("Complexity threshold: {}", cli.threshold);
     println!();
 
-    // Read and analyze file
-    let content = fs::read_to_string(&cli.path)
-        .with_context(|| format!("Failed to read file: {}", cli.path.display()))?;
-
+    // Read and analyze the file, or every file below the directory
     let start = Instant::now();
-    let functions = ComplexityAnalyzer::analyze_file(&content)?;
+    let functions = if cli.path.is_dir() {
+        let project =
+            ComplexityAnalyzer::analyze_project(&cli.path, &AnalyzeOptions::default(), None)?;
+        for (file, reason) in &project.skipped {
+            println!(
+                "{}  Skipped {}: {}",
+                icon("⚠️", "[!]"),
+                file.display(),
+                reason
+            );
+        }
+        project.into_functions()
+    } else {
+        ComplexityAnalyzer::analyze_file_at(&cli.path)?
+    };
     let analysis_time = start.elapsed();
 
-    println!("📊 Analysis completed in {:?}", analysis_time);
-    println!("Found {} functions", functions.len());
+    println!(
+        "{} Analysis completed in {:?}",
+        icon("📊", "*"),
+        analysis_time
+    );
+    let summary = ComplexitySummary::from_functions(&functions);
+    println!(
+        "Found {} functions (average cyclomatic complexity {:.1}, {} rated high)",
+        summary.total_functions,
+        summary.average_cyclomatic,
+        summary.high_complexity_count()
+    );
     println!();
 
     // Filter high complexity functions
     let high_complexity_functions: Vec<_> = functions
         .iter()
-        .filter(|f| f.cyclomatic_complexity >= cli.threshold)
+        .filter(|f| !f.suppressed && f.cyclomatic_complexity >= cli.threshold)
         .collect();
 
     if high_complexity_functions.is_empty() {
         println!(
-            "✅ No functions found above complexity threshold of {}",
+            "{} No functions found above complexity threshold of {}",
+            icon("✅", "[ok]"),
             cli.threshold
         );
         return Ok(());
     }
 
     println!(
-        "⚠️  Found {} functions above complexity threshold:",
+        "{}  Found {} functions above complexity threshold:",
+        icon("⚠️", "[!]"),
         high_complexity_functions.len()
     );
 
     for func in &high_complexity_functions {
-        print_performance_analysis(func);
+        print_performance_analysis(func, &weights);
     }
 
     if cli.flamegraph {
         println!();
-        println!("🔥 Flamegraph Integration");
-        show_flamegraph_commands(&high_complexity_functions);
+        println!("{} Flamegraph Integration", icon("🔥", "*"));
+        // Most complex first, so the benchmark snippet targets the worst offenders
+        let ranked = ComplexitySummary::with_top_n(
+            high_complexity_functions.iter().copied(),
+            high_complexity_functions.len(),
+        );
+        show_flamegraph_commands(&ranked.top_functions);
     }
 
     // Generate performance recommendations
     println!();
-    generate_performance_recommendations(&high_complexity_functions);
+    generate_performance_recommendations(&high_complexity_functions, &weights);
 
     Ok(())
 }
 
-fn print_performance_analysis(func: &rust_copartner::complexity_analyzer::FunctionComplexity) {
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    println!("🔍 Function: {}", func.name.to_uppercase());
+fn print_performance_analysis(
+    func: &rust_copartner::complexity_analyzer::FunctionComplexity,
+    weights: &ScoreWeights,
+) {
+    print_rule();
+    println!("{} Function: {}", icon("🔍", "*"), func.name.to_uppercase());
 
     // Complexity analysis
     let complexity_emoji = match func.return_complexity {
-        ComplexityRating::Low => "🟢",
-        ComplexityRating::Medium => "🟡",
-        ComplexityRating::High => "🟠",
-        ComplexityRating::VeryHigh => "🔴",
+        ComplexityRating::Low => icon("🟢", "[L]"),
+        ComplexityRating::Medium => icon("🟡", "[M]"),
+        ComplexityRating::High => icon("🟠", "[H]"),
+        ComplexityRating::VeryHigh => icon("🔴", "[VH]"),
     };
 
     println!(
@@ -96,46 +145,82 @@ fn print_performance_analysis(func: &rust_copartner::complexity_analyzer::Functi
         complexity_emoji, func.return_complexity
     );
     println!(
-        "   📈 Cyclomatic: {} | Cognitive: {}",
-        func.cyclomatic_complexity, func.cognitive_complexity
+        "   {} Cyclomatic: {} | Cognitive: {}",
+        icon("📈", "*"),
+        func.cyclomatic_complexity,
+        func.cognitive_complexity
     );
 
     // Performance impact factor analysis
-    let performance_score = calculate_performance_impact(func);
-    println!("   ⚡ Performance Impact Score: {}/100", performance_score);
+    let performance_score = ComplexityScore::compute(func, weights);
+    match weights.cap() {
+        Some(cap) => println!(
+            "   {} Performance Impact Score: {}/{}",
+            icon("⚡", "*"),
+            performance_score.value,
+            cap
+        ),
+        None => println!(
+            "   {} Performance Impact Score: {}",
+            icon("⚡", "*"),
+            performance_score.value
+        ),
+    }
 
     // Detailed analysis
     if func.details.loops > 0 {
         println!(
-            "   🔄 Contains {} loop(s) - Potential O(n) or higher complexity",
+            "   {} Contains {} loop(s) - Potential O(n) or higher complexity",
+            icon("🔄", "*"),
             func.details.loops
         );
     }
 
     if func.details.nested_functions > 0 {
         println!(
-            "   📦 {} nested function(s) - May affect stack usage",
+            "   {} {} nested function(s) - May affect stack usage",
+            icon("📦", "*"),
             func.details.nested_functions
         );
     }
 
     if func.details.unsafe_blocks > 0 {
         println!(
-            "   ⚠️  {} unsafe block(s) - Requires careful performance verification",
+            "   {}  {} unsafe block(s) - Requires careful performance verification",
+            icon("⚠️", "[!]"),
             func.details.unsafe_blocks
         );
     }
 
     if func.details.max_nesting_depth > 3 {
         println!(
-            "   🏗️  Deep nesting ({}x) - May cause branch prediction issues",
+            "   {}  Deep nesting ({}x) - May cause branch prediction issues",
+            icon("🏗️", "*"),
             func.details.max_nesting_depth
         );
     }
 
+    if func.details.await_points > 0 {
+        println!(
+            "   {} {} await point(s) - Each suspension grows the async state machine",
+            icon("⏳", "*"),
+            func.details.await_points
+        );
+    }
+
+    if func
+        .details
+        .attributes
+        .iter()
+        .any(|attr| attr == "inline(always)")
+    {
+        println!("   {} Marked #[inline(always)] - forcing a function this complex inline bloats every call site", icon("🚫", "*"));
+    }
+
     if func.details.function_calls > 10 {
         println!(
-            "   📞 High function call count ({}) - Consider call overhead",
+            "   {} High function call count ({}) - Consider call overhead",
+            icon("📞", "*"),
             func.details.function_calls
         );
     }
@@ -145,69 +230,62 @@ fn print_performance_analysis(func: &rust_copartner::complexity_analyzer::Functi
     println!();
 }
 
-fn calculate_performance_impact(
-    func: &rust_copartner::complexity_analyzer::FunctionComplexity,
-) -> u32 {
-    let mut score = 0;
-
-    // Base complexity impact
-    score += func.cyclomatic_complexity * 5;
-    score += func.cognitive_complexity * 3;
-
-    // Specific performance factors
-    score += func.details.loops * 15; // Loops have significant performance impact
-    score += func.details.max_nesting_depth * 8; // Deep nesting affects branch prediction
-    score += func.details.function_calls * 2; // Function call overhead
-    score += func.details.unsafe_blocks * 10; // unsafe blocks require special attention
-    score += func.parameter_count * 3; // Too many parameters affect stack usage
-
-    // Limit to under 100
-    score.min(100) as u32
-}
-
 fn print_optimization_suggestions(func: &rust_copartner::complexity_analyzer::FunctionComplexity) {
     let mut suggestions = Vec::new();
 
     if func.details.loops > 2 {
-        suggestions.push("Consider vectorization or parallel processing for multiple loops");
+        suggestions
+            .push("Consider vectorization or parallel processing for multiple loops".to_string());
     }
 
-    if func.details.max_nesting_depth > 4 {
-        suggestions.push("Refactor to reduce nesting - use early returns or helper functions");
+    if func.details.early_returns > 5 {
+        suggestions.push(
+            "Many exit points - restructure so the function has fewer return paths".to_string(),
+        );
     }
 
     if func.details.function_calls > 15 {
-        suggestions.push("High function call overhead - consider inlining hot path functions");
+        suggestions
+            .push("High function call overhead - consider inlining hot path functions".to_string());
     }
 
-    if func.parameter_count > 5 {
-        suggestions.push("Too many parameters - consider using structs to reduce stack pressure");
-    }
-
-    if func.cyclomatic_complexity > 15 {
-        suggestions.push("Very high complexity - split into smaller, focused functions");
+    // Structural refactorings, with the higher limits this tool has always used
+    let config = HeuristicConfig {
+        max_cyclomatic: 15,
+        max_call_targets: 15,
+        ..Default::default()
+    };
+    for candidate in find_candidates(std::slice::from_ref(func), &config) {
+        for suggestion in candidate.suggestions {
+            // More early returns would make the exit points worse
+            if suggestion == Suggestion::ReduceNesting && func.details.early_returns > 5 {
+                continue;
+            }
+            suggestions.push(suggestion.to_string());
+        }
     }
 
     if !suggestions.is_empty() {
-        println!("   💡 Optimization Suggestions:");
+        println!("   {} Optimization Suggestions:", icon("💡", "*"));
         for (i, suggestion) in suggestions.iter().enumerate() {
             println!("      {}. {}", i + 1, suggestion);
         }
     }
 }
 
-fn show_flamegraph_commands(
-    functions: &[&rust_copartner::complexity_analyzer::FunctionComplexity],
-) {
+fn show_flamegraph_commands(functions: &[rust_copartner::complexity_analyzer::FunctionComplexity]) {
     println!("To profile these high-complexity functions with flamegraph:");
     println!();
 
     // Basic flamegraph commands
-    println!("1. 📊 Profile the entire application:");
+    println!("1. {} Profile the entire application:", icon("📊", "*"));
     println!("   cargo flamegraph --bin complexity_cli -- stats --path .");
     println!();
 
-    println!("2. 🎯 Profile specific functions (add this to your main.rs for testing):");
+    println!(
+        "2. {} Profile specific functions (add this to your main.rs for testing):",
+        icon("🎯", "*")
+    );
     println!("   ```rust");
     println!("   fn benchmark_high_complexity() {{");
 
@@ -224,22 +302,22 @@ fn show_flamegraph_commands(
     println!("   ```");
     println!();
 
-    println!("3. 🔧 Advanced flamegraph options:");
+    println!("3. {} Advanced flamegraph options:", icon("🔧", "*"));
     println!("   cargo flamegraph --bin performance_analyzer --");
     println!("   cargo flamegraph --freq 997 --bin complexity_cli"); // Custom sampling frequency
     println!("   cargo flamegraph --min-width 0.01 --bin complexity_cli"); // Show more details
     println!();
 
-    println!("4. 🌡️  Hot path analysis commands:");
+    println!("4. {}  Hot path analysis commands:", icon("🌡️", "*"));
     println!("   # Generate flamegraph focused on CPU-intensive operations");
     println!("   CARGO_PROFILE_RELEASE_DEBUG=true cargo flamegraph --release --bin complexity_cli");
     println!();
 
     // Specific analysis recommendations for functions
-    println!("📋 Specific Analysis Recommendations:");
+    println!("{} Specific Analysis Recommendations:", icon("📋", "*"));
     for func in functions.iter().take(5) {
         let focus_areas = get_profiling_focus(func);
-        println!("   • {}: {}", func.name, focus_areas);
+        println!("   {} {}: {}", icon("•", "-"), func.name, focus_areas);
     }
 }
 
@@ -271,58 +349,94 @@ fn get_profiling_focus(func: &rust_copartner::complexity_analyzer::FunctionCompl
 
 fn generate_performance_recommendations(
     functions: &[&rust_copartner::complexity_analyzer::FunctionComplexity],
+    weights: &ScoreWeights,
 ) {
-    println!("🎯 Performance Optimization Strategy");
-    println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    println!("{} Performance Optimization Strategy", icon("🎯", "*"));
+    print_rule();
 
-    let total_score: u32 = functions
+    let total_score: usize = functions
         .iter()
-        .map(|f| calculate_performance_impact(f))
+        .map(|f| ComplexityScore::compute(f, weights).value)
         .sum();
 
     let avg_score = total_score as f64 / functions.len() as f64;
 
-    println!("📊 Overall Assessment:");
-    println!("   • {} functions analyzed", functions.len());
-    println!("   • Average performance impact: {:.1}/100", avg_score);
+    println!("{} Overall Assessment:", icon("📊", "*"));
+    println!(
+        "   {} {} functions analyzed",
+        icon("•", "-"),
+        functions.len()
+    );
+    match weights.cap() {
+        Some(cap) => println!(
+            "   {} Average performance impact: {:.1}/{}",
+            icon("•", "-"),
+            avg_score,
+            cap
+        ),
+        None => println!(
+            "   {} Average performance impact: {:.1}",
+            icon("•", "-"),
+            avg_score
+        ),
+    }
 
     if avg_score > 70.0 {
-        println!("   🔥 HIGH PRIORITY: Critical performance bottlenecks detected!");
-        println!("   💡 Recommended actions:");
+        println!(
+            "   {} HIGH PRIORITY: Critical performance bottlenecks detected!",
+            icon("🔥", "*")
+        );
+        println!("   {} Recommended actions:", icon("💡", "*"));
         println!("      1. Profile with cargo flamegraph immediately");
         println!("      2. Focus on loop optimization and algorithm complexity");
         println!("      3. Consider refactoring the highest complexity functions");
     } else if avg_score > 40.0 {
-        println!("   ⚠️  MEDIUM PRIORITY: Some performance concerns");
-        println!("   💡 Recommended actions:");
+        println!(
+            "   {}  MEDIUM PRIORITY: Some performance concerns",
+            icon("⚠️", "[!]")
+        );
+        println!("   {} Recommended actions:", icon("💡", "*"));
         println!("      1. Monitor performance in production");
         println!("      2. Profile during load testing");
         println!("      3. Consider incremental optimizations");
     } else {
-        println!("   ✅ LOW PRIORITY: Performance looks acceptable");
-        println!("   💡 Recommended actions:");
+        println!(
+            "   {} LOW PRIORITY: Performance looks acceptable",
+            icon("✅", "[ok]")
+        );
+        println!("   {} Recommended actions:", icon("💡", "*"));
         println!("      1. Maintain current code quality");
         println!("      2. Profile periodically as codebase grows");
     }
 
     println!();
-    println!("🏆 Quick Wins (easiest optimizations):");
+    println!("{} Quick Wins (easiest optimizations):", icon("🏆", "*"));
 
     let mut quick_wins = Vec::new();
     for func in functions.iter() {
         if func.parameter_count > 5 {
             quick_wins.push(format!("{}: Reduce parameter count", func.name));
         }
-        if func.details.max_nesting_depth > 4 {
+        if func.details.early_returns > 5 {
+            quick_wins.push(format!("{}: Consolidate scattered returns", func.name));
+        } else if func.details.max_nesting_depth > 4 {
             quick_wins.push(format!("{}: Reduce nesting with early returns", func.name));
         }
     }
 
     if quick_wins.is_empty() {
-        println!("   • No immediate quick wins identified - good code structure!");
+        println!(
+            "   {} No immediate quick wins identified - good code structure!",
+            icon("•", "-")
+        );
     } else {
         for (i, win) in quick_wins.iter().take(5).enumerate() {
             println!("   {}. {}", i + 1, win);
         }
     }
 }
+
+/// Separator between report sections
+fn print_rule() {
+    println!("{}", icon("━", "-").repeat(40));
+}
@@ -1,15 +1,26 @@
 use anyhow::{Context, Result};
-use clap::Parser;
-use rust_copartner::analyzer::{analyze_project, write_ndjson, AnalyzeConfig};
+use clap::builder::PossibleValuesParser;
+use clap::{ArgGroup, Parser, ValueEnum};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use indicatif::{ProgressBar, ProgressStyle};
+use rust_copartner::analyzer::{
+    analyze_project_streaming, read_ndjson, AnalyzeConfig, Change, ExportDiffer, OutputRecord,
+    QdrantSink, VectorFields, RECORD_KINDS,
+};
+use serde::Serialize;
+use std::collections::HashSet;
 use std::fs::File;
-use std::io::{self, BufWriter};
-use std::path::PathBuf;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 #[derive(Parser, Debug)]
 #[command(
     name = "project_analyzer",
     version,
-    about = "Scan Rust sources and emit NDJSON metadata"
+    about = "Scan Rust sources and emit NDJSON metadata",
+    group(ArgGroup::new("deletions_source").args(["diff_against", "changed_since"]).multiple(true))
 )]
 struct Cli {
     /// Root directory of Rust sources
@@ -20,9 +31,295 @@ struct Cli {
     #[arg(long, value_name = "string")]
     repo_id: String,
 
-    /// Output file for NDJSON (default stdout)
+    /// Output file (default stdout, or none with --qdrant-url)
     #[arg(long, value_name = "file")]
     out: Option<PathBuf>,
+
+    /// Commit recorded on every record
+    #[arg(long, value_name = "sha")]
+    commit: Option<String>,
+
+    /// Record the HEAD commit of --path when --commit is not given
+    #[arg(long)]
+    detect_git: bool,
+
+    /// Only analyze files that differ from this git ref (committed or not);
+    /// with --deletions-out, deleted files are listed there
+    #[arg(long, value_name = "git-ref")]
+    changed_since: Option<String>,
+
+    /// Reuse records of unchanged files from this manifest, updating it afterwards
+    #[arg(long, value_name = "file")]
+    cache: Option<PathBuf>,
+
+    /// Skip files and directories matching this glob, relative to --path (repeatable)
+    #[arg(long, value_name = "glob")]
+    exclude: Vec<String>,
+
+    /// Don't honor .gitignore, .ignore and git exclude files
+    #[arg(long)]
+    no_ignore: bool,
+
+    /// Number of files parsed in parallel (default: one per CPU)
+    #[arg(long, short = 'j', value_name = "n")]
+    jobs: Option<usize>,
+
+    /// Include #[cfg(test)] modules, #[test] functions and files under tests/
+    #[arg(long)]
+    include_tests: bool,
+
+    /// Split records whose text is longer than this many chars into chunks
+    #[arg(long, value_name = "n")]
+    max_chars: Option<usize>,
+
+    /// Lines shared between consecutive chunks
+    #[arg(long, value_name = "lines", default_value_t = 5)]
+    chunk_overlap: usize,
+
+    /// Truncate text and code_body longer than this many bytes, with a marker
+    #[arg(long, value_name = "n")]
+    max_text_bytes: Option<usize>,
+
+    /// Add an embedding_text rendered from this template, e.g.
+    /// "{doc_comment} {signature}"; placeholders are {signature},
+    /// {identifiers}, {code_body} and {doc_comment}
+    #[arg(long, value_name = "template", value_parser = parse_template)]
+    embedding_template: Option<String>,
+
+    /// Warn about records estimated above this many tokens
+    #[arg(long, value_name = "n")]
+    max_tokens_per_record: Option<usize>,
+
+    /// Only analyze this workspace member, by package name (repeatable)
+    #[arg(long, value_name = "name")]
+    member: Vec<String>,
+
+    /// Only emit records of this kind (repeatable)
+    #[arg(long, value_name = "kind", value_parser = PossibleValuesParser::new(RECORD_KINDS))]
+    kind: Vec<String>,
+
+    /// Drop structs, enums and functions that aren't `pub`
+    #[arg(long)]
+    public_only: bool,
+
+    /// Keep only the first record of each content_hash, dropping identical copies
+    #[arg(long)]
+    dedupe_by_content: bool,
+
+    /// Keep filesystem walk order instead of sorting by path, then line
+    #[arg(long)]
+    no_sort: bool,
+
+    /// Gzip the output (implied by an --out ending in .gz)
+    #[arg(long)]
+    compress: bool,
+
+    /// Write files that could not be read or parsed, with the reason, as JSON
+    #[arg(long, value_name = "file")]
+    errors_out: Option<PathBuf>,
+
+    /// Only emit records added or changed since this earlier export (NDJSON,
+    /// JSON array, or gzipped)
+    #[arg(long, value_name = "file", requires = "deletions_out")]
+    diff_against: Option<PathBuf>,
+
+    /// Write the ids of deleted records here as a JSON array. With only
+    /// --changed-since, just the module and uses records of deleted files are
+    /// known; add --diff-against for every record.
+    #[arg(long, value_name = "file", requires = "deletions_source")]
+    deletions_out: Option<PathBuf>,
+
+    /// No progress bar or summary line on stderr
+    #[arg(long)]
+    quiet: bool,
+
+    /// Print the final summary as one JSON object on stderr
+    #[arg(long)]
+    summary_json: bool,
+
+    /// One record per line, or a single JSON array
+    #[arg(long, value_enum, default_value_t = Format::Ndjson)]
+    format: Format,
+
+    /// Upsert records into this Qdrant instance, e.g. http://localhost:6333
+    #[arg(long, value_name = "url", requires = "collection")]
+    qdrant_url: Option<String>,
+
+    /// Qdrant collection to upsert into
+    #[arg(long, value_name = "name", requires = "qdrant_url")]
+    collection: Option<String>,
+
+    /// Points per Qdrant request
+    #[arg(long, value_name = "n", default_value_t = 256)]
+    batch_size: usize,
+
+    /// Qdrant API key (default $QDRANT_API_KEY)
+    #[arg(long, value_name = "key")]
+    qdrant_api_key: Option<String>,
+
+    /// Send empty named vectors instead of none, for collections that don't
+    /// embed server-side
+    #[arg(long)]
+    placeholder_vectors: bool,
+
+    /// Retries of a Qdrant batch after a 429, a 5xx or a connection error
+    #[arg(long, value_name = "n", default_value_t = 3)]
+    retries: usize,
+
+    /// Wait before the first retry, doubled for each one after, up to a minute
+    #[arg(long, value_name = "ms", default_value_t = 500)]
+    retry_delay_ms: u64,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Ndjson,
+    Json,
+}
+
+/// Output stream, gzipped or not
+enum Output {
+    Plain(Box<dyn Write>),
+    Gzip(GzEncoder<Box<dyn Write>>),
+}
+
+impl Output {
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Plain(mut out) => out.flush(),
+            Output::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(out) => out.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(out) => out.flush(),
+            Output::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Writes records one at a time in the chosen format, producing the same
+/// bytes as `write_ndjson` and `write_json_array`
+struct RecordWriter {
+    format: Format,
+    out: Output,
+    count: usize,
+}
+
+impl RecordWriter {
+    fn new(format: Format, out: Output) -> Result<Self> {
+        let mut writer = RecordWriter {
+            format,
+            out,
+            count: 0,
+        };
+        if let Format::Json = format {
+            writer.out.write_all(b"[")?;
+        }
+        Ok(writer)
+    }
+
+    fn write(&mut self, record: &OutputRecord) -> Result<()> {
+        if matches!(self.format, Format::Json) && self.count > 0 {
+            self.out.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut self.out, record)?;
+        if let Format::Ndjson = self.format {
+            self.out.write_all(b"\n")?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        if let Format::Json = self.format {
+            self.out.write_all(b"]\n")?;
+        }
+        self.out.finish()?;
+        Ok(())
+    }
+}
+
+/// Counts for the final summary line, with a warning per oversized record as
+/// it streams by
+#[derive(Default, Serialize)]
+struct Summary {
+    files: usize,
+    cached: usize,
+    analyzed: usize,
+    removed: usize,
+    skipped: usize,
+    records: usize,
+    approx_tokens: usize,
+    elapsed_ms: u128,
+}
+
+impl Summary {
+    fn add(&mut self, r: &OutputRecord, max_tokens: Option<usize>, progress: &ProgressBar) {
+        if let Some(max) = max_tokens.filter(|&max| r.payload.approx_tokens > max) {
+            progress.suspend(|| {
+                eprintln!(
+                    "[project_analyzer] Warning: {} in {} is ~{} tokens (limit {})",
+                    r.payload.qual_symbol, r.payload.path, r.payload.approx_tokens, max
+                )
+            });
+        }
+        self.records += 1;
+        self.approx_tokens += r.payload.approx_tokens;
+        progress.set_message(format!("{} records", self.records));
+    }
+
+    fn print(&self, cache: bool) {
+        let mut line = format!(
+            "[project_analyzer] {} files, {} records, ~{} tokens, {} skipped in {:.1}s",
+            self.files,
+            self.records,
+            self.approx_tokens,
+            self.skipped,
+            self.elapsed_ms as f64 / 1000.0
+        );
+        if cache {
+            line += &format!(
+                " ({} cached, {} analyzed, {} removed)",
+                self.cached, self.analyzed, self.removed
+            );
+        }
+        eprintln!("{line}");
+    }
+}
+
+fn parse_template(template: &str) -> Result<String> {
+    VectorFields::validate_template(template)?;
+    Ok(template.to_string())
+}
+
+/// Progress bar on stderr, hidden with --quiet or when stderr isn't a terminal
+fn progress_bar(quiet: bool) -> ProgressBar {
+    if quiet || !io::stderr().is_terminal() {
+        return ProgressBar::hidden();
+    }
+    let style = ProgressStyle::with_template("{spinner} [{elapsed}] {pos}/{len} files, {msg}")
+        .expect("valid progress template");
+    ProgressBar::new(0).with_style(style)
+}
+
+fn write_json_file(path: &Path, value: &impl Serialize) -> Result<()> {
+    let f = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(f);
+    serde_json::to_writer_pretty(&mut out, value)?;
+    out.write_all(b"\n")?;
+    out.flush()?;
+    Ok(())
 }
 
 fn main() -> Result<()> {
@@ -30,18 +327,168 @@ fn main() -> Result<()> {
     let cfg = AnalyzeConfig {
         path: cli.path.clone(),
         repo_id: cli.repo_id.clone(),
+        commit: cli.commit.clone(),
+        detect_git: cli.detect_git,
+        changed_since: cli.changed_since.clone(),
+        cache: cli.cache.clone(),
+        exclude_globs: cli.exclude.clone(),
+        respect_gitignore: !cli.no_ignore,
+        threads: cli.jobs,
+        include_tests: cli.include_tests,
+        max_chars: cli.max_chars,
+        chunk_overlap: cli.chunk_overlap,
+        max_text_bytes: cli.max_text_bytes,
+        embedding_template: cli.embedding_template.clone(),
+        members: cli.member.clone(),
+        kinds: (!cli.kind.is_empty()).then(|| cli.kind.iter().cloned().collect()),
+        sort: !cli.no_sort,
+        progress: Some(progress_bar(cli.quiet)),
     };
-    let records = analyze_project(&cfg)?;
-
-    match cli.out {
+    let started = Instant::now();
+    // Read before --out is created, in case both name the same file
+    let mut differ = match &cli.diff_against {
+        Some(path) => {
+            let mut f =
+                File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+            let previous = read_ndjson(&mut f)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            Some(ExportDiffer::new(&previous))
+        }
+        None => None,
+    };
+    let (mut added, mut changed) = (0, 0);
+    let mut seen_content = HashSet::new();
+    let out: Option<Box<dyn Write>> = match &cli.out {
         Some(p) => {
-            let f =
-                File::create(&p).with_context(|| format!("Failed to create {}", p.display()))?;
-            write_ndjson(&records, &mut BufWriter::new(f))?;
+            let f = File::create(p).with_context(|| format!("Failed to create {}", p.display()))?;
+            Some(Box::new(BufWriter::new(f)))
+        }
+        // Records going to Qdrant only land on stdout when asked for
+        None if cli.qdrant_url.is_some() => None,
+        None => Some(Box::new(BufWriter::new(io::stdout().lock()))),
+    };
+    let compress = cli.compress
+        || cli
+            .out
+            .as_ref()
+            .is_some_and(|p| p.extension().is_some_and(|ext| ext == "gz"));
+    let mut writer = match out {
+        Some(out) if compress => Some(RecordWriter::new(
+            cli.format,
+            Output::Gzip(GzEncoder::new(out, Compression::default())),
+        )?),
+        Some(out) => Some(RecordWriter::new(cli.format, Output::Plain(out))?),
+        None => None,
+    };
+    let progress = cfg.progress.clone().unwrap_or_else(ProgressBar::hidden);
+    let mut qdrant = match (&cli.qdrant_url, &cli.collection) {
+        (Some(url), Some(collection)) => {
+            let mut sink = QdrantSink::new(url, collection)?;
+            sink.api_key = cli
+                .qdrant_api_key
+                .clone()
+                .or_else(|| std::env::var("QDRANT_API_KEY").ok());
+            sink.batch_size = cli.batch_size;
+            sink.placeholder_vectors = cli.placeholder_vectors;
+            sink.max_retries = cli.retries;
+            sink.retry_delay = Duration::from_millis(cli.retry_delay_ms);
+            sink.progress = Some(progress.clone());
+            Some(sink)
+        }
+        _ => None,
+    };
+    let mut summary = Summary::default();
+    let stats = analyze_project_streaming(&cfg, |record| {
+        // Records without a visibility (impls, traits, modules...) are kept
+        if cli.public_only && !matches!(record.payload.visibility.as_str(), "" | "pub") {
+            return Ok(());
+        }
+        if cli.dedupe_by_content && !seen_content.insert(record.payload.content_hash.clone()) {
+            return Ok(());
+        }
+        if let Some(differ) = &mut differ {
+            match differ.classify(&record) {
+                Change::Added => added += 1,
+                Change::Changed => changed += 1,
+                Change::Unchanged => return Ok(()),
+            }
+        }
+        summary.add(&record, cli.max_tokens_per_record, &progress);
+        if let Some(qdrant) = &mut qdrant {
+            qdrant.push(&record);
+        }
+        match &mut writer {
+            Some(writer) => writer.write(&record),
+            None => Ok(()),
+        }
+    })?;
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+    let upserted = qdrant.map(QdrantSink::finish);
+    progress.finish_and_clear();
+
+    summary.files = stats.files;
+    summary.cached = stats.cached;
+    summary.analyzed = stats.analyzed;
+    summary.removed = stats.removed;
+    summary.skipped = stats.skipped.len();
+    if let Some(path) = &cli.errors_out {
+        write_json_file(path, &stats.skipped)?;
+    }
+    if let Some(since) = &cli.changed_since {
+        if !cli.quiet {
+            eprintln!(
+                "[project_analyzer] {} changed and {} deleted files since {}",
+                stats.changed.len(),
+                stats.deleted.len(),
+                since
+            );
+        }
+    }
+    match (differ, &cli.deletions_out) {
+        (Some(differ), Some(path)) => {
+            let removed = match &cli.changed_since {
+                // Files that weren't analyzed keep their records
+                Some(_) => differ.removed_in(|path| {
+                    stats.changed.iter().any(|p| p == path)
+                        || stats.deleted.iter().any(|d| d.path == path)
+                }),
+                None => differ.removed(),
+            };
+            write_json_file(path, &removed)?;
+            if !cli.quiet {
+                eprintln!(
+                    "[project_analyzer] {} added, {} changed, {} deleted since the previous export",
+                    added,
+                    changed,
+                    removed.len()
+                );
+            }
+        }
+        (None, Some(path)) => {
+            let ids: Vec<&String> = stats.deleted.iter().flat_map(|d| &d.ids).collect();
+            write_json_file(path, &ids)?;
+        }
+        _ => {}
+    }
+    summary.elapsed_ms = started.elapsed().as_millis();
+    if cli.summary_json {
+        eprintln!("{}", serde_json::to_string(&summary)?);
+    } else if !cli.quiet {
+        summary.print(cfg.cache.is_some());
+    }
+    if let Some(stats) = upserted {
+        if !cli.quiet {
+            eprintln!(
+                "[project_analyzer] {} points upserted to {}, {} failed",
+                stats.upserted,
+                cli.collection.as_deref().unwrap_or_default(),
+                stats.failed
+            );
         }
-        None => {
-            let mut out = io::stdout().lock();
-            write_ndjson(&records, &mut out)?;
+        if stats.failed > 0 {
+            anyhow::bail!("{} points could not be upserted to Qdrant", stats.failed);
         }
     }
     Ok(())
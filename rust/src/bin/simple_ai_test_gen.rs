@@ -6,11 +6,15 @@ use clap::Parser;
 use colored::*;
 use dotenv::dotenv;
 use reqwest::Client;
+use rust_copartner::cli_style::{self, icon};
 use rust_copartner::complexity_analyzer::{
     ComplexityAnalyzer, ComplexityRating, FunctionComplexity,
 };
 use serde::{Deserialize, Serialize};
-use std::{env, fs, path::PathBuf};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
 
 #[derive(Parser)]
 #[command(name = "simple-ai-test-gen")]
@@ -35,6 +39,10 @@ struct Cli {
     /// Verbose output
     #[arg(short, long)]
     verbose: bool,
+
+    /// Plain output without colors or emoji (also set by NO_COLOR)
+    #[arg(long)]
+    no_color: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -77,8 +85,14 @@ struct GeneratedTestSuite {
 async fn main() -> Result<()> {
     dotenv().ok();
     let cli = Cli::parse();
+    cli_style::init(cli.no_color);
 
-    println!("{}", "🤖 Simple AI Test Generator".bright_cyan().bold());
+    println!(
+        "{}",
+        format!("{} Simple AI Test Generator", icon("🤖", "*"))
+            .bright_cyan()
+            .bold()
+    );
     println!(
         "Analyzing: {}",
         cli.file.display().to_string().bright_yellow()
@@ -94,7 +108,7 @@ async fn main() -> Result<()> {
         env::var("OPENROUTER_MODEL").unwrap_or_else(|_| "deepseek/deepseek-r1:free".to_string());
 
     if cli.verbose {
-        println!("🔧 Configuration:");
+        println!("{} Configuration:", icon("🔧", "*"));
         println!("   API Base: {}", base_url.bright_blue());
         println!("   Model: {}", model.bright_green());
         println!();
@@ -123,7 +137,8 @@ async fn main() -> Result<()> {
     }
 
     println!(
-        "📊 Found {} functions requiring AI-generated tests:",
+        "{} Found {} functions requiring AI-generated tests:",
+        icon("📊", "*"),
         target_functions.len().to_string().bright_green()
     );
 
@@ -132,7 +147,10 @@ async fn main() -> Result<()> {
     }
 
     if cli.dry_run {
-        println!("\n{}", "🏃 Dry run mode - skipping API calls".bright_blue());
+        println!(
+            "\n{}",
+            format!("{} Dry run mode - skipping API calls", icon("🏃", "*")).bright_blue()
+        );
         show_generation_plan(&target_functions);
         return Ok(());
     }
@@ -145,7 +163,7 @@ async fn main() -> Result<()> {
     for (index, func) in target_functions.iter().enumerate() {
         println!(
             "\n{} Generating tests for: {} ({}/{})...",
-            "🤖".bright_green(),
+            icon("🤖", "*").bright_green(),
             func.name.bright_cyan(),
             index + 1,
             target_functions.len()
@@ -156,11 +174,12 @@ async fn main() -> Result<()> {
         {
             Ok(test_suite) => {
                 println!(
-                    "   ✅ Generated {} test cases",
+                    "   {} Generated {} test cases",
+                    icon("✅", "[ok]"),
                     test_suite.test_count.to_string().bright_green()
                 );
                 if cli.verbose {
-                    println!("   📝 Generated code preview:");
+                    println!("   {} Generated code preview:", icon("📝", "*"));
                     let preview = test_suite
                         .test_code
                         .lines()
@@ -172,7 +191,11 @@ async fn main() -> Result<()> {
                 all_tests.push(test_suite);
             }
             Err(e) => {
-                println!("   ❌ Failed: {}", e.to_string().bright_red());
+                println!(
+                    "   {} Failed: {}",
+                    icon("❌", "[x]"),
+                    e.to_string().bright_red()
+                );
             }
         }
 
@@ -184,13 +207,14 @@ async fn main() -> Result<()> {
         save_generated_tests(&all_tests, &cli.output, &cli.file).await?;
         println!(
             "\n{} Tests saved to: {}",
-            "🎉".bright_green(),
+            icon("🎉", "*").bright_green(),
             cli.output.display().to_string().bright_cyan()
         );
 
         let total_tests: usize = all_tests.iter().map(|t| t.test_count).sum();
         println!(
-            "📊 Summary: {} test functions generated for {} source functions",
+            "{} Summary: {} test functions generated for {} source functions",
+            icon("📊", "*"),
             total_tests.to_string().bright_yellow(),
             all_tests.len().to_string().bright_cyan()
         );
@@ -201,32 +225,32 @@ async fn main() -> Result<()> {
 
 fn print_function_summary(func: &FunctionComplexity) {
     let complexity_color = match func.return_complexity {
-        ComplexityRating::Low => "🟢",
-        ComplexityRating::Medium => "🟡",
-        ComplexityRating::High => "🟠",
-        ComplexityRating::VeryHigh => "🔴",
+        ComplexityRating::Low => icon("🟢", "[L]"),
+        ComplexityRating::Medium => icon("🟡", "[M]"),
+        ComplexityRating::High => icon("🟠", "[H]"),
+        ComplexityRating::VeryHigh => icon("🔴", "[VH]"),
     };
 
     println!(
-        "   {} {} (CC: {}, Lines: ~{})",
+        "   {} {} (CC: {}, Lines: {})",
         complexity_color,
         func.name.bright_white(),
         func.cyclomatic_complexity.to_string().bright_cyan(),
-        func.parameter_count * 5 // Estimate line count
+        func.line_count
     );
 }
 
 fn show_generation_plan(functions: &[&FunctionComplexity]) {
-    println!("\n📋 Test Generation Plan:");
+    println!("\n{} Test Generation Plan:", icon("📋", "*"));
 
     let estimated_cost = functions.len() as f64 * 0.003; // About $0.003 per function
-    println!("💰 Estimated cost: ${:.4}", estimated_cost);
+    println!("{} Estimated cost: ${:.4}", icon("💰", "*"), estimated_cost);
 
-    println!("\n📝 Test types to generate:");
-    println!("   • Basic functionality tests");
-    println!("   • Edge case tests");
-    println!("   • Error condition tests");
-    println!("   • Parameter validation tests");
+    println!("\n{} Test types to generate:", icon("📝", "*"));
+    println!("   {} Basic functionality tests", icon("•", "-"));
+    println!("   {} Edge case tests", icon("•", "-"));
+    println!("   {} Error condition tests", icon("•", "-"));
+    println!("   {} Parameter validation tests", icon("•", "-"));
 }
 
 async fn generate_test_for_function(
@@ -251,10 +275,10 @@ Requirements:
 6. Return ONLY the test code, properly formatted and ready to compile
 
 Function Analysis:
-- Complexity: {} (Cyclomatic: {})
+- Complexity: {:?} (Cyclomatic: {})
 - Parameters: {}
 - Analysis: Function has {} loops, {} max nesting depth"#,
-        format!("{:?}", func.return_complexity),
+        func.return_complexity,
         func.cyclomatic_complexity,
         func.parameter_count,
         func.details.loops,
@@ -295,7 +319,7 @@ Return only the Rust test code with #[test] functions."#,
     };
 
     let response = client
-        .post(&format!("{}/chat/completions", base_url))
+        .post(format!("{}/chat/completions", base_url))
         .header("Authorization", &format!("Bearer {}", api_key))
         .header("Content-Type", "application/json")
         .header(
@@ -342,12 +366,13 @@ Return only the Rust test code with #[test] functions."#,
 
 fn extract_function_code(source_code: &str, func: &FunctionComplexity) -> Result<String> {
     let lines: Vec<&str> = source_code.lines().collect();
+    let name = func.short_name();
 
     // Find function definition
     for (i, line) in lines.iter().enumerate() {
-        if line.contains(&format!("fn {}", func.name))
-            || line.contains(&format!("pub fn {}", func.name))
-            || line.contains(&format!("async fn {}", func.name))
+        if line.contains(&format!("fn {}", name))
+            || line.contains(&format!("pub fn {}", name))
+            || line.contains(&format!("async fn {}", name))
         {
             // Extract from function start to matching brace end
             let mut brace_count = 0;
@@ -385,8 +410,8 @@ fn extract_function_code(source_code: &str, func: &FunctionComplexity) -> Result
 
 async fn save_generated_tests(
     test_suites: &[GeneratedTestSuite],
-    output_path: &PathBuf,
-    source_file: &PathBuf,
+    output_path: &Path,
+    source_file: &Path,
 ) -> Result<()> {
     // Ensure output directory exists
     if let Some(parent) = output_path.parent() {
@@ -0,0 +1,40 @@
+//! Terminal styling shared by the binaries. Color and emoji are dropped when
+//! `--no-color` is passed, `NO_COLOR` is set, or stdout is not a terminal;
+//! `CLICOLOR_FORCE` keeps them on for pipes.
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+fn env_set(name: &str) -> bool {
+    std::env::var_os(name).is_some_and(|value| !value.is_empty() && value != "0")
+}
+
+/// Decide once at startup whether output is styled; `no_color` is the
+/// binary's `--no-color` flag
+pub fn init(no_color: bool) {
+    let plain = if no_color || env_set("NO_COLOR") {
+        true
+    } else if env_set("CLICOLOR_FORCE") {
+        false
+    } else {
+        !std::io::stdout().is_terminal()
+    };
+    PLAIN.store(plain, Ordering::Relaxed);
+    colored::control::set_override(!plain);
+}
+
+/// Whether color and emoji are turned off
+pub fn is_plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+/// `emoji`, or the ASCII `fallback` when output is plain
+pub fn icon(emoji: &'static str, fallback: &'static str) -> &'static str {
+    if is_plain() {
+        fallback
+    } else {
+        emoji
+    }
+}
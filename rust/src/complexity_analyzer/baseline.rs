@@ -0,0 +1,137 @@
+use super::lint::LintMetric;
+use super::FunctionComplexity;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Functions that were already over the limits when the baseline was taken,
+/// with their metrics at that time. Checking against a baseline only fails on
+/// functions that are new or got worse, so a legacy codebase can ratchet down.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Baseline {
+    pub functions: Vec<BaselineEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    /// Relative to the analyzed root
+    pub file: PathBuf,
+    /// Qualified function name, e.g. `parser::<Lexer as Iterator>::next`
+    pub function: String,
+    pub metrics: BTreeMap<LintMetric, usize>,
+}
+
+/// A metric that is higher than the baseline recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Regression {
+    pub metric: LintMetric,
+    pub recorded: usize,
+    pub actual: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BaselineStatus {
+    /// Recorded and no metric got worse
+    Accepted,
+    /// Recorded, but at least one metric is higher now
+    Regressed(Vec<Regression>),
+    /// Not in the baseline
+    New,
+}
+
+impl Baseline {
+    /// Record every metric of `functions`; files are stored relative to `root`
+    pub fn from_functions<'a>(
+        functions: impl IntoIterator<Item = &'a FunctionComplexity>,
+        root: &Path,
+    ) -> Self {
+        let mut functions: Vec<BaselineEntry> = functions
+            .into_iter()
+            .map(|func| BaselineEntry {
+                file: relative_file(func, root),
+                function: func.name.clone(),
+                metrics: LintMetric::ALL
+                    .iter()
+                    .map(|&metric| (metric, metric.value(func)))
+                    .collect(),
+            })
+            .collect();
+        functions.sort_by(|a, b| (&a.file, &a.function).cmp(&(&b.file, &b.function)));
+        functions.dedup_by(|a, b| a.file == b.file && a.function == b.function);
+        Self { functions }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read baseline: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Invalid baseline: {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write baseline: {}", path.display()))
+    }
+
+    fn entry(&self, file: &Path, function: &str) -> Option<&BaselineEntry> {
+        self.functions
+            .iter()
+            .find(|entry| entry.file == file && entry.function == function)
+    }
+
+    /// Compare a function analyzed below `root` with its recorded metrics
+    pub fn status(&self, func: &FunctionComplexity, root: &Path) -> BaselineStatus {
+        let Some(entry) = self.entry(&relative_file(func, root), &func.name) else {
+            return BaselineStatus::New;
+        };
+        let regressions: Vec<Regression> = entry
+            .metrics
+            .iter()
+            .filter_map(|(&metric, &recorded)| {
+                let actual = metric.value(func);
+                (actual > recorded).then_some(Regression {
+                    metric,
+                    recorded,
+                    actual,
+                })
+            })
+            .collect();
+        if regressions.is_empty() {
+            BaselineStatus::Accepted
+        } else {
+            BaselineStatus::Regressed(regressions)
+        }
+    }
+
+    /// Entries whose function no longer violates anything (`failing` being the
+    /// functions that still do); they can be dropped from the baseline
+    pub fn fixed(&self, failing: &[&FunctionComplexity], root: &Path) -> Vec<&BaselineEntry> {
+        let failing: Vec<(PathBuf, &str)> = failing
+            .iter()
+            .map(|func| (relative_file(func, root), func.name.as_str()))
+            .collect();
+        self.functions
+            .iter()
+            .filter(|entry| {
+                !failing
+                    .iter()
+                    .any(|(file, name)| *file == entry.file && *name == entry.function)
+            })
+            .collect()
+    }
+}
+
+fn relative_file(func: &FunctionComplexity, root: &Path) -> PathBuf {
+    func.file
+        .as_deref()
+        .map(|file| file.strip_prefix(root).unwrap_or(file).to_path_buf())
+        .unwrap_or_default()
+}
+
+impl std::fmt::Display for Regression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} -> {}", self.metric, self.recorded, self.actual)
+    }
+}
@@ -0,0 +1,108 @@
+use super::{AnalyzeOptions, FunctionComplexity};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Per-file analysis results keyed by a hash of the file contents and the
+/// analysis options, so unchanged files are not parsed again.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct AnalysisCache {
+    entries: HashMap<String, Vec<FunctionComplexity>>,
+    /// Lookups answered from the cache during this session
+    #[serde(skip)]
+    pub hits: usize,
+    /// Lookups that required parsing the file
+    #[serde(skip)]
+    pub misses: usize,
+    #[serde(skip)]
+    used: HashSet<String>,
+}
+
+impl AnalysisCache {
+    /// Directory used by the CLI when `--cache` is given without a value
+    pub const DEFAULT_DIR: &'static str = ".copartner-cache";
+    const FILE_NAME: &'static str = "complexity.json";
+
+    /// Load the cache stored in `dir`, starting empty if there is none yet or
+    /// it was written in an incompatible format
+    pub fn load(dir: &Path) -> Result<Self> {
+        let file = Self::file_in(dir);
+        if !file.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&file)
+            .with_context(|| format!("Failed to read cache: {}", file.display()))?;
+        Ok(serde_json::from_str(&content).unwrap_or_default())
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create cache directory: {}", dir.display()))?;
+        let file = Self::file_in(dir);
+        let content = serde_json::to_string(self)?;
+        std::fs::write(&file, content)
+            .with_context(|| format!("Failed to write cache: {}", file.display()))
+    }
+
+    fn file_in(dir: &Path) -> PathBuf {
+        dir.join(Self::FILE_NAME)
+    }
+
+    /// Cache key for `content` analyzed with `options`. The crate version is
+    /// mixed in so a new release never serves metrics computed by an old one.
+    pub fn key(content: &str, options: &AnalyzeOptions) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+        hasher.update([0x1f]);
+        hasher.update(
+            format!(
+                "{}/{}/{}/{}/{}",
+                options.count_try,
+                options.include_tests,
+                options.thresholds.low_max,
+                options.thresholds.medium_max,
+                options.thresholds.high_max
+            )
+            .as_bytes(),
+        );
+        hasher.update([0x1f]);
+        hasher.update(content.as_bytes());
+        format!("{:x}", hasher.finalize())
+    }
+
+    pub fn get(&mut self, key: &str) -> Option<Vec<FunctionComplexity>> {
+        match self.entries.get(key) {
+            Some(functions) => {
+                self.hits += 1;
+                self.used.insert(key.to_string());
+                Some(functions.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&mut self, key: String, functions: Vec<FunctionComplexity>) {
+        self.used.insert(key.clone());
+        self.entries.insert(key, functions);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop entries not read or written since the cache was loaded, e.g. for
+    /// files that have since been edited or deleted
+    pub fn prune_unused(&mut self) {
+        let used = &self.used;
+        self.entries.retain(|key, _| used.contains(key));
+    }
+}
@@ -0,0 +1,107 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Project settings read from `copartner.toml`, so thresholds, excludes and
+/// CI limits don't have to be repeated on every invocation. Every value is
+/// optional; command-line flags take precedence over anything set here.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CopartnerConfig {
+    pub thresholds: ThresholdsConfig,
+    pub files: FilesConfig,
+    pub fail_on: FailOnConfig,
+    pub output: OutputConfig,
+}
+
+/// Upper bounds of the rating buckets, see `ComplexityThresholds`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThresholdsConfig {
+    pub low_max: Option<usize>,
+    pub medium_max: Option<usize>,
+    pub high_max: Option<usize>,
+}
+
+/// Which files directory walks pick up, see `FileSelection`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesConfig {
+    pub exclude: Vec<String>,
+    pub include: Vec<String>,
+    pub respect_ignore: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FailOnConfig {
+    pub cyclomatic: Option<usize>,
+    pub cognitive: Option<usize>,
+    /// `medium`, `high` or `very-high`
+    pub rating: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// `pretty`, `json` or `ndjson`
+    pub format: Option<String>,
+    /// JSON export written by `dir`
+    pub export: Option<PathBuf>,
+}
+
+impl CopartnerConfig {
+    /// Names looked for in each directory, in order
+    pub const FILE_NAMES: [&'static str; 2] = ["copartner.toml", ".copartner.toml"];
+
+    /// Written by `complexity_cli config init`; every setting is commented out,
+    /// so the file parses to the defaults until edited
+    pub const TEMPLATE: &'static str = r#"# Settings for complexity_cli. Command-line flags override these values.
+
+[thresholds]
+# Highest cyclomatic complexity rated Low, Medium and High
+# low_max = 5
+# medium_max = 10
+# high_max = 20
+
+[files]
+# Globs relative to the analyzed directory
+# exclude = ["generated/**", "benches/**"]
+# include = ["src/**"]
+# Set to false to also analyze files listed in .gitignore
+# respect_ignore = true
+
+[fail_on]
+# Exit with code 2 when a function exceeds these limits
+# cyclomatic = 15
+# cognitive = 20
+# Fail on functions rated this or worse: "medium", "high" or "very-high"
+# rating = "very-high"
+
+[output]
+# "pretty", "json" or "ndjson"
+# format = "pretty"
+# export = "complexity.json"
+"#;
+
+    /// The nearest config file in `start` (a file's directory, if `start` is
+    /// a file) or any of its ancestors
+    pub fn discover(start: &Path) -> Option<PathBuf> {
+        let start = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+        let dir = if start.is_file() {
+            start.parent()?
+        } else {
+            start.as_path()
+        };
+        dir.ancestors()
+            .flat_map(|dir| Self::FILE_NAMES.iter().map(move |name| dir.join(name)))
+            .find(|candidate| candidate.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse config: {}", path.display()))
+    }
+}
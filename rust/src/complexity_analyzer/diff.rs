@@ -0,0 +1,189 @@
+use super::{ComplexityAnalyzer, FunctionComplexity};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Metrics compared between two versions of a function
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MetricSnapshot {
+    pub cyclomatic_complexity: usize,
+    pub cognitive_complexity: usize,
+    pub parameter_count: usize,
+    pub line_count: usize,
+}
+
+impl From<&FunctionComplexity> for MetricSnapshot {
+    fn from(func: &FunctionComplexity) -> Self {
+        Self {
+            cyclomatic_complexity: func.cyclomatic_complexity,
+            cognitive_complexity: func.cognitive_complexity,
+            parameter_count: func.parameter_count,
+            line_count: func.line_count,
+        }
+    }
+}
+
+/// A function present in both versions, possibly under a new name
+#[derive(Debug, Clone, Serialize)]
+pub struct FunctionChange {
+    pub old_name: String,
+    pub new_name: String,
+    pub before: MetricSnapshot,
+    pub after: MetricSnapshot,
+}
+
+impl FunctionChange {
+    fn new(old: &FunctionComplexity, new: &FunctionComplexity) -> Self {
+        Self {
+            old_name: old.name.clone(),
+            new_name: new.name.clone(),
+            before: old.into(),
+            after: new.into(),
+        }
+    }
+
+    pub fn cyclomatic_delta(&self) -> isize {
+        self.after.cyclomatic_complexity as isize - self.before.cyclomatic_complexity as isize
+    }
+
+    pub fn cognitive_delta(&self) -> isize {
+        self.after.cognitive_complexity as isize - self.before.cognitive_complexity as isize
+    }
+}
+
+impl std::fmt::Display for FunctionChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.old_name != self.new_name {
+            write!(f, "`{}` (renamed to `{}`)", self.old_name, self.new_name)?;
+        } else {
+            write!(f, "`{}`", self.new_name)?;
+        }
+        write!(
+            f,
+            ": complexity went from {} to {}, cognitive from {} to {}",
+            self.before.cyclomatic_complexity,
+            self.after.cyclomatic_complexity,
+            self.before.cognitive_complexity,
+            self.after.cognitive_complexity
+        )
+    }
+}
+
+/// Function-level differences between two analyses of the same code
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ComplexityDiff {
+    pub added: Vec<MetricEntry>,
+    pub removed: Vec<MetricEntry>,
+    /// Same name in both versions with different metrics
+    pub changed: Vec<FunctionChange>,
+    /// Matched under a different name, by identical body or unique short name
+    pub renamed: Vec<FunctionChange>,
+}
+
+/// A function that only exists in one of the versions
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricEntry {
+    pub name: String,
+    #[serde(flatten)]
+    pub metrics: MetricSnapshot,
+}
+
+impl From<&FunctionComplexity> for MetricEntry {
+    fn from(func: &FunctionComplexity) -> Self {
+        Self {
+            name: func.name.clone(),
+            metrics: func.into(),
+        }
+    }
+}
+
+impl ComplexityDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+            && self.renamed.is_empty()
+    }
+}
+
+impl ComplexityAnalyzer {
+    /// Match functions by qualified name and report what changed. Functions
+    /// left unmatched are paired as renames when their bodies hash the same,
+    /// or when exactly one function on each side has the same short name
+    /// (e.g. `Point3D::new` after renaming the type to `Point`).
+    pub fn diff(old: &[FunctionComplexity], new: &[FunctionComplexity]) -> ComplexityDiff {
+        let mut diff = ComplexityDiff::default();
+        let old_by_name: BTreeMap<&str, &FunctionComplexity> =
+            old.iter().map(|f| (f.name.as_str(), f)).collect();
+        let new_by_name: BTreeMap<&str, &FunctionComplexity> =
+            new.iter().map(|f| (f.name.as_str(), f)).collect();
+
+        for (name, before) in &old_by_name {
+            if let Some(after) = new_by_name.get(name) {
+                let change = FunctionChange::new(before, after);
+                if change.before != change.after {
+                    diff.changed.push(change);
+                }
+            }
+        }
+
+        let mut removed: Vec<&FunctionComplexity> = old_by_name
+            .iter()
+            .filter(|(name, _)| !new_by_name.contains_key(*name))
+            .map(|(_, f)| *f)
+            .collect();
+        let mut added: Vec<&FunctionComplexity> = new_by_name
+            .iter()
+            .filter(|(name, _)| !old_by_name.contains_key(*name))
+            .map(|(_, f)| *f)
+            .collect();
+
+        // Identical bodies first, then unambiguous short names
+        removed.retain(|before| {
+            match added
+                .iter()
+                .position(|after| after.body_hash == before.body_hash)
+            {
+                Some(i) => {
+                    diff.renamed
+                        .push(FunctionChange::new(before, added.remove(i)));
+                    false
+                }
+                None => true,
+            }
+        });
+        let short_name_count = |functions: &[&FunctionComplexity], short: &str| {
+            functions.iter().filter(|f| f.short_name() == short).count()
+        };
+        let unique_pairs: Vec<(usize, usize)> = removed
+            .iter()
+            .enumerate()
+            .filter_map(|(i, before)| {
+                let short = before.short_name();
+                if short_name_count(&removed, short) != 1 || short_name_count(&added, short) != 1 {
+                    return None;
+                }
+                let j = added.iter().position(|after| after.short_name() == short)?;
+                Some((i, j))
+            })
+            .collect();
+        for &(i, j) in &unique_pairs {
+            diff.renamed.push(FunctionChange::new(removed[i], added[j]));
+        }
+        let paired_old: Vec<usize> = unique_pairs.iter().map(|(i, _)| *i).collect();
+        let paired_new: Vec<usize> = unique_pairs.iter().map(|(_, j)| *j).collect();
+
+        diff.removed = removed
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !paired_old.contains(i))
+            .map(|(_, f)| MetricEntry::from(*f))
+            .collect();
+        diff.added = added
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| !paired_new.contains(j))
+            .map(|(_, f)| MetricEntry::from(*f))
+            .collect();
+        diff
+    }
+}
@@ -0,0 +1,158 @@
+use super::{AnalyzeOptions, ComplexityAnalyzer, ComplexityDiff, FunctionComplexity};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// Only in the working tree, either new since the revision or untracked
+    Added,
+    /// Only in the revision
+    Deleted,
+    Modified,
+}
+
+/// Complexity changes of one `.rs` file between a revision and the working tree
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    /// Relative to the directory the comparison ran in
+    pub file: PathBuf,
+    pub status: FileStatus,
+    pub diff: ComplexityDiff,
+}
+
+/// Run git in `dir` and return its stdout
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("git printed non UTF-8 output")
+}
+
+/// `.rs` files below `dir` (or just `pathspec` within it) that differ between
+/// `rev` and the working tree, untracked files included. Renames show up as a
+/// deletion plus an addition.
+pub fn changed_rust_files(
+    dir: &Path,
+    rev: &str,
+    pathspec: &str,
+) -> Result<Vec<(PathBuf, FileStatus)>> {
+    let diff = git(
+        dir,
+        &[
+            "diff",
+            "--name-status",
+            "--no-renames",
+            "--relative",
+            rev,
+            "--",
+            pathspec,
+        ],
+    )?;
+    let mut files: Vec<(PathBuf, FileStatus)> = diff
+        .lines()
+        .filter_map(|line| {
+            let (status, file) = line.split_once('\t')?;
+            let status = match status {
+                "A" => FileStatus::Added,
+                "D" => FileStatus::Deleted,
+                _ => FileStatus::Modified,
+            };
+            Some((PathBuf::from(file), status))
+        })
+        .collect();
+
+    let untracked = git(
+        dir,
+        &["ls-files", "--others", "--exclude-standard", "--", pathspec],
+    )?;
+    files.extend(
+        untracked
+            .lines()
+            .map(|file| (PathBuf::from(file), FileStatus::Added)),
+    );
+    files.retain(|(file, _)| file.extension().is_some_and(|e| e == "rs"));
+    files.sort();
+    Ok(files)
+}
+
+/// Contents of `file` (relative to `dir`) as of `rev`
+pub fn file_at_revision(dir: &Path, rev: &str, file: &Path) -> Result<String> {
+    // `./` makes git resolve the path against `dir` instead of the repository root
+    let spec = format!("{}:./{}", rev, file.to_string_lossy().replace('\\', "/"));
+    git(dir, &["show", &spec])
+}
+
+fn analyze(
+    content: &str,
+    file: &Path,
+    options: &AnalyzeOptions,
+) -> Result<Vec<FunctionComplexity>> {
+    ComplexityAnalyzer::analyze_file_with(content, options)
+        .with_context(|| format!("Failed to analyze {}", file.display()))
+}
+
+/// Compare every changed `.rs` file at `path` (a directory or a single file
+/// in a git work tree) against `rev`. Files without function-level changes
+/// are left out.
+pub fn diff_against_revision(
+    path: &Path,
+    rev: &str,
+    options: &AnalyzeOptions,
+) -> Result<Vec<FileDiff>> {
+    let (dir, pathspec) = if path.is_file() {
+        let name = path
+            .file_name()
+            .with_context(|| format!("Not a file: {}", path.display()))?;
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty());
+        (
+            dir.unwrap_or(Path::new(".")),
+            name.to_string_lossy().into_owned(),
+        )
+    } else {
+        (path, ".".to_string())
+    };
+    git(
+        dir,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", rev),
+        ],
+    )
+    .with_context(|| format!("Unknown git revision: {}", rev))?;
+
+    let mut diffs = Vec::new();
+    for (file, status) in changed_rust_files(dir, rev, &pathspec)? {
+        let before = match status {
+            FileStatus::Added => Vec::new(),
+            _ => analyze(&file_at_revision(dir, rev, &file)?, &file, options)?,
+        };
+        let after = match status {
+            FileStatus::Deleted => Vec::new(),
+            _ => {
+                let full = dir.join(&file);
+                let content = std::fs::read_to_string(&full)
+                    .with_context(|| format!("Failed to read file: {}", full.display()))?;
+                analyze(&content, &file, options)?
+            }
+        };
+        let diff = ComplexityAnalyzer::diff(&before, &after);
+        if !diff.is_empty() {
+            diffs.push(FileDiff { file, status, diff });
+        }
+    }
+    Ok(diffs)
+}
@@ -0,0 +1,148 @@
+use super::{AnalyzeOptions, ComplexityAnalyzer, FunctionComplexity};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Upper limits (inclusive) checked by [`lint_file`]; `None` disables a check
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LintConfig {
+    pub max_cyclomatic: Option<usize>,
+    pub max_cognitive: Option<usize>,
+    pub max_params: Option<usize>,
+    pub max_nesting: Option<usize>,
+    pub max_lines: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintMetric {
+    Cyclomatic,
+    Cognitive,
+    Parameters,
+    Nesting,
+    Lines,
+}
+
+/// A function exceeding one of the configured limits
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Violation {
+    pub function: String,
+    pub metric: LintMetric,
+    pub actual: usize,
+    pub limit: usize,
+    pub file: Option<PathBuf>,
+    pub line: usize,
+}
+
+impl LintConfig {
+    fn limits(&self) -> [(LintMetric, Option<usize>); 5] {
+        [
+            (LintMetric::Cyclomatic, self.max_cyclomatic),
+            (LintMetric::Cognitive, self.max_cognitive),
+            (LintMetric::Parameters, self.max_params),
+            (LintMetric::Nesting, self.max_nesting),
+            (LintMetric::Lines, self.max_lines),
+        ]
+    }
+}
+
+impl LintMetric {
+    pub const ALL: [LintMetric; 5] = [
+        Self::Cyclomatic,
+        Self::Cognitive,
+        Self::Parameters,
+        Self::Nesting,
+        Self::Lines,
+    ];
+
+    /// Stable identifier for reports, e.g. `copartner/cyclomatic-complexity`
+    pub fn rule_id(self) -> &'static str {
+        match self {
+            Self::Cyclomatic => "copartner/cyclomatic-complexity",
+            Self::Cognitive => "copartner/cognitive-complexity",
+            Self::Parameters => "copartner/parameter-count",
+            Self::Nesting => "copartner/nesting-depth",
+            Self::Lines => "copartner/line-count",
+        }
+    }
+
+    pub fn value(self, func: &FunctionComplexity) -> usize {
+        match self {
+            Self::Cyclomatic => func.cyclomatic_complexity,
+            Self::Cognitive => func.cognitive_complexity,
+            Self::Parameters => func.parameter_count,
+            Self::Nesting => func.details.max_nesting_depth,
+            Self::Lines => func.line_count,
+        }
+    }
+}
+
+/// Check already analyzed functions, one violation per exceeded limit.
+/// Suppressed functions are skipped.
+pub fn lint_functions(functions: &[FunctionComplexity], config: &LintConfig) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    for func in functions.iter().filter(|f| !f.suppressed) {
+        for (metric, limit) in config.limits() {
+            let Some(limit) = limit else { continue };
+            let actual = metric.value(func);
+            if actual > limit {
+                violations.push(Violation {
+                    function: func.name.clone(),
+                    metric,
+                    actual,
+                    limit,
+                    file: func.file.clone(),
+                    line: func.start_line,
+                });
+            }
+        }
+    }
+    violations
+}
+
+pub fn lint_file(content: &str, config: &LintConfig) -> Result<Vec<Violation>> {
+    let functions = ComplexityAnalyzer::analyze_file(content)?;
+    Ok(lint_functions(&functions, config))
+}
+
+/// Lint every `.rs` file below `path`. Unlike `analyze_project`, files that
+/// cannot be read or parsed are an error, so a build never passes by accident.
+pub fn lint_project(path: &Path, config: &LintConfig) -> Result<Vec<Violation>> {
+    let project = ComplexityAnalyzer::analyze_project(path, &AnalyzeOptions::default(), None)?;
+    if !project.skipped.is_empty() {
+        let reasons: Vec<String> = project
+            .skipped
+            .iter()
+            .map(|(file, reason)| format!("{}: {}", file.display(), reason))
+            .collect();
+        anyhow::bail!("Failed to analyze:\n{}", reasons.join("\n"));
+    }
+    let functions: Vec<FunctionComplexity> = project.into_functions();
+    Ok(lint_functions(&functions, config))
+}
+
+impl std::fmt::Display for LintMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cyclomatic => write!(f, "cyclomatic complexity"),
+            Self::Cognitive => write!(f, "cognitive complexity"),
+            Self::Parameters => write!(f, "parameter count"),
+            Self::Nesting => write!(f, "nesting depth"),
+            Self::Lines => write!(f, "line count"),
+        }
+    }
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}: ", file.display(), self.line)?,
+            None => write!(f, "line {}: ", self.line)?,
+        }
+        write!(
+            f,
+            "`{}` {} is {} (limit {})",
+            self.function, self.metric, self.actual, self.limit
+        )
+    }
+}
@@ -0,0 +1,2077 @@
+pub mod baseline;
+pub mod cache;
+pub mod config;
+pub mod diff;
+pub mod git;
+pub mod lint;
+pub mod query;
+pub mod refactor;
+pub mod report;
+pub mod sarif;
+pub mod score;
+pub mod walk;
+
+pub use baseline::{Baseline, BaselineStatus};
+pub use cache::AnalysisCache;
+pub use config::CopartnerConfig;
+pub use diff::{ComplexityDiff, FunctionChange, MetricEntry, MetricSnapshot};
+pub use query::{ComplexityReportExt, Metric};
+pub use score::{ComplexityScore, ScoreWeights};
+pub use walk::FileSelection;
+
+use crate::analyzer::util::path_to_string;
+use anyhow::{Context, Result};
+use quote::ToTokens;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use syn::{punctuated::Punctuated, spanned::Spanned, visit::Visit, *};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FunctionComplexity {
+    pub name: String,
+    pub cyclomatic_complexity: usize,
+    pub cognitive_complexity: usize,
+    /// Pre-Sonar cognitive formula (branches + nesting * 2 + unsafe * 3).
+    /// Deprecated: kept for one release so historical exports stay comparable.
+    pub legacy_cognitive: usize,
+    /// Physical lines from the signature to the closing brace, including
+    /// blank lines and comments
+    pub line_count: usize,
+    /// Statements in the body, nested blocks included
+    pub logical_lines: usize,
+    /// 1-based line of the `fn` signature (0 when span locations are unavailable)
+    pub start_line: usize,
+    /// 1-based line of the closing brace
+    pub end_line: usize,
+    /// Source file, set when analyzed through a path-based entry point
+    pub file: Option<PathBuf>,
+    pub parameter_count: usize,
+    pub is_async: bool,
+    pub is_const: bool,
+    pub is_unsafe: bool,
+    /// "pub", "pub(crate)", "pub(super)", "pub(in path)" or "private"
+    pub visibility: String,
+    /// Rating bucket of the cyclomatic complexity. Despite the name it says
+    /// nothing about the return type; see `ComplexityDetails::signature_complexity`.
+    pub return_complexity: ComplexityRating,
+    pub details: ComplexityDetails,
+    pub halstead: HalsteadMetrics,
+    /// Has at least one `///` or `#[doc = ...]` attribute
+    pub has_doc_comment: bool,
+    /// Comment lines / total lines of the function; only computed by the
+    /// file-level APIs since comments are not part of the syntax tree
+    pub comment_density: f32,
+    /// SHA-256 of the body's tokens, so formatting and comments don't change it
+    pub body_hash: String,
+    /// Opted out of findings via `#[allow(copartner::complexity)]` or a
+    /// `// copartner:ignore-complexity` comment right above the function.
+    /// Still analyzed and counted, but skipped by lints and threshold filters.
+    pub suppressed: bool,
+    /// The impl block this method belongs to; `None` for free functions
+    pub impl_parent: Option<ImplParent>,
+}
+
+/// Self type and trait of an impl block, by their last path segment. The type
+/// is prefixed with the inline modules the impl block sits in.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ImplParent {
+    pub type_name: String,
+    pub trait_name: Option<String>,
+}
+
+impl ImplParent {
+    /// `Type` or `<Type as Trait>`, as used in qualified function names
+    pub fn prefix(&self) -> String {
+        match &self.trait_name {
+            Some(trait_name) => format!("<{} as {}>", self.type_name, trait_name),
+            None => self.type_name.clone(),
+        }
+    }
+}
+
+impl FunctionComplexity {
+    /// Name without impl qualification (`<Point as Display>::fmt` -> `fmt`)
+    pub fn short_name(&self) -> &str {
+        self.name.rsplit("::").next().unwrap_or(&self.name)
+    }
+
+    /// Declared plain `pub`; restricted visibility like `pub(crate)` is not public API
+    pub fn is_public(&self) -> bool {
+        self.visibility == "pub"
+    }
+
+    /// `path:line` when the file is known, otherwise `line N`
+    pub fn location(&self) -> String {
+        match &self.file {
+            Some(file) => format!("{}:{}", file.display(), self.start_line),
+            None => format!("line {}", self.start_line),
+        }
+    }
+
+    /// Classic maintainability index:
+    /// `171 - 5.2 * ln(V) - 0.23 * CC - 16.2 * ln(LOC)`, clamped to 0-100.
+    pub fn maintainability_index(&self) -> f64 {
+        let volume = self.halstead.volume.max(1.0);
+        let loc = self.line_count.max(1) as f64;
+        let mi =
+            171.0 - 5.2 * volume.ln() - 0.23 * self.cyclomatic_complexity as f64 - 16.2 * loc.ln();
+        mi.clamp(0.0, 100.0)
+    }
+}
+
+/// Aggregate complexity for all functions of a single file
+#[derive(Debug, Clone)]
+pub struct FileComplexity {
+    pub functions: Vec<FunctionComplexity>,
+    pub total_lines: usize,
+    pub total_cyclomatic: usize,
+    pub max_cyclomatic: usize,
+    pub average_cyclomatic: f64,
+    pub average_cognitive: f64,
+    /// Average maintainability index across functions (100 for files without functions)
+    pub maintainability_index: f64,
+}
+
+impl FileComplexity {
+    pub fn from_functions(functions: Vec<FunctionComplexity>) -> Self {
+        let count = functions.len();
+        let total_lines = functions.iter().map(|f| f.line_count).sum();
+        let total_cyclomatic = functions.iter().map(|f| f.cyclomatic_complexity).sum();
+        let max_cyclomatic = functions
+            .iter()
+            .map(|f| f.cyclomatic_complexity)
+            .max()
+            .unwrap_or(0);
+        let total_cognitive: usize = functions.iter().map(|f| f.cognitive_complexity).sum();
+        let total_mi: f64 = functions.iter().map(|f| f.maintainability_index()).sum();
+
+        let (average_cyclomatic, average_cognitive, maintainability_index) = if count > 0 {
+            (
+                total_cyclomatic as f64 / count as f64,
+                total_cognitive as f64 / count as f64,
+                total_mi / count as f64,
+            )
+        } else {
+            (0.0, 0.0, 100.0)
+        };
+
+        Self {
+            functions,
+            total_lines,
+            total_cyclomatic,
+            max_cyclomatic,
+            average_cyclomatic,
+            average_cognitive,
+            maintainability_index,
+        }
+    }
+}
+
+/// Number of functions in each rating bucket
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RatingDistribution {
+    pub low: usize,
+    pub medium: usize,
+    pub high: usize,
+    pub very_high: usize,
+}
+
+impl RatingDistribution {
+    pub fn add(&mut self, rating: &ComplexityRating) {
+        match rating {
+            ComplexityRating::Low => self.low += 1,
+            ComplexityRating::Medium => self.medium += 1,
+            ComplexityRating::High => self.high += 1,
+            ComplexityRating::VeryHigh => self.very_high += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.low + self.medium + self.high + self.very_high
+    }
+
+    /// Share of functions in `rating`'s bucket, in percent (0 when empty)
+    pub fn percentage(&self, rating: &ComplexityRating) -> f64 {
+        let count = match rating {
+            ComplexityRating::Low => self.low,
+            ComplexityRating::Medium => self.medium,
+            ComplexityRating::High => self.high,
+            ComplexityRating::VeryHigh => self.very_high,
+        };
+        match self.total() {
+            0 => 0.0,
+            total => count as f64 / total as f64 * 100.0,
+        }
+    }
+}
+
+/// Totals, averages and the most complex entries of a set of functions
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ComplexitySummary {
+    pub total_functions: usize,
+    pub total_lines: usize,
+    pub max_cyclomatic: usize,
+    pub average_cyclomatic: f64,
+    pub average_cognitive: f64,
+    pub average_maintainability_index: f64,
+    /// Percentage of functions with a doc comment
+    pub doc_coverage: f64,
+    pub distribution: RatingDistribution,
+    /// Functions opted out of findings; counted above but not in `top_functions`
+    pub suppressed: usize,
+    /// Highest cyclomatic complexity first; ties keep their input order
+    pub top_functions: Vec<FunctionComplexity>,
+}
+
+impl ComplexitySummary {
+    pub const DEFAULT_TOP_N: usize = 5;
+
+    pub fn from_functions<'a>(functions: impl IntoIterator<Item = &'a FunctionComplexity>) -> Self {
+        Self::with_top_n(functions, Self::DEFAULT_TOP_N)
+    }
+
+    pub fn with_top_n<'a>(
+        functions: impl IntoIterator<Item = &'a FunctionComplexity>,
+        top_n: usize,
+    ) -> Self {
+        let functions: Vec<&FunctionComplexity> = functions.into_iter().collect();
+        let mut summary = Self {
+            total_functions: functions.len(),
+            ..Default::default()
+        };
+
+        let mut total_cyclomatic = 0;
+        let mut total_cognitive = 0;
+        let mut total_mi = 0.0;
+        let mut documented = 0;
+        for func in &functions {
+            summary.total_lines += func.line_count;
+            summary.max_cyclomatic = summary.max_cyclomatic.max(func.cyclomatic_complexity);
+            summary.distribution.add(&func.return_complexity);
+            documented += usize::from(func.has_doc_comment);
+            summary.suppressed += usize::from(func.suppressed);
+            total_cyclomatic += func.cyclomatic_complexity;
+            total_cognitive += func.cognitive_complexity;
+            total_mi += func.maintainability_index();
+        }
+
+        if summary.total_functions > 0 {
+            let count = summary.total_functions as f64;
+            summary.average_cyclomatic = total_cyclomatic as f64 / count;
+            summary.average_cognitive = total_cognitive as f64 / count;
+            summary.average_maintainability_index = total_mi / count;
+            summary.doc_coverage = documented as f64 / count * 100.0;
+        }
+
+        summary.top_functions = functions
+            .into_iter()
+            .filter(|f| !f.suppressed)
+            .collect::<Vec<_>>()
+            .top_by_cyclomatic(top_n)
+            .into_iter()
+            .cloned()
+            .collect();
+        summary
+    }
+
+    /// Functions rated High or Very High
+    pub fn high_complexity_count(&self) -> usize {
+        self.distribution.high + self.distribution.very_high
+    }
+}
+
+/// Results of analyzing every Rust file below a directory
+#[derive(Debug, Clone, Default)]
+pub struct ProjectComplexity {
+    /// Per-file results keyed by path relative to the analyzed root
+    pub files: BTreeMap<PathBuf, FileComplexity>,
+    /// Files that could not be read or parsed, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+    pub total_functions: usize,
+    pub total_lines: usize,
+    pub average_cyclomatic: f64,
+    pub average_cognitive: f64,
+    pub average_maintainability_index: f64,
+    /// Percentage of functions with a doc comment
+    pub doc_coverage: f64,
+    pub distribution: RatingDistribution,
+}
+
+impl ProjectComplexity {
+    fn from_files(
+        files: BTreeMap<PathBuf, FileComplexity>,
+        skipped: Vec<(PathBuf, String)>,
+    ) -> Self {
+        let mut project = Self {
+            files,
+            skipped,
+            ..Default::default()
+        };
+
+        let summary = project.summary();
+        project.total_functions = summary.total_functions;
+        project.total_lines = summary.total_lines;
+        project.average_cyclomatic = summary.average_cyclomatic;
+        project.average_cognitive = summary.average_cognitive;
+        project.average_maintainability_index = summary.average_maintainability_index;
+        project.doc_coverage = summary.doc_coverage;
+        project.distribution = summary.distribution;
+        project
+    }
+
+    /// Aggregates over every analyzed function
+    pub fn summary(&self) -> ComplexitySummary {
+        ComplexitySummary::from_functions(self.functions())
+    }
+
+    /// All analyzed functions, in file path order
+    pub fn functions(&self) -> impl Iterator<Item = &FunctionComplexity> {
+        self.files.values().flat_map(|f| f.functions.iter())
+    }
+
+    pub fn into_functions(self) -> Vec<FunctionComplexity> {
+        self.files.into_values().flat_map(|f| f.functions).collect()
+    }
+}
+
+/// Aggregate complexity of the methods in the impl blocks of one type. Inherent
+/// and trait impls are kept apart, multiple inherent blocks are merged.
+#[derive(Debug, Clone, Serialize)]
+pub struct ImplComplexity {
+    pub type_name: String,
+    pub trait_name: Option<String>,
+    /// Source file, when the methods were analyzed through a path-based entry point
+    pub file: Option<PathBuf>,
+    pub method_count: usize,
+    pub total_cyclomatic: usize,
+    pub max_cyclomatic: usize,
+    pub rating: ComplexityRating,
+}
+
+impl ImplComplexity {
+    /// Total scores are divided by this before rating against the function
+    /// thresholds, so a handful of simple methods still rates Low
+    pub const SCORE_SCALE: usize = 5;
+
+    /// Group methods by file and impl parent; free functions are ignored.
+    /// Sorted by total complexity, highest first.
+    pub fn group(
+        functions: &[FunctionComplexity],
+        thresholds: &ComplexityThresholds,
+    ) -> Vec<ImplComplexity> {
+        let mut groups: BTreeMap<(Option<&PathBuf>, &ImplParent), Vec<&FunctionComplexity>> =
+            BTreeMap::new();
+        for func in functions {
+            if let Some(parent) = &func.impl_parent {
+                groups
+                    .entry((func.file.as_ref(), parent))
+                    .or_default()
+                    .push(func);
+            }
+        }
+
+        let mut impls: Vec<ImplComplexity> = groups
+            .into_iter()
+            .map(|((file, parent), methods)| {
+                let total_cyclomatic = methods.iter().map(|f| f.cyclomatic_complexity).sum();
+                ImplComplexity {
+                    type_name: parent.type_name.clone(),
+                    trait_name: parent.trait_name.clone(),
+                    file: file.cloned(),
+                    method_count: methods.len(),
+                    total_cyclomatic,
+                    max_cyclomatic: methods
+                        .iter()
+                        .map(|f| f.cyclomatic_complexity)
+                        .max()
+                        .unwrap_or(0),
+                    rating: ComplexityRating::from_score_with(
+                        total_cyclomatic / Self::SCORE_SCALE,
+                        thresholds,
+                    ),
+                }
+            })
+            .collect();
+        impls.sort_by_key(|i| std::cmp::Reverse(i.total_cyclomatic));
+        impls
+    }
+
+    pub fn average_cyclomatic(&self) -> f64 {
+        if self.method_count == 0 {
+            0.0
+        } else {
+            self.total_cyclomatic as f64 / self.method_count as f64
+        }
+    }
+
+    /// `Type` or `<Type as Trait>`
+    pub fn display_name(&self) -> String {
+        ImplParent {
+            type_name: self.type_name.clone(),
+            trait_name: self.trait_name.clone(),
+        }
+        .prefix()
+    }
+}
+
+/// Name given to bare statements by [`ComplexityAnalyzer::analyze_snippet`]
+pub const SNIPPET_FN_NAME: &str = "__snippet";
+
+/// Result of [`ComplexityAnalyzer::analyze_file_lenient`]
+#[derive(Debug, Clone, Default)]
+pub struct LenientAnalysis {
+    pub functions: Vec<FunctionComplexity>,
+    /// 1-based inclusive line ranges of top-level items that failed to parse
+    pub unparsed: Vec<(usize, usize)>,
+}
+
+/// Structural size of a struct or enum definition
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeComplexity {
+    pub name: String,
+    pub kind: TypeKind,
+    /// For enums, summed across all variants
+    pub field_count: usize,
+    /// Always 0 for structs
+    pub variant_count: usize,
+    pub generic_parameters: usize,
+    pub derive_count: usize,
+    pub start_line: usize,
+    pub rating: ComplexityRating,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum TypeKind {
+    Struct,
+    Enum,
+}
+
+impl TypeComplexity {
+    /// Type scores are divided by this before rating against the function
+    /// thresholds, since a field or variant is cheaper to read than a branch
+    pub const SCORE_SCALE: usize = 2;
+
+    /// Fields of a struct or variants of an enum, plus generic parameters
+    pub fn score(&self) -> usize {
+        let members = match self.kind {
+            TypeKind::Struct => self.field_count,
+            TypeKind::Enum => self.variant_count,
+        };
+        members + self.generic_parameters
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComplexityDetails {
+    pub if_statements: usize,
+    pub let_else: usize,
+    pub match_arms: usize,
+    /// Match arms with an `if` guard
+    pub match_guards: usize,
+    pub loops: usize,
+    pub boolean_operators: usize,
+    pub try_operators: usize,
+    pub await_points: usize,
+    /// `return` expressions other than a trailing `return` at the end of the body
+    pub early_returns: usize,
+    /// The function calls itself directly (mutual recursion is not detected)
+    pub is_recursive: bool,
+    pub nested_functions: usize,
+    pub function_calls: usize,
+    pub max_nesting_depth: usize,
+    /// Deepest nesting of sub-expressions within a single statement
+    pub max_expression_depth: usize,
+    // Advanced analysis features
+    pub function_call_chain: Vec<String>,
+    pub macro_invocations: Vec<String>,
+    pub module_dependencies: Vec<String>,
+    pub unsafe_blocks: usize,
+    pub generic_parameters: usize,
+    /// Generic nesting depth of the return type plus trait bounds in the where clause
+    pub signature_complexity: usize,
+    /// Trait bounds on the signature's type parameters, inline and in the where clause
+    pub bound_count: usize,
+    // Error-handling smells
+    pub unwrap_calls: usize,
+    pub expect_calls: usize,
+    /// `panic!`, `unreachable!`, `todo!` and `unimplemented!`
+    pub panic_macros: usize,
+    /// Attributes on the function other than doc comments, e.g. `inline(always)`
+    /// or `tokio::main`
+    pub attributes: Vec<String>,
+}
+
+impl ComplexityDetails {
+    /// Constructs that can panic at runtime
+    pub fn panic_points(&self) -> usize {
+        self.unwrap_calls + self.expect_calls + self.panic_macros
+    }
+}
+
+/// Halstead software science metrics computed over a function body.
+///
+/// Tokens are classified as follows:
+/// - operators: punctuation (joint characters such as `::`, `&&`, `+=` and `->` form a
+///   single operator), delimiter pairs `()`, `[]` and `{}`, and keywords such as `let`,
+///   `if`, `match`, `return`, `as` or `mut`
+/// - operands: identifiers, literals, lifetimes, and the value-like keywords `self`,
+///   `Self`, `crate`, `super`, `true` and `false`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HalsteadMetrics {
+    pub distinct_operators: usize,
+    pub distinct_operands: usize,
+    pub total_operators: usize,
+    pub total_operands: usize,
+    pub volume: f64,
+    pub difficulty: f64,
+    pub effort: f64,
+}
+
+// Keywords not listed here (`self`, `Self`, `crate`, `super`, `true`, `false`) are operands
+const OPERATOR_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "dyn", "else", "enum", "extern", "fn",
+    "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "static", "struct", "trait", "type", "unsafe", "use", "where", "while", "yield",
+];
+
+impl HalsteadMetrics {
+    pub fn from_tokens(tokens: proc_macro2::TokenStream) -> Self {
+        let mut counter = HalsteadCounter::default();
+        counter.walk(tokens);
+
+        let n1 = counter.operators.len();
+        let n2 = counter.operands.len();
+        let total_operators = counter.total_operators;
+        let total_operands = counter.total_operands;
+
+        let vocabulary = (n1 + n2) as f64;
+        let length = (total_operators + total_operands) as f64;
+        let volume = if vocabulary > 0.0 {
+            length * vocabulary.log2()
+        } else {
+            0.0
+        };
+        let difficulty = if n2 > 0 {
+            (n1 as f64 / 2.0) * (total_operands as f64 / n2 as f64)
+        } else {
+            0.0
+        };
+
+        Self {
+            distinct_operators: n1,
+            distinct_operands: n2,
+            total_operators,
+            total_operands,
+            volume,
+            difficulty,
+            effort: difficulty * volume,
+        }
+    }
+}
+
+#[derive(Default)]
+struct HalsteadCounter {
+    operators: std::collections::HashSet<String>,
+    operands: std::collections::HashSet<String>,
+    total_operators: usize,
+    total_operands: usize,
+}
+
+impl HalsteadCounter {
+    fn walk(&mut self, tokens: proc_macro2::TokenStream) {
+        use proc_macro2::{Delimiter, Spacing, TokenTree};
+
+        let mut punct = String::new();
+        let mut iter = tokens.into_iter().peekable();
+        while let Some(tree) = iter.next() {
+            match tree {
+                TokenTree::Group(group) => {
+                    let op = match group.delimiter() {
+                        Delimiter::Parenthesis => "()",
+                        Delimiter::Bracket => "[]",
+                        Delimiter::Brace => "{}",
+                        Delimiter::None => "",
+                    };
+                    if !op.is_empty() {
+                        self.operator(op.to_string());
+                    }
+                    self.walk(group.stream());
+                }
+                TokenTree::Ident(ident) => {
+                    let name = ident.to_string();
+                    if OPERATOR_KEYWORDS.contains(&name.as_str()) {
+                        self.operator(name);
+                    } else {
+                        self.operand(name);
+                    }
+                }
+                TokenTree::Literal(lit) => self.operand(lit.to_string()),
+                TokenTree::Punct(p) if p.as_char() == '\'' => {
+                    // Lifetime or label: `'a`
+                    if let Some(TokenTree::Ident(ident)) = iter.next() {
+                        self.operand(format!("'{}", ident));
+                    }
+                }
+                TokenTree::Punct(p) => {
+                    punct.push(p.as_char());
+                    let continues = p.spacing() == Spacing::Joint
+                        && matches!(iter.peek(), Some(TokenTree::Punct(next)) if next.as_char() != '\'');
+                    if !continues {
+                        self.operator(std::mem::take(&mut punct));
+                    }
+                }
+            }
+        }
+    }
+
+    fn operator(&mut self, op: String) {
+        self.total_operators += 1;
+        self.operators.insert(op);
+    }
+
+    fn operand(&mut self, operand: String) {
+        self.total_operands += 1;
+        self.operands.insert(operand);
+    }
+}
+
+/// Ordered from best to worst, so `rating >= ComplexityRating::High` selects
+/// High and Very High
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ComplexityRating {
+    Low,      // 1-5
+    Medium,   // 6-10
+    High,     // 11-20
+    VeryHigh, // 21+
+}
+
+/// Upper bounds (inclusive) of each rating bucket
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComplexityThresholds {
+    pub low_max: usize,
+    pub medium_max: usize,
+    pub high_max: usize,
+}
+
+impl Default for ComplexityThresholds {
+    fn default() -> Self {
+        Self {
+            low_max: 5,
+            medium_max: 10,
+            high_max: 20,
+        }
+    }
+}
+
+impl ComplexityThresholds {
+    /// Bounds must be strictly increasing so every bucket is reachable
+    pub fn validate(&self) -> Result<()> {
+        if self.low_max < self.medium_max && self.medium_max < self.high_max {
+            Ok(())
+        } else {
+            anyhow::bail!(
+                "Invalid thresholds: expected low_max < medium_max < high_max, got {} / {} / {}",
+                self.low_max,
+                self.medium_max,
+                self.high_max
+            )
+        }
+    }
+}
+
+impl ComplexityRating {
+    pub fn from_score(score: usize) -> Self {
+        Self::from_score_with(score, &ComplexityThresholds::default())
+    }
+
+    pub fn from_score_with(score: usize, thresholds: &ComplexityThresholds) -> Self {
+        if score <= thresholds.low_max {
+            Self::Low
+        } else if score <= thresholds.medium_max {
+            Self::Medium
+        } else if score <= thresholds.high_max {
+            Self::High
+        } else {
+            Self::VeryHigh
+        }
+    }
+}
+
+/// Knobs that change which functions are analyzed and how they are scored.
+/// The default keeps the historical behavior so existing exports stay comparable.
+#[derive(Debug, Clone)]
+pub struct AnalyzeOptions {
+    /// Treat each `?` operator as an early-return decision point
+    pub count_try: bool,
+    /// Analyze `#[test]` functions and `#[cfg(test)]` modules
+    pub include_tests: bool,
+    /// Bounds used to derive each function's rating
+    pub thresholds: ComplexityThresholds,
+    /// Directory depth limit for `analyze_project` (`None` walks the whole tree)
+    pub max_depth: Option<usize>,
+    /// Ignore files and globs deciding which files `analyze_project` visits
+    pub selection: FileSelection,
+}
+
+impl Default for AnalyzeOptions {
+    fn default() -> Self {
+        Self {
+            count_try: false,
+            include_tests: true,
+            thresholds: ComplexityThresholds::default(),
+            max_depth: None,
+            selection: FileSelection::default(),
+        }
+    }
+}
+
+pub struct ComplexityAnalyzer;
+
+impl ComplexityAnalyzer {
+    pub fn analyze_file(content: &str) -> Result<Vec<FunctionComplexity>> {
+        Self::analyze_file_with(content, &AnalyzeOptions::default())
+    }
+
+    /// Read and analyze a file, recording its path on every function
+    pub fn analyze_file_at(path: &Path) -> Result<Vec<FunctionComplexity>> {
+        Self::analyze_file_at_with(path, &AnalyzeOptions::default())
+    }
+
+    pub fn analyze_file_at_with(
+        path: &Path,
+        options: &AnalyzeOptions,
+    ) -> Result<Vec<FunctionComplexity>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read file: {}", path.display()))?;
+        let mut functions = Self::analyze_file_with(&content, options)
+            .with_context(|| format!("Failed to analyze {}", path.display()))?;
+        for func in &mut functions {
+            func.file = Some(path.to_path_buf());
+        }
+        Ok(functions)
+    }
+
+    /// Analyze every `.rs` file below `path` in parallel, skipping `target/`
+    /// directories and whatever `options.selection` leaves out (by default,
+    /// gitignored files). Files that fail to read or parse are reported in
+    /// `ProjectComplexity::skipped` instead of aborting the run. With a cache,
+    /// files whose contents are unchanged since a previous run are not parsed.
+    pub fn analyze_project(
+        path: &Path,
+        options: &AnalyzeOptions,
+        mut cache: Option<&mut AnalysisCache>,
+    ) -> Result<ProjectComplexity> {
+        if !path.is_dir() {
+            anyhow::bail!("Not a directory: {}", path.display());
+        }
+
+        let walk::SourceFiles {
+            files: sources,
+            errors: mut skipped,
+        } = walk::rust_files(path, &options.selection, options.max_depth)?;
+
+        // Read everything up front so cache hits never reach the parser
+        let reads: Vec<_> = sources
+            .par_iter()
+            .map(|file| {
+                let content = std::fs::read_to_string(file)
+                    .with_context(|| format!("Failed to read file: {}", file.display()));
+                (file, content)
+            })
+            .collect();
+
+        let mut results = Vec::with_capacity(reads.len());
+        let mut pending = Vec::new();
+        for (file, content) in reads {
+            match content {
+                Ok(content) => {
+                    let key = AnalysisCache::key(&content, options);
+                    match cache.as_deref_mut().and_then(|c| c.get(&key)) {
+                        Some(functions) => results.push((file, Ok(functions))),
+                        None => pending.push((file, key, content)),
+                    }
+                }
+                Err(e) => results.push((file, Err(e))),
+            }
+        }
+
+        let parsed: Vec<_> = pending
+            .into_par_iter()
+            .map(|(file, key, content)| {
+                let result = Self::analyze_file_with(&content, options)
+                    .with_context(|| format!("Failed to analyze {}", file.display()));
+                (file, key, result)
+            })
+            .collect();
+        for (file, key, result) in parsed {
+            if let (Some(cache), Ok(functions)) = (cache.as_deref_mut(), &result) {
+                cache.insert(key, functions.clone());
+            }
+            results.push((file, result));
+        }
+
+        let mut files = BTreeMap::new();
+        for (file, result) in results {
+            let rel = pathdiff::diff_paths(file, path).unwrap_or_else(|| file.clone());
+            match result {
+                Ok(mut functions) => {
+                    for func in &mut functions {
+                        func.file = Some(file.clone());
+                    }
+                    files.insert(rel, FileComplexity::from_functions(functions));
+                }
+                Err(e) => skipped.push((file.clone(), format!("{:#}", e))),
+            }
+        }
+
+        Ok(ProjectComplexity::from_files(files, skipped))
+    }
+
+    pub fn analyze_file_summary(content: &str) -> Result<FileComplexity> {
+        Self::analyze_file(content).map(FileComplexity::from_functions)
+    }
+
+    pub fn analyze_file_with(
+        content: &str,
+        options: &AnalyzeOptions,
+    ) -> Result<Vec<FunctionComplexity>> {
+        let syntax = syn::parse_file(content).context("Failed to parse Rust file")?;
+
+        let mut analyzer = FunctionVisitor::new(content, options);
+        analyzer.visit_file(&syntax);
+
+        Ok(analyzer.functions)
+    }
+
+    pub fn analyze_snippet(code: &str) -> Result<Vec<FunctionComplexity>> {
+        Self::analyze_snippet_with(code, &AnalyzeOptions::default())
+    }
+
+    /// Analyze a fragment rather than a whole file: tried as a file, then as a
+    /// single item, then as bare statements wrapped in a synthetic
+    /// `fn __snippet()`. Line numbers stay relative to `code`.
+    pub fn analyze_snippet_with(
+        code: &str,
+        options: &AnalyzeOptions,
+    ) -> Result<Vec<FunctionComplexity>> {
+        if let Ok(functions) = Self::analyze_file_with(code, options) {
+            return Ok(functions);
+        }
+        if let Ok(item) = syn::parse_str::<Item>(code) {
+            let mut analyzer = FunctionVisitor::new(code, options);
+            analyzer.visit_item(&item);
+            return Ok(analyzer.functions);
+        }
+
+        // The body starts on the signature's line so the lines don't shift
+        let wrapped = format!("fn {}() {{{}\n}}", SNIPPET_FN_NAME, code);
+        Self::analyze_file_with(&wrapped, options)
+            .context("Failed to parse snippet as a file, an item or statements")
+    }
+
+    pub fn analyze_file_lenient(content: &str) -> LenientAnalysis {
+        Self::analyze_file_lenient_with(content, &AnalyzeOptions::default())
+    }
+
+    /// Like [`Self::analyze_file_with`], but when the file does not parse as a
+    /// whole, each top-level item is parsed on its own so one syntax error
+    /// (common while editing) only loses the item it is in.
+    pub fn analyze_file_lenient_with(content: &str, options: &AnalyzeOptions) -> LenientAnalysis {
+        if let Ok(functions) = Self::analyze_file_with(content, options) {
+            return LenientAnalysis {
+                functions,
+                unparsed: Vec::new(),
+            };
+        }
+
+        let mut result = LenientAnalysis::default();
+        for range in split_top_level_items(content) {
+            // Pad with the newlines preceding the item so spans keep their
+            // original line numbers
+            let newlines = content[..range.start].matches('\n').count();
+            let chunk = format!("{}{}", "\n".repeat(newlines), &content[range.clone()]);
+            match Self::analyze_file_with(&chunk, options) {
+                Ok(functions) => result.functions.extend(functions),
+                Err(_) => {
+                    let text = &content[range.clone()];
+                    let leading = &text[..text.len() - text.trim_start().len()];
+                    let start_line = newlines + leading.matches('\n').count() + 1;
+                    let end_line = newlines + text.trim_end().matches('\n').count() + 1;
+                    result.unparsed.push((start_line, end_line));
+                }
+            }
+        }
+        result
+    }
+
+    pub fn analyze_impls(content: &str) -> Result<Vec<ImplComplexity>> {
+        let options = AnalyzeOptions::default();
+        let functions = Self::analyze_file_with(content, &options)?;
+        Ok(ImplComplexity::group(&functions, &options.thresholds))
+    }
+
+    pub fn analyze_types(content: &str) -> Result<Vec<TypeComplexity>> {
+        Self::analyze_types_with(content, &AnalyzeOptions::default())
+    }
+
+    pub fn analyze_types_with(
+        content: &str,
+        options: &AnalyzeOptions,
+    ) -> Result<Vec<TypeComplexity>> {
+        let syntax = syn::parse_file(content).context("Failed to parse Rust file")?;
+
+        let mut visitor = TypeVisitor {
+            options,
+            types: Vec::new(),
+        };
+        visitor.visit_file(&syntax);
+
+        Ok(visitor.types)
+    }
+
+    pub fn analyze_function(func: &ItemFn) -> FunctionComplexity {
+        Self::analyze_function_with(func, &AnalyzeOptions::default())
+    }
+
+    pub fn analyze_function_with(func: &ItemFn, options: &AnalyzeOptions) -> FunctionComplexity {
+        Self::analyze_function_in(func, options, None)
+    }
+
+    /// `func` as a method of `self_type` when given, a free function otherwise
+    fn analyze_function_in(
+        func: &ItemFn,
+        options: &AnalyzeOptions,
+        self_type: Option<&str>,
+    ) -> FunctionComplexity {
+        let mut visitor = ComplexityVisitor {
+            fn_name: func.sig.ident.to_string(),
+            self_type: self_type.map(str::to_string),
+            ..Default::default()
+        };
+        // The signature is handled here once; visiting only the body keeps
+        // `visit_item_fn` reserved for functions nested inside it
+        visitor.details.generic_parameters = func.sig.generics.params.len();
+        visitor.details.bound_count = bound_count(&func.sig.generics);
+        visitor.visit_block(&func.block);
+        if matches!(
+            func.block.stmts.last(),
+            Some(Stmt::Expr(Expr::Return(_), _))
+        ) {
+            // A trailing `return` is the normal exit, not an early one
+            visitor.details.early_returns = visitor.details.early_returns.saturating_sub(1);
+        }
+
+        let cyclomatic = visitor.calculate_cyclomatic_complexity(options);
+        let cognitive = visitor.calculate_cognitive_complexity();
+        let legacy_cognitive = visitor.calculate_legacy_cognitive_complexity();
+        let (start_line, end_line, line_count) = Self::line_span(func);
+        let body = func.block.to_token_stream();
+        let mut details = visitor.details;
+        details.signature_complexity = signature_complexity(&func.sig);
+        details.attributes = func
+            .attrs
+            .iter()
+            .filter(|a| !a.path().is_ident("doc"))
+            .map(attribute_label)
+            .collect();
+
+        FunctionComplexity {
+            name: func.sig.ident.to_string(),
+            cyclomatic_complexity: cyclomatic,
+            cognitive_complexity: cognitive,
+            legacy_cognitive,
+            line_count,
+            logical_lines: visitor.statements,
+            start_line,
+            end_line,
+            file: None,
+            parameter_count: func.sig.inputs.len(),
+            is_async: func.sig.asyncness.is_some(),
+            is_const: func.sig.constness.is_some(),
+            is_unsafe: func.sig.unsafety.is_some(),
+            visibility: visibility_label(&func.vis),
+            return_complexity: ComplexityRating::from_score_with(cyclomatic, &options.thresholds),
+            details,
+            halstead: HalsteadMetrics::from_tokens(body.clone()),
+            body_hash: format!("{:x}", Sha256::digest(body.to_string().as_bytes())),
+            has_doc_comment: func.attrs.iter().any(|a| a.path().is_ident("doc")),
+            suppressed: func.attrs.iter().any(is_suppression_attr),
+            impl_parent: None,
+            comment_density: 0.0,
+        }
+    }
+
+    /// Returns (start_line, end_line, line_count)
+    fn line_span(func: &ItemFn) -> (usize, usize, usize) {
+        // Span lines cover the signature through the closing brace (doc comments excluded)
+        let start = func.sig.span().start().line;
+        let end = func.block.span().end().line;
+        if start > 0 && end >= start {
+            return (start, end, end - start + 1);
+        }
+        // Fallback when span locations are unavailable (e.g. synthesized tokens)
+        let lines = func.to_token_stream().to_string().lines().count().max(1);
+        (0, 0, lines)
+    }
+}
+
+struct FunctionVisitor<'a> {
+    options: &'a AnalyzeOptions,
+    // Raw source lines, used for metrics the syntax tree cannot see (comments)
+    lines: Vec<&'a str>,
+    functions: Vec<FunctionComplexity>,
+    // Impl block being visited, if any
+    current_impl: Option<ImplParent>,
+    // Inline `mod` blocks enclosing the current item, outermost first
+    modules: Vec<String>,
+}
+
+impl<'a> FunctionVisitor<'a> {
+    fn new(content: &'a str, options: &'a AnalyzeOptions) -> Self {
+        Self {
+            options,
+            lines: content.lines().collect(),
+            functions: Vec::new(),
+            current_impl: None,
+            modules: Vec::new(),
+        }
+    }
+
+    fn skips(&self, attrs: &[Attribute]) -> bool {
+        skips_test_code(self.options, attrs)
+    }
+
+    fn push(&mut self, mut complexity: FunctionComplexity) {
+        if complexity.start_line > 0 && complexity.end_line <= self.lines.len() {
+            let body = &self.lines[complexity.start_line - 1..complexity.end_line];
+            complexity.comment_density = comment_density(body);
+            complexity.suppressed |= has_suppression_comment(&self.lines, complexity.start_line);
+        }
+        self.functions.push(complexity);
+    }
+
+    /// `name` prefixed with the enclosing module path, e.g. `handlers::handle`
+    fn qualify(&self, name: &str) -> String {
+        self.modules
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(name))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    fn impl_parent(&self, item: &ItemImpl) -> ImplParent {
+        ImplParent {
+            type_name: self.qualify(&type_display_name(&item.self_ty)),
+            trait_name: item
+                .trait_
+                .as_ref()
+                .and_then(|(_, path, _)| path.segments.last())
+                .map(|s| s.ident.to_string()),
+        }
+    }
+}
+
+fn visibility_label(vis: &Visibility) -> String {
+    match vis {
+        Visibility::Public(_) => "pub".to_string(),
+        Visibility::Restricted(restricted) => {
+            let path = path_to_string(&restricted.path);
+            if restricted.in_token.is_some() {
+                format!("pub(in {})", path)
+            } else {
+                format!("pub({})", path)
+            }
+        }
+        Visibility::Inherited => "private".to_string(),
+    }
+}
+
+/// Generic nesting depth of the return type (`Result<Vec<T>, E>` is 2) plus the
+/// number of trait bounds in the where clause
+fn signature_complexity(sig: &Signature) -> usize {
+    let return_depth = match &sig.output {
+        ReturnType::Default => 0,
+        ReturnType::Type(_, ty) => type_nesting_depth(ty),
+    };
+    let where_bounds = sig.generics.where_clause.as_ref().map_or(0, |wc| {
+        wc.predicates
+            .iter()
+            .map(|pred| match pred {
+                WherePredicate::Type(pt) => trait_bound_count(&pt.bounds),
+                _ => 0,
+            })
+            .sum()
+    });
+    return_depth + where_bounds
+}
+
+/// Trait bounds declared inline on type parameters and in the where clause
+fn bound_count(generics: &Generics) -> usize {
+    let inline: usize = generics
+        .type_params()
+        .map(|param| trait_bound_count(&param.bounds))
+        .sum();
+    let where_clause: usize = generics.where_clause.as_ref().map_or(0, |wc| {
+        wc.predicates
+            .iter()
+            .map(|pred| match pred {
+                WherePredicate::Type(pt) => trait_bound_count(&pt.bounds),
+                _ => 0,
+            })
+            .sum()
+    });
+    inline + where_clause
+}
+
+/// Lifetime bounds such as `T: 'a` are not counted
+fn trait_bound_count<'a>(bounds: impl IntoIterator<Item = &'a TypeParamBound>) -> usize {
+    bounds
+        .into_iter()
+        .filter(|b| matches!(b, TypeParamBound::Trait(_)))
+        .count()
+}
+
+fn type_nesting_depth(ty: &Type) -> usize {
+    match ty {
+        Type::Path(tp) => path_nesting_depth(&tp.path),
+        Type::Tuple(t) => t
+            .elems
+            .iter()
+            .map(type_nesting_depth)
+            .max()
+            .map_or(0, |d| d + 1),
+        Type::Array(a) => 1 + type_nesting_depth(&a.elem),
+        Type::Slice(s) => 1 + type_nesting_depth(&s.elem),
+        Type::Reference(r) => type_nesting_depth(&r.elem),
+        Type::Ptr(p) => type_nesting_depth(&p.elem),
+        Type::Paren(p) => type_nesting_depth(&p.elem),
+        Type::Group(g) => type_nesting_depth(&g.elem),
+        Type::TraitObject(t) => bounds_nesting_depth(t.bounds.iter()),
+        Type::ImplTrait(t) => bounds_nesting_depth(t.bounds.iter()),
+        _ => 0,
+    }
+}
+
+fn path_nesting_depth(path: &syn::Path) -> usize {
+    path.segments
+        .iter()
+        .map(|seg| match &seg.arguments {
+            PathArguments::AngleBracketed(args) => {
+                let inner = args
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        GenericArgument::Type(t) => type_nesting_depth(t),
+                        GenericArgument::AssocType(a) => type_nesting_depth(&a.ty),
+                        _ => 0,
+                    })
+                    .max()
+                    .unwrap_or(0);
+                1 + inner
+            }
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+fn bounds_nesting_depth<'a>(bounds: impl Iterator<Item = &'a TypeParamBound>) -> usize {
+    bounds
+        .map(|b| match b {
+            TypeParamBound::Trait(t) => path_nesting_depth(&t.path),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Byte ranges of top-level items, found by bracket matching outside of
+/// comments and string or char literals. An item ends at a `;` or a closing
+/// `}` at depth zero, unless a `;` or `.` follows the brace (grouped `use`,
+/// struct literal consts), so leading attributes and doc comments stay
+/// attached. A line starting an item at column zero while brackets are still
+/// open closes the item before it, so one unbalanced item doesn't swallow the
+/// rest of the file.
+fn split_top_level_items(content: &str) -> Vec<std::ops::Range<usize>> {
+    let bytes = content.as_bytes();
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if !content.is_char_boundary(i) {
+            i += 1;
+            continue;
+        }
+        let rest = &content[i..];
+        if depth > 0 && (i == 0 || bytes[i - 1] == b'\n') && starts_item(rest) {
+            items.push(start..i);
+            start = i;
+            depth = 0;
+        }
+        if rest.starts_with("//") {
+            i += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+        if rest.starts_with("/*") {
+            i += block_comment_len(rest);
+            continue;
+        }
+        if let Some(len) = raw_string_len(content, i) {
+            i += len;
+            continue;
+        }
+
+        match bytes[i] {
+            b'"' => {
+                let mut j = i + 1;
+                while j < bytes.len() && bytes[j] != b'"' {
+                    j += if bytes[j] == b'\\' { 2 } else { 1 };
+                }
+                i = j + 1;
+                continue;
+            }
+            b'\'' => {
+                // Char literal, or a lifetime which needs no skipping
+                let mut chars = rest[1..].chars();
+                match chars.next() {
+                    Some('\\') => {
+                        // Skip the escaped char itself so `'\''` is handled
+                        let closing = rest.get(3..).and_then(|after| after.find('\''));
+                        i += closing.map_or(rest.len(), |end| end + 4);
+                        continue;
+                    }
+                    Some(c) if chars.next() == Some('\'') => {
+                        i += 2 + c.len_utf8();
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+            b'{' | b'(' | b'[' => depth += 1,
+            b'}' | b')' | b']' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0
+                    && bytes[i] == b'}'
+                    && !matches!(next_significant(content, i + 1), Some(b';' | b'.'))
+                {
+                    items.push(start..i + 1);
+                    start = i + 1;
+                }
+            }
+            b';' if depth == 0 => {
+                items.push(start..i + 1);
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    if !content[start..].trim().is_empty() {
+        items.push(start..content.len());
+    }
+    // Unterminated literals or comments can overshoot the end
+    for item in &mut items {
+        item.end = item.end.min(content.len());
+    }
+    items
+}
+
+/// Keywords and attributes that open an item at column zero
+const ITEM_STARTS: &[&str] = &[
+    "#[",
+    "///",
+    "pub ",
+    "pub(",
+    "fn ",
+    "async ",
+    "unsafe ",
+    "extern ",
+    "const ",
+    "static ",
+    "struct ",
+    "enum ",
+    "union ",
+    "trait ",
+    "impl ",
+    "impl<",
+    "type ",
+    "mod ",
+    "use ",
+    "macro_rules!",
+];
+
+fn starts_item(line: &str) -> bool {
+    ITEM_STARTS.iter().any(|start| line.starts_with(start))
+}
+
+/// Length of the block comment at the start of `rest`, nested ones included
+fn block_comment_len(rest: &str) -> usize {
+    let bytes = rest.as_bytes();
+    let (mut depth, mut i) = (0usize, 0);
+    while i + 1 < bytes.len() {
+        match &bytes[i..i + 2] {
+            b"/*" => depth += 1,
+            b"*/" => depth -= 1,
+            _ => {
+                i += 1;
+                continue;
+            }
+        }
+        i += 2;
+        if depth == 0 {
+            return i;
+        }
+    }
+    rest.len()
+}
+
+/// First byte from `i` on that is neither whitespace nor in a comment
+fn next_significant(content: &str, mut i: usize) -> Option<u8> {
+    loop {
+        let rest = content.get(i..)?.trim_start();
+        i = content.len() - rest.len();
+        if rest.starts_with("//") {
+            i += rest.find('\n')?;
+        } else if rest.starts_with("/*") {
+            i += block_comment_len(rest);
+        } else {
+            return rest.bytes().next();
+        }
+    }
+}
+
+/// Length of a raw string literal (`r"..."`, `br#"..."#`) starting at `i`
+fn raw_string_len(content: &str, i: usize) -> Option<usize> {
+    let bytes = content.as_bytes();
+    if i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'_') {
+        return None;
+    }
+    let rest = &content[i..];
+    let after_prefix = rest.strip_prefix("br").or_else(|| rest.strip_prefix('r'))?;
+    let hashes = after_prefix.len() - after_prefix.trim_start_matches('#').len();
+    let body = after_prefix[hashes..].strip_prefix('"')?;
+    let terminator = format!("\"{}", "#".repeat(hashes));
+    let end = body
+        .find(&terminator)
+        .map_or(body.len(), |end| end + terminator.len());
+    Some(rest.len() - body.len() + end)
+}
+
+/// Share of lines that are or contain a comment. Line-based, so `//` inside a
+/// string literal is counted too.
+fn comment_density(lines: &[&str]) -> f32 {
+    if lines.is_empty() {
+        return 0.0;
+    }
+    let mut in_block = false;
+    let mut comment_lines = 0;
+    for line in lines {
+        let trimmed = line.trim_start();
+        let is_comment = if in_block {
+            in_block = !line.contains("*/");
+            true
+        } else if let Some(start) = line.find("/*") {
+            in_block = !line[start + 2..].contains("*/");
+            true
+        } else {
+            trimmed.starts_with("//") || line.contains(" //")
+        };
+        comment_lines += usize::from(is_comment);
+    }
+    comment_lines as f32 / lines.len() as f32
+}
+
+const SUPPRESSION_COMMENT: &str = "// copartner:ignore-complexity";
+
+/// `#[allow(copartner::complexity)]`; not a real lint, only read by the analyzer
+fn is_suppression_attr(attr: &Attribute) -> bool {
+    if !attr.path().is_ident("allow") {
+        return false;
+    }
+    attr.parse_args_with(punctuated::Punctuated::<syn::Path, Token![,]>::parse_terminated)
+        .map(|paths| {
+            paths
+                .iter()
+                .any(|path| path_to_string(path) == "copartner::complexity")
+        })
+        .unwrap_or(false)
+}
+
+/// Looks for the suppression comment in the attribute, doc and comment lines
+/// directly above the signature at `sig_line`; a blank line ends the search.
+fn has_suppression_comment(lines: &[&str], sig_line: usize) -> bool {
+    lines[..sig_line - 1]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with("//") || line.starts_with("#["))
+        .any(|line| line.starts_with(SUPPRESSION_COMMENT))
+}
+
+fn skips_test_code(options: &AnalyzeOptions, attrs: &[Attribute]) -> bool {
+    !options.include_tests && is_test_code(attrs)
+}
+
+/// Marked `#[test]`-like (`#[tokio::test]`, ...) or `#[cfg(test)]`
+pub(crate) fn is_test_code(attrs: &[Attribute]) -> bool {
+    attrs.iter().any(|a| is_test_attr(a) || is_cfg_test(a))
+}
+
+/// `#[test]`, `#[tokio::test]` and similar test harness attributes
+fn is_test_attr(attr: &Attribute) -> bool {
+    attr.path()
+        .segments
+        .last()
+        .is_some_and(|s| s.ident == "test")
+}
+
+/// `#[cfg(..)]` whose predicate cannot hold outside `cfg(test)`, like `test`
+/// or `all(test, feature = "slow")`; `not(test)` and `any(test, unix)` can
+fn is_cfg_test(attr: &Attribute) -> bool {
+    match &attr.meta {
+        Meta::List(list) if list.path.is_ident("cfg") => list
+            .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+            .is_ok_and(|preds| preds.len() == 1 && eval_without_test(&preds[0]) == Some(false)),
+        _ => false,
+    }
+}
+
+/// Value of a cfg predicate when `test` is off: `None` when it depends on
+/// other options
+fn eval_without_test(pred: &Meta) -> Option<bool> {
+    match pred {
+        Meta::Path(path) if path.is_ident("test") => Some(false),
+        Meta::List(list) => {
+            let preds = list
+                .parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)
+                .ok()?;
+            let values: Vec<Option<bool>> = preds.iter().map(eval_without_test).collect();
+            if list.path.is_ident("not") && values.len() == 1 {
+                values[0].map(|v| !v)
+            } else if list.path.is_ident("all") {
+                if values.contains(&Some(false)) {
+                    Some(false)
+                } else {
+                    values.iter().all(|v| *v == Some(true)).then_some(true)
+                }
+            } else if list.path.is_ident("any") {
+                if values.contains(&Some(true)) {
+                    Some(true)
+                } else {
+                    values.iter().all(|v| *v == Some(false)).then_some(false)
+                }
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Short display name for a type: the last path segment without generics
+/// (`DataProcessor<T>` -> `DataProcessor`), or the token text for other types.
+fn type_display_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(tp) if tp.qself.is_none() => tp
+            .path
+            .segments
+            .last()
+            .map(|s| s.ident.to_string())
+            .unwrap_or_default(),
+        _ => ty.to_token_stream().to_string(),
+    }
+}
+
+impl<'ast> Visit<'ast> for FunctionVisitor<'_> {
+    fn visit_item_fn(&mut self, func: &'ast ItemFn) {
+        if self.skips(&func.attrs) {
+            return;
+        }
+        let mut complexity = ComplexityAnalyzer::analyze_function_with(func, self.options);
+        complexity.name = self.qualify(&complexity.name);
+        self.push(complexity);
+
+        // Continue visiting nested functions; they are not qualified by an outer impl
+        let current_impl = self.current_impl.take();
+        syn::visit::visit_item_fn(self, func);
+        self.current_impl = current_impl;
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if self.skips(&item.attrs) {
+            return;
+        }
+        self.modules.push(item.ident.to_string());
+        syn::visit::visit_item_mod(self, item);
+        self.modules.pop();
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast ItemImpl) {
+        if self.skips(&item.attrs) {
+            return;
+        }
+        let outer = self.current_impl.replace(self.impl_parent(item));
+        syn::visit::visit_item_impl(self, item);
+        self.current_impl = outer;
+    }
+
+    fn visit_impl_item_fn(&mut self, func: &'ast ImplItemFn) {
+        if self.skips(&func.attrs) {
+            return;
+        }
+        // Handle methods in impl blocks
+        let item_fn = ItemFn {
+            attrs: func.attrs.clone(),
+            vis: func.vis.clone(),
+            sig: func.sig.clone(),
+            block: Box::new(func.block.clone()),
+        };
+        let self_type = self.current_impl.as_ref().map_or("Self", |p| &p.type_name);
+        let mut complexity =
+            ComplexityAnalyzer::analyze_function_in(&item_fn, self.options, Some(self_type));
+        if let Some(parent) = &self.current_impl {
+            complexity.name = format!("{}::{}", parent.prefix(), complexity.name);
+            complexity.impl_parent = Some(parent.clone());
+        }
+        self.push(complexity);
+
+        let current_impl = self.current_impl.take();
+        syn::visit::visit_impl_item_fn(self, func);
+        self.current_impl = current_impl;
+    }
+}
+
+struct TypeVisitor<'a> {
+    options: &'a AnalyzeOptions,
+    types: Vec<TypeComplexity>,
+}
+
+impl TypeVisitor<'_> {
+    fn push(
+        &mut self,
+        ident: &Ident,
+        kind: TypeKind,
+        field_count: usize,
+        variant_count: usize,
+        generics: &Generics,
+        attrs: &[Attribute],
+    ) {
+        let mut ty = TypeComplexity {
+            name: ident.to_string(),
+            kind,
+            field_count,
+            variant_count,
+            generic_parameters: generics.params.len(),
+            derive_count: attrs.iter().map(derive_count).sum(),
+            start_line: ident.span().start().line,
+            rating: ComplexityRating::Low,
+        };
+        ty.rating = ComplexityRating::from_score_with(
+            ty.score() / TypeComplexity::SCORE_SCALE,
+            &self.options.thresholds,
+        );
+        self.types.push(ty);
+    }
+}
+
+/// `path`, `path(args)` or `path = value`, without the `#[...]`
+fn attribute_label(attr: &Attribute) -> String {
+    let path = path_to_string(attr.path());
+    match &attr.meta {
+        Meta::Path(_) => path,
+        Meta::List(list) => format!("{}({})", path, list.tokens),
+        Meta::NameValue(nv) => format!("{} = {}", path, nv.value.to_token_stream()),
+    }
+}
+
+fn derive_count(attr: &Attribute) -> usize {
+    if !attr.path().is_ident("derive") {
+        return 0;
+    }
+    attr.parse_args_with(punctuated::Punctuated::<syn::Path, Token![,]>::parse_terminated)
+        .map(|paths| paths.len())
+        .unwrap_or(0)
+}
+
+impl<'ast> Visit<'ast> for TypeVisitor<'_> {
+    fn visit_item_struct(&mut self, item: &'ast ItemStruct) {
+        if skips_test_code(self.options, &item.attrs) {
+            return;
+        }
+        self.push(
+            &item.ident,
+            TypeKind::Struct,
+            item.fields.len(),
+            0,
+            &item.generics,
+            &item.attrs,
+        );
+        syn::visit::visit_item_struct(self, item);
+    }
+
+    fn visit_item_enum(&mut self, item: &'ast ItemEnum) {
+        if skips_test_code(self.options, &item.attrs) {
+            return;
+        }
+        let field_count = item.variants.iter().map(|v| v.fields.len()).sum();
+        self.push(
+            &item.ident,
+            TypeKind::Enum,
+            field_count,
+            item.variants.len(),
+            &item.generics,
+            &item.attrs,
+        );
+        syn::visit::visit_item_enum(self, item);
+    }
+
+    fn visit_item_mod(&mut self, item: &'ast ItemMod) {
+        if skips_test_code(self.options, &item.attrs) {
+            return;
+        }
+        syn::visit::visit_item_mod(self, item);
+    }
+
+    fn visit_item_fn(&mut self, func: &'ast ItemFn) {
+        if skips_test_code(self.options, &func.attrs) {
+            return;
+        }
+        syn::visit::visit_item_fn(self, func);
+    }
+}
+
+// Expression depth that doesn't add to cognitive complexity
+const EXPRESSION_DEPTH_ALLOWANCE: usize = 5;
+
+#[derive(Default)]
+struct ComplexityVisitor {
+    details: ComplexityDetails,
+    nesting_depth: usize,
+    // Runs of identical short-circuit operators (`a && b && c` is one run)
+    boolean_sequences: usize,
+    // Sonar-style cognitive score, accumulated as structures are entered
+    cognitive: usize,
+    // Set while descending into the `else if` branch of an if expression
+    in_else_if: bool,
+    // `return` inside a closure exits the closure, not the analyzed function
+    closure_depth: usize,
+    // Name of the analyzed function, used to spot direct recursion
+    fn_name: String,
+    // Type a method is implemented on; `None` for free functions
+    self_type: Option<String>,
+    // Statements seen in the body, reported as logical lines
+    statements: usize,
+    // Depth of the expression being visited, reset for every block
+    expression_depth: usize,
+}
+
+impl ComplexityVisitor {
+    fn calculate_cyclomatic_complexity(&self, options: &AnalyzeOptions) -> usize {
+        // McCabe cyclomatic complexity = edges - nodes + 2
+        // Simplified calculation: 1 + number of decision points
+        let try_points = if options.count_try {
+            self.details.try_operators
+        } else {
+            0
+        };
+        1 + self.details.if_statements
+            + self.details.let_else
+            + self.details.match_arms
+            + self.details.match_guards
+            + self.details.loops
+            + self.details.boolean_operators
+            + try_points
+    }
+
+    fn calculate_cognitive_complexity(&self) -> usize {
+        // Increments are accumulated while visiting (see `structural_increment`),
+        // plus one per run of identical boolean operators and half a point per
+        // early return since scattered exits make control flow harder to follow
+        // Sonar also adds one for each method in a recursion cycle
+        let recursion = usize::from(self.details.is_recursive);
+        // Half a point per level of expression nesting beyond what a short
+        // method chain like `a.b()?.c()` already reaches
+        let deep_expressions = self
+            .details
+            .max_expression_depth
+            .saturating_sub(EXPRESSION_DEPTH_ALLOWANCE)
+            / 2;
+        // Every three trait bounds add a point: heavily bounded generics are
+        // harder to call and to change
+        let bounds = self.details.bound_count / 3;
+        self.cognitive
+            + self.boolean_sequences
+            + self.details.early_returns / 2
+            + recursion
+            + deep_expressions
+            + bounds
+    }
+
+    fn record_macro(&mut self, mac: &Macro) {
+        // Record macro invocations
+        self.details
+            .macro_invocations
+            .push(path_to_string(&mac.path));
+
+        if let Some(last) = mac.path.segments.last() {
+            if matches!(
+                last.ident.to_string().as_str(),
+                "panic" | "unreachable" | "todo" | "unimplemented"
+            ) {
+                self.details.panic_macros += 1;
+            }
+        }
+    }
+
+    fn is_self_call(&self, path: &syn::Path) -> bool {
+        let segments: Vec<_> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        // A bare `name(..)` in a method calls a free function, not the method
+        match (segments.as_slice(), &self.self_type) {
+            ([name], None) => *name == self.fn_name,
+            ([ty, name], Some(self_type)) => {
+                (ty == "Self" || ty == self_type) && *name == self.fn_name
+            }
+            _ => false,
+        }
+    }
+
+    fn calculate_legacy_cognitive_complexity(&self) -> usize {
+        let base = self.details.if_statements + self.details.loops + self.details.match_arms;
+        let nesting_penalty = self.details.max_nesting_depth * 2;
+        let unsafe_penalty = self.details.unsafe_blocks * 3; // unsafe blocks increase cognitive burden
+        base + nesting_penalty + unsafe_penalty
+    }
+
+    /// +1 for the structure itself, plus the nesting level it appears at
+    fn structural_increment(&mut self) {
+        self.cognitive += 1 + self.nesting_depth;
+    }
+
+    fn enter_nesting(&mut self) {
+        self.nesting_depth += 1;
+        if self.nesting_depth > self.details.max_nesting_depth {
+            self.details.max_nesting_depth = self.nesting_depth;
+        }
+    }
+
+    fn exit_nesting(&mut self) {
+        self.nesting_depth = self.nesting_depth.saturating_sub(1);
+    }
+
+    fn collect_use_path(&mut self, tree: &UseTree, path_parts: &mut Vec<String>) {
+        match tree {
+            UseTree::Path(use_path) => {
+                path_parts.push(use_path.ident.to_string());
+                self.collect_use_path(&use_path.tree, path_parts);
+            }
+            UseTree::Name(use_name) => {
+                path_parts.push(use_name.ident.to_string());
+                let full_path = path_parts.join("::");
+                self.details.module_dependencies.push(full_path);
+            }
+            UseTree::Group(use_group) => {
+                for item in &use_group.items {
+                    let mut new_path = path_parts.clone();
+                    self.collect_use_path(item, &mut new_path);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for ComplexityVisitor {
+    fn visit_expr_if(&mut self, expr: &'ast ExprIf) {
+        self.details.if_statements += 1;
+        // `else if` is a hybrid increment: no nesting penalty, and its branches
+        // sit at the same depth as the `if` they continue
+        let else_if = std::mem::take(&mut self.in_else_if);
+        if else_if {
+            self.cognitive += 1;
+        } else {
+            self.structural_increment();
+            self.enter_nesting();
+        }
+
+        for attr in &expr.attrs {
+            self.visit_attribute(attr);
+        }
+        self.visit_expr(&expr.cond);
+        self.visit_block(&expr.then_branch);
+        if let Some((_, else_branch)) = &expr.else_branch {
+            match &**else_branch {
+                Expr::If(_) => self.in_else_if = true,
+                _ => self.cognitive += 1,
+            }
+            self.visit_expr(else_branch);
+        }
+        if !else_if {
+            self.exit_nesting();
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        self.expression_depth += 1;
+        self.details.max_expression_depth =
+            self.details.max_expression_depth.max(self.expression_depth);
+        syn::visit::visit_expr(self, expr);
+        self.expression_depth -= 1;
+    }
+
+    fn visit_block(&mut self, block: &'ast Block) {
+        // Statements inside a block start a fresh expression tree, so control
+        // flow nesting is left to `max_nesting_depth`
+        let depth = std::mem::take(&mut self.expression_depth);
+        syn::visit::visit_block(self, block);
+        self.expression_depth = depth;
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        self.statements += 1;
+        syn::visit::visit_stmt(self, stmt);
+    }
+
+    fn visit_local(&mut self, local: &'ast Local) {
+        // `let PAT = EXPR else { ... };` branches into the diverging block
+        let Some((_, diverge)) = local.init.as_ref().and_then(|init| init.diverge.as_ref()) else {
+            syn::visit::visit_local(self, local);
+            return;
+        };
+
+        self.details.let_else += 1;
+        self.structural_increment();
+        for attr in &local.attrs {
+            self.visit_attribute(attr);
+        }
+        self.visit_pat(&local.pat);
+        if let Some(init) = &local.init {
+            self.visit_expr(&init.expr);
+        }
+        self.enter_nesting();
+        self.visit_expr(diverge);
+        self.exit_nesting();
+    }
+
+    fn visit_expr_match(&mut self, expr: &'ast ExprMatch) {
+        // Each match expression counts as a decision point, each arm adds complexity
+        self.details.match_arms += expr.arms.len();
+        // An `if` guard is one more branch within its arm
+        self.details.match_guards += expr.arms.iter().filter(|arm| arm.guard.is_some()).count();
+        self.structural_increment();
+        self.enter_nesting();
+        syn::visit::visit_expr_match(self, expr);
+        self.exit_nesting();
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast ExprWhile) {
+        self.details.loops += 1;
+        self.structural_increment();
+        self.enter_nesting();
+        syn::visit::visit_expr_while(self, expr);
+        self.exit_nesting();
+    }
+
+    fn visit_expr_for_loop(&mut self, expr: &'ast ExprForLoop) {
+        self.details.loops += 1;
+        self.structural_increment();
+        self.enter_nesting();
+        syn::visit::visit_expr_for_loop(self, expr);
+        self.exit_nesting();
+    }
+
+    fn visit_expr_loop(&mut self, expr: &'ast ExprLoop) {
+        self.details.loops += 1;
+        self.structural_increment();
+        self.enter_nesting();
+        syn::visit::visit_expr_loop(self, expr);
+        self.exit_nesting();
+    }
+
+    fn visit_expr_break(&mut self, expr: &'ast ExprBreak) {
+        // Jumps to a label break the linear flow of reading
+        if expr.label.is_some() {
+            self.cognitive += 1;
+        }
+        syn::visit::visit_expr_break(self, expr);
+    }
+
+    fn visit_expr_continue(&mut self, expr: &'ast ExprContinue) {
+        if expr.label.is_some() {
+            self.cognitive += 1;
+        }
+        syn::visit::visit_expr_continue(self, expr);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast ExprBinary) {
+        if matches!(expr.op, BinOp::And(_) | BinOp::Or(_)) {
+            self.details.boolean_operators += 1;
+
+            // Binary chains are left-associative, so a run continues when the
+            // left operand uses the same operator
+            let continues_run = matches!(
+                &*expr.left,
+                Expr::Binary(left) if std::mem::discriminant(&left.op) == std::mem::discriminant(&expr.op)
+            );
+            if !continues_run {
+                self.boolean_sequences += 1;
+            }
+        }
+
+        syn::visit::visit_expr_binary(self, expr);
+    }
+
+    fn visit_expr_try(&mut self, expr: &'ast ExprTry) {
+        self.details.try_operators += 1;
+        syn::visit::visit_expr_try(self, expr);
+    }
+
+    fn visit_expr_return(&mut self, expr: &'ast ExprReturn) {
+        if self.closure_depth == 0 {
+            self.details.early_returns += 1;
+        }
+        syn::visit::visit_expr_return(self, expr);
+    }
+
+    fn visit_expr_closure(&mut self, expr: &'ast ExprClosure) {
+        self.closure_depth += 1;
+        syn::visit::visit_expr_closure(self, expr);
+        self.closure_depth -= 1;
+    }
+
+    fn visit_expr_await(&mut self, expr: &'ast ExprAwait) {
+        self.details.await_points += 1;
+        syn::visit::visit_expr_await(self, expr);
+    }
+
+    fn visit_expr_call(&mut self, expr: &'ast ExprCall) {
+        self.details.function_calls += 1;
+
+        // Extract function call names
+        if let Expr::Path(path_expr) = &*expr.func {
+            if self.is_self_call(&path_expr.path) {
+                self.details.is_recursive = true;
+            }
+            // Keep the full path so `std::mem::size_of` and a local `size_of` differ
+            self.details
+                .function_call_chain
+                .push(path_to_string(&path_expr.path));
+        }
+
+        syn::visit::visit_expr_call(self, expr);
+    }
+
+    fn visit_expr_method_call(&mut self, expr: &'ast ExprMethodCall) {
+        match (expr.method.to_string().as_str(), expr.args.len()) {
+            ("unwrap", 0) => self.details.unwrap_calls += 1,
+            ("expect", 1) => self.details.expect_calls += 1,
+            _ => {}
+        }
+
+        // `self.name(..)` inside method `name`
+        if let Expr::Path(receiver) = &*expr.receiver {
+            if receiver.path.is_ident("self") && expr.method == self.fn_name {
+                self.details.is_recursive = true;
+            }
+        }
+        syn::visit::visit_expr_method_call(self, expr);
+
+        // Recorded after the receiver so `a()?.b()` reads in call order
+        self.details.function_calls += 1;
+        self.details
+            .function_call_chain
+            .push(expr.method.to_string());
+    }
+
+    fn visit_expr_macro(&mut self, expr: &'ast ExprMacro) {
+        self.record_macro(&expr.mac);
+        syn::visit::visit_expr_macro(self, expr);
+    }
+
+    // `panic!(..);` in statement position is a `Stmt::Macro`, not an expression
+    fn visit_stmt_macro(&mut self, stmt: &'ast StmtMacro) {
+        self.record_macro(&stmt.mac);
+        syn::visit::visit_stmt_macro(self, stmt);
+    }
+
+    fn visit_expr_unsafe(&mut self, expr: &'ast ExprUnsafe) {
+        self.details.unsafe_blocks += 1;
+        self.enter_nesting();
+        syn::visit::visit_expr_unsafe(self, expr);
+        self.exit_nesting();
+    }
+
+    fn visit_use_tree(&mut self, use_tree: &'ast UseTree) {
+        // Collect module dependencies
+        match use_tree {
+            UseTree::Path(use_path) => {
+                let mut path_parts = vec![use_path.ident.to_string()];
+                self.collect_use_path(&use_path.tree, &mut path_parts);
+            }
+            UseTree::Name(use_name) => {
+                self.details
+                    .module_dependencies
+                    .push(use_name.ident.to_string());
+            }
+            UseTree::Group(use_group) => {
+                for item in &use_group.items {
+                    self.visit_use_tree(item);
+                }
+            }
+            _ => {}
+        }
+
+        syn::visit::visit_use_tree(self, use_tree);
+    }
+
+    fn visit_item_fn(&mut self, func: &'ast ItemFn) {
+        // Only reached for fns declared inside the analyzed body
+        self.details.nested_functions += 1;
+
+        // Analyze generic parameters
+        self.details.generic_parameters += func.sig.generics.params.len();
+
+        syn::visit::visit_item_fn(self, func);
+    }
+}
+
+impl std::fmt::Display for ComplexityRating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Low => write!(f, "Low"),
+            Self::Medium => write!(f, "Medium"),
+            Self::High => write!(f, "High"),
+            Self::VeryHigh => write!(f, "Very High"),
+        }
+    }
+}
+
+impl std::fmt::Display for TypeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Struct => write!(f, "struct"),
+            Self::Enum => write!(f, "enum"),
+        }
+    }
+}
+
+impl std::fmt::Display for FunctionComplexity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f,
+            "Function: {}\n  Cyclomatic Complexity: {}\n  Cognitive Complexity: {}\n  Lines: {} ({} logical)\n  Parameters: {}\n  Rating: {}",
+            self.name,
+            self.cyclomatic_complexity,
+            self.cognitive_complexity,
+            self.line_count,
+            self.logical_lines,
+            self.parameter_count,
+            self.return_complexity
+        )
+    }
+}
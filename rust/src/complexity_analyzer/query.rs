@@ -0,0 +1,73 @@
+use super::{ComplexityRating, FunctionComplexity};
+use regex::Regex;
+use std::borrow::Borrow;
+
+/// Per-function number that results can be sorted or filtered by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    Cyclomatic,
+    Cognitive,
+    Lines,
+    Parameters,
+}
+
+impl Metric {
+    pub fn value(self, func: &FunctionComplexity) -> usize {
+        match self {
+            Self::Cyclomatic => func.cyclomatic_complexity,
+            Self::Cognitive => func.cognitive_complexity,
+            Self::Lines => func.line_count,
+            Self::Parameters => func.parameter_count,
+        }
+    }
+}
+
+/// Sorting and filtering over analysis results, usable on owned results and
+/// on collections of references alike. Every method consumes and returns the
+/// collection so calls chain; sorting is stable and highest-first.
+pub trait ComplexityReportExt: Sized {
+    fn sort_by_metric(self, metric: Metric) -> Self;
+
+    /// Functions rated `min` or worse
+    fn filter_rating(self, min: ComplexityRating) -> Self;
+
+    /// Functions whose metric is at least `min`
+    fn filter_min(self, metric: Metric, min: usize) -> Self;
+
+    /// Functions whose qualified name matches `pattern`
+    fn filter_name(self, pattern: &Regex) -> Self;
+
+    fn top_by(self, metric: Metric, n: usize) -> Self;
+
+    fn top_by_cyclomatic(self, n: usize) -> Self {
+        self.top_by(Metric::Cyclomatic, n)
+    }
+}
+
+impl<T: Borrow<FunctionComplexity>> ComplexityReportExt for Vec<T> {
+    fn sort_by_metric(mut self, metric: Metric) -> Self {
+        self.sort_by_key(|f| std::cmp::Reverse(metric.value(f.borrow())));
+        self
+    }
+
+    fn filter_rating(mut self, min: ComplexityRating) -> Self {
+        self.retain(|f| f.borrow().return_complexity >= min);
+        self
+    }
+
+    fn filter_min(mut self, metric: Metric, min: usize) -> Self {
+        self.retain(|f| metric.value(f.borrow()) >= min);
+        self
+    }
+
+    fn filter_name(mut self, pattern: &Regex) -> Self {
+        self.retain(|f| pattern.is_match(&f.borrow().name));
+        self
+    }
+
+    fn top_by(self, metric: Metric, n: usize) -> Self {
+        let mut sorted = self.sort_by_metric(metric);
+        sorted.truncate(n);
+        sorted
+    }
+}
@@ -0,0 +1,167 @@
+use super::FunctionComplexity;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// Limits (exclusive) above which a heuristic fires in [`find_candidates`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeuristicConfig {
+    pub max_cyclomatic: usize,
+    pub max_lines: usize,
+    pub max_params: usize,
+    pub max_nesting: usize,
+    /// Distinct functions and methods called from the body
+    pub max_call_targets: usize,
+    pub max_loops: usize,
+}
+
+impl Default for HeuristicConfig {
+    fn default() -> Self {
+        Self {
+            max_cyclomatic: 10,
+            max_lines: 50,
+            max_params: 5,
+            max_nesting: 4,
+            max_call_targets: 10,
+            max_loops: 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Heuristic {
+    HighCyclomatic,
+    LongFunction,
+    ManyParameters,
+    DeepNesting,
+    ManyCallTargets,
+    ManyLoops,
+}
+
+/// Machine-readable refactoring action
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Suggestion {
+    SplitFunction,
+    IntroduceStruct,
+    ReduceNesting,
+    ExtractLoopBody,
+}
+
+/// A function that triggered at least one heuristic, with the actions that
+/// address them
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RefactoringCandidate {
+    pub function: String,
+    pub file: Option<PathBuf>,
+    pub line: usize,
+    pub heuristics: Vec<Heuristic>,
+    /// Deduplicated, in the order of the heuristics that asked for them
+    pub suggestions: Vec<Suggestion>,
+}
+
+impl Heuristic {
+    const ALL: [Heuristic; 6] = [
+        Self::HighCyclomatic,
+        Self::LongFunction,
+        Self::ManyParameters,
+        Self::DeepNesting,
+        Self::ManyCallTargets,
+        Self::ManyLoops,
+    ];
+
+    fn fires(self, func: &FunctionComplexity, config: &HeuristicConfig) -> bool {
+        match self {
+            Self::HighCyclomatic => func.cyclomatic_complexity > config.max_cyclomatic,
+            Self::LongFunction => func.line_count > config.max_lines,
+            Self::ManyParameters => func.parameter_count > config.max_params,
+            Self::DeepNesting => func.details.max_nesting_depth > config.max_nesting,
+            Self::ManyCallTargets => call_targets(func) > config.max_call_targets,
+            Self::ManyLoops => func.details.loops > config.max_loops,
+        }
+    }
+
+    pub fn suggestion(self) -> Suggestion {
+        match self {
+            Self::HighCyclomatic | Self::LongFunction | Self::ManyCallTargets => {
+                Suggestion::SplitFunction
+            }
+            Self::ManyParameters => Suggestion::IntroduceStruct,
+            Self::DeepNesting => Suggestion::ReduceNesting,
+            Self::ManyLoops => Suggestion::ExtractLoopBody,
+        }
+    }
+}
+
+fn call_targets(func: &FunctionComplexity) -> usize {
+    func.details
+        .function_call_chain
+        .iter()
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Collect candidates, skipping suppressed functions. The functions that
+/// trigger the most heuristics come first; ties keep the input order.
+pub fn find_candidates(
+    functions: &[FunctionComplexity],
+    config: &HeuristicConfig,
+) -> Vec<RefactoringCandidate> {
+    let mut candidates: Vec<RefactoringCandidate> = functions
+        .iter()
+        .filter(|f| !f.suppressed)
+        .filter_map(|func| {
+            let heuristics: Vec<Heuristic> = Heuristic::ALL
+                .into_iter()
+                .filter(|h| h.fires(func, config))
+                .collect();
+            if heuristics.is_empty() {
+                return None;
+            }
+            let mut suggestions = Vec::new();
+            for suggestion in heuristics.iter().map(|h| h.suggestion()) {
+                if !suggestions.contains(&suggestion) {
+                    suggestions.push(suggestion);
+                }
+            }
+            Some(RefactoringCandidate {
+                function: func.name.clone(),
+                file: func.file.clone(),
+                line: func.start_line,
+                heuristics,
+                suggestions,
+            })
+        })
+        .collect();
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.heuristics.len()));
+    candidates
+}
+
+impl std::fmt::Display for Heuristic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HighCyclomatic => write!(f, "high cyclomatic complexity"),
+            Self::LongFunction => write!(f, "long function"),
+            Self::ManyParameters => write!(f, "many parameters"),
+            Self::DeepNesting => write!(f, "deep nesting"),
+            Self::ManyCallTargets => write!(f, "many call targets"),
+            Self::ManyLoops => write!(f, "many loops"),
+        }
+    }
+}
+
+impl std::fmt::Display for Suggestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SplitFunction => write!(f, "Split into smaller, focused functions"),
+            Self::IntroduceStruct => {
+                write!(f, "Introduce a struct to group related parameters")
+            }
+            Self::ReduceNesting => {
+                write!(f, "Reduce nesting using early returns or helper functions")
+            }
+            Self::ExtractLoopBody => write!(f, "Extract loop bodies into helper functions"),
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use super::{ComplexityRating, FileComplexity, FunctionComplexity, ProjectComplexity};
+use std::fmt::Write;
+use std::path::Path;
+
+/// Rows in the worst-functions table when the caller has no preference
+pub const DEFAULT_WORST_N: usize = 20;
+
+const RATINGS: [ComplexityRating; 4] = [
+    ComplexityRating::Low,
+    ComplexityRating::Medium,
+    ComplexityRating::High,
+    ComplexityRating::VeryHigh,
+];
+
+/// Non-suppressed functions with the highest cyclomatic complexity, with
+/// their file relative to the analyzed root
+fn worst_functions(project: &ProjectComplexity, n: usize) -> Vec<(&Path, &FunctionComplexity)> {
+    let mut functions: Vec<_> = project
+        .files
+        .iter()
+        .flat_map(|(file, summary)| {
+            summary
+                .functions
+                .iter()
+                .filter(|f| !f.suppressed)
+                .map(move |f| (file.as_path(), f))
+        })
+        .collect();
+    functions.sort_by_key(|(_, f)| std::cmp::Reverse(f.cyclomatic_complexity));
+    functions.truncate(n);
+    functions
+}
+
+/// Files with at least one function, hottest (highest max cyclomatic) first
+fn file_rollups(project: &ProjectComplexity) -> Vec<(&Path, &FileComplexity)> {
+    let mut files: Vec<_> = project
+        .files
+        .iter()
+        .filter(|(_, summary)| !summary.functions.is_empty())
+        .map(|(file, summary)| (file.as_path(), summary))
+        .collect();
+    files.sort_by_key(|(_, summary)| std::cmp::Reverse(summary.max_cyclomatic));
+    files
+}
+
+fn summary_rows(project: &ProjectComplexity) -> Vec<(&'static str, String)> {
+    let summary = project.summary();
+    vec![
+        ("Files", project.files.len().to_string()),
+        ("Functions", summary.total_functions.to_string()),
+        ("Total lines", summary.total_lines.to_string()),
+        ("Max cyclomatic", summary.max_cyclomatic.to_string()),
+        (
+            "Average cyclomatic",
+            format!("{:.2}", summary.average_cyclomatic),
+        ),
+        (
+            "Average cognitive",
+            format!("{:.2}", summary.average_cognitive),
+        ),
+        (
+            "Average maintainability index",
+            format!("{:.2}", summary.average_maintainability_index),
+        ),
+        ("Doc coverage", format!("{:.1}%", summary.doc_coverage)),
+    ]
+}
+
+fn location(file: &Path, func: &FunctionComplexity) -> String {
+    format!("{}:{}", file.display(), func.start_line)
+}
+
+/// GitHub-flavored markdown report: summary, rating distribution, the `worst_n`
+/// most complex functions and per-file rollups
+pub fn render_markdown(project: &ProjectComplexity, worst_n: usize) -> String {
+    let mut md = String::new();
+    // Writing to a String cannot fail
+    let _ = write_markdown(&mut md, project, worst_n);
+    md
+}
+
+fn write_markdown(
+    md: &mut String,
+    project: &ProjectComplexity,
+    worst_n: usize,
+) -> std::fmt::Result {
+    writeln!(md, "# Complexity Report")?;
+
+    writeln!(md, "\n## Summary\n")?;
+    writeln!(md, "| Metric | Value |")?;
+    writeln!(md, "|---|---:|")?;
+    for (metric, value) in summary_rows(project) {
+        writeln!(md, "| {} | {} |", metric, value)?;
+    }
+
+    writeln!(md, "\n## Rating Distribution\n")?;
+    writeln!(md, "| Rating | Functions | Share |")?;
+    writeln!(md, "|---|---:|---:|")?;
+    for rating in &RATINGS {
+        writeln!(
+            md,
+            "| {} | {} | {:.1}% |",
+            rating,
+            count(project, rating),
+            project.distribution.percentage(rating)
+        )?;
+    }
+
+    writeln!(md, "\n## Worst Functions\n")?;
+    writeln!(
+        md,
+        "| Function | Location | Cyclomatic | Cognitive | Lines | Params | Rating |"
+    )?;
+    writeln!(md, "|---|---|---:|---:|---:|---:|---|")?;
+    for (file, func) in worst_functions(project, worst_n) {
+        writeln!(
+            md,
+            "| {} | {} | {} | {} | {} | {} | {} |",
+            markdown_code(&func.name),
+            markdown_code(&location(file, func)),
+            func.cyclomatic_complexity,
+            func.cognitive_complexity,
+            func.line_count,
+            func.parameter_count,
+            func.return_complexity
+        )?;
+    }
+
+    writeln!(md, "\n## Files\n")?;
+    writeln!(
+        md,
+        "| File | Functions | Lines | Max cyclomatic | Average cyclomatic | Maintainability |"
+    )?;
+    writeln!(md, "|---|---:|---:|---:|---:|---:|")?;
+    for (file, summary) in file_rollups(project) {
+        writeln!(
+            md,
+            "| {} | {} | {} | {} | {:.2} | {:.2} |",
+            markdown_code(&file.display().to_string()),
+            summary.functions.len(),
+            summary.total_lines,
+            summary.max_cyclomatic,
+            summary.average_cyclomatic,
+            summary.maintainability_index
+        )?;
+    }
+
+    if !project.skipped.is_empty() {
+        writeln!(md, "\n## Skipped Files\n")?;
+        for (file, reason) in &project.skipped {
+            writeln!(
+                md,
+                "- {}: {}",
+                markdown_code(&file.display().to_string()),
+                reason
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn count(project: &ProjectComplexity, rating: &ComplexityRating) -> usize {
+    let distribution = &project.distribution;
+    match rating {
+        ComplexityRating::Low => distribution.low,
+        ComplexityRating::Medium => distribution.medium,
+        ComplexityRating::High => distribution.high,
+        ComplexityRating::VeryHigh => distribution.very_high,
+    }
+}
+
+/// Inline code span, so `<T as Trait>` is not taken for HTML; pipes would
+/// still end the table cell
+fn markdown_code(text: &str) -> String {
+    format!("`{}`", text.replace('|', "\\|"))
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;color:#222}\
+table{border-collapse:collapse;margin-bottom:2rem}\
+th,td{border:1px solid #ccc;padding:.3rem .6rem;text-align:left}\
+td.num{text-align:right}\
+th.sortable{cursor:pointer;background:#f4f4f4}\
+code{font-size:.9em}\
+.rating-low{color:#2a7}.rating-medium{color:#b80}.rating-high{color:#d40}.rating-very-high{color:#c00;font-weight:bold}";
+
+// Sorts a table by the clicked column; numeric cells carry a data-value
+const HTML_SORT_SCRIPT: &str = "document.querySelectorAll('th.sortable').forEach(function(th){\
+th.addEventListener('click',function(){\
+var table=th.closest('table'),body=table.tBodies[0],i=th.cellIndex,asc=th.dataset.asc!=='true';\
+th.dataset.asc=asc;\
+var key=function(row){var c=row.cells[i];return c.dataset.value!==undefined?parseFloat(c.dataset.value):c.textContent;};\
+Array.from(body.rows).sort(function(a,b){var x=key(a),y=key(b);return (x<y?-1:x>y?1:0)*(asc?1:-1);})\
+.forEach(function(row){body.appendChild(row);});});});";
+
+fn rating_class(rating: &ComplexityRating) -> &'static str {
+    match rating {
+        ComplexityRating::Low => "rating-low",
+        ComplexityRating::Medium => "rating-medium",
+        ComplexityRating::High => "rating-high",
+        ComplexityRating::VeryHigh => "rating-very-high",
+    }
+}
+
+/// Self-contained HTML version of [`render_markdown`], with inline CSS and a
+/// small script that sorts the tables by the clicked column
+pub fn render_html(project: &ProjectComplexity, worst_n: usize) -> String {
+    let mut html = String::new();
+    // Writing to a String cannot fail
+    let _ = write_html(&mut html, project, worst_n);
+    html
+}
+
+fn write_html(html: &mut String, project: &ProjectComplexity, worst_n: usize) -> std::fmt::Result {
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+    writeln!(html, "<title>Complexity Report</title>")?;
+    writeln!(html, "<style>{}</style>\n</head>\n<body>", HTML_STYLE)?;
+    writeln!(html, "<h1>Complexity Report</h1>")?;
+
+    writeln!(html, "<h2>Summary</h2>\n<table>")?;
+    for (metric, value) in summary_rows(project) {
+        writeln!(
+            html,
+            "<tr><th>{}</th><td class=\"num\">{}</td></tr>",
+            metric, value
+        )?;
+    }
+    writeln!(html, "</table>")?;
+
+    writeln!(html, "<h2>Rating Distribution</h2>\n<table>")?;
+    writeln!(
+        html,
+        "<tr><th>Rating</th><th>Functions</th><th>Share</th></tr>"
+    )?;
+    for rating in &RATINGS {
+        writeln!(
+            html,
+            "<tr><td class=\"{}\">{}</td><td class=\"num\">{}</td><td class=\"num\">{:.1}%</td></tr>",
+            rating_class(rating),
+            rating,
+            count(project, rating),
+            project.distribution.percentage(rating)
+        )?;
+    }
+    writeln!(html, "</table>")?;
+
+    writeln!(html, "<h2>Worst Functions</h2>\n<table>")?;
+    writeln!(
+        html,
+        "<thead><tr>{}</tr></thead>\n<tbody>",
+        sortable_headers(&[
+            "Function",
+            "Location",
+            "Cyclomatic",
+            "Cognitive",
+            "Lines",
+            "Params",
+            "Rating",
+        ])
+    )?;
+    for (file, func) in worst_functions(project, worst_n) {
+        writeln!(
+            html,
+            "<tr><td><code>{}</code></td><td>{}</td>{}{}{}{}<td class=\"{}\">{}</td></tr>",
+            escape_html(&func.name),
+            escape_html(&location(file, func)),
+            number_cell(func.cyclomatic_complexity as f64, 0),
+            number_cell(func.cognitive_complexity as f64, 0),
+            number_cell(func.line_count as f64, 0),
+            number_cell(func.parameter_count as f64, 0),
+            rating_class(&func.return_complexity),
+            func.return_complexity
+        )?;
+    }
+    writeln!(html, "</tbody>\n</table>")?;
+
+    writeln!(html, "<h2>Files</h2>\n<table>")?;
+    writeln!(
+        html,
+        "<thead><tr>{}</tr></thead>\n<tbody>",
+        sortable_headers(&[
+            "File",
+            "Functions",
+            "Lines",
+            "Max cyclomatic",
+            "Average cyclomatic",
+            "Maintainability",
+        ])
+    )?;
+    for (file, summary) in file_rollups(project) {
+        writeln!(
+            html,
+            "<tr><td>{}</td>{}{}{}{}{}</tr>",
+            escape_html(&file.display().to_string()),
+            number_cell(summary.functions.len() as f64, 0),
+            number_cell(summary.total_lines as f64, 0),
+            number_cell(summary.max_cyclomatic as f64, 0),
+            number_cell(summary.average_cyclomatic, 2),
+            number_cell(summary.maintainability_index, 2)
+        )?;
+    }
+    writeln!(html, "</tbody>\n</table>")?;
+
+    if !project.skipped.is_empty() {
+        writeln!(html, "<h2>Skipped Files</h2>\n<ul>")?;
+        for (file, reason) in &project.skipped {
+            writeln!(
+                html,
+                "<li>{}: {}</li>",
+                escape_html(&file.display().to_string()),
+                escape_html(reason)
+            )?;
+        }
+        writeln!(html, "</ul>")?;
+    }
+
+    writeln!(html, "<script>{}</script>", HTML_SORT_SCRIPT)?;
+    writeln!(html, "</body>\n</html>")
+}
+
+fn sortable_headers(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("<th class=\"sortable\">{}</th>", name))
+        .collect()
+}
+
+fn number_cell(value: f64, precision: usize) -> String {
+    format!(
+        "<td class=\"num\" data-value=\"{}\">{:.*}</td>",
+        value, precision, value
+    )
+}
@@ -0,0 +1,76 @@
+use super::lint::{LintMetric, Violation};
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::path::Path;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const TOOL_NAME: &str = "rust-copartner";
+const HELP_URI: &str =
+    "https://github.com/JoverZhang/Rust-Copartner/blob/main/rust/src/complexity_analyzer/lint.rs";
+
+/// SARIF 2.1.0 log with one result per violation, for code scanning tools
+pub fn to_sarif(violations: &[Violation]) -> Value {
+    let rules: Vec<Value> = LintMetric::ALL
+        .iter()
+        .map(|metric| {
+            json!({
+                "id": metric.rule_id(),
+                "shortDescription": { "text": format!("Function {} exceeds the limit", metric) },
+                "helpUri": HELP_URI,
+            })
+        })
+        .collect();
+    let results: Vec<Value> = violations.iter().map(result).collect();
+
+    json!({
+        "$schema": SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": TOOL_NAME,
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": HELP_URI,
+                    "rules": rules,
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn result(violation: &Violation) -> Value {
+    let mut result = json!({
+        "ruleId": violation.metric.rule_id(),
+        "level": "warning",
+        "message": {
+            "text": format!(
+                "`{}` {} is {} (limit {})",
+                violation.function, violation.metric, violation.actual, violation.limit
+            )
+        },
+    });
+
+    if let Some(file) = &violation.file {
+        let mut physical = json!({
+            "artifactLocation": { "uri": artifact_uri(file) },
+        });
+        // SARIF lines are 1-based; 0 means the line is unknown
+        if violation.line > 0 {
+            physical["region"] = json!({ "startLine": violation.line });
+        }
+        result["locations"] = json!([{ "physicalLocation": physical }]);
+    }
+    result
+}
+
+/// SARIF URIs use forward slashes on every platform
+fn artifact_uri(file: &Path) -> String {
+    file.to_string_lossy().replace('\\', "/")
+}
+
+pub fn write_sarif(violations: &[Violation], path: &Path) -> Result<()> {
+    let content = serde_json::to_string_pretty(&to_sarif(violations))?;
+    std::fs::write(path, content)
+        .with_context(|| format!("Failed to write SARIF file: {}", path.display()))
+}
@@ -0,0 +1,95 @@
+use super::FunctionComplexity;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Per-metric multipliers of the composite score. Missing keys in a weights
+/// file fall back to the defaults.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoreWeights {
+    pub cyclomatic: usize,
+    pub cognitive: usize,
+    /// Loops have significant performance impact
+    pub loops: usize,
+    /// Deep nesting affects branch prediction
+    pub nesting: usize,
+    pub function_calls: usize,
+    pub unsafe_blocks: usize,
+    /// Many parameters affect stack usage
+    pub parameters: usize,
+    /// Each await yields and grows the future's state machine
+    pub await_points: usize,
+    /// Upper bound of the score; `None` leaves it uncapped. TOML has no null,
+    /// so `cap = 0` does the same there.
+    pub cap: Option<usize>,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            cyclomatic: 5,
+            cognitive: 3,
+            loops: 15,
+            nesting: 8,
+            function_calls: 2,
+            unsafe_blocks: 10,
+            parameters: 3,
+            await_points: 4,
+            cap: Some(100),
+        }
+    }
+}
+
+impl ScoreWeights {
+    /// Load weights from a TOML file such as:
+    ///
+    /// ```toml
+    /// loops = 20
+    /// cap = 200
+    /// ```
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read weights: {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse weights: {}", path.display()))
+    }
+
+    /// The effective cap, if any
+    pub fn cap(&self) -> Option<usize> {
+        self.cap.filter(|&cap| cap > 0)
+    }
+}
+
+/// Weighted sum of a function's metrics, used to rank functions by how much
+/// attention they need
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct ComplexityScore {
+    /// Score after applying the cap
+    pub value: usize,
+    /// Score before applying the cap
+    pub raw: usize,
+}
+
+impl ComplexityScore {
+    pub fn compute(func: &FunctionComplexity, weights: &ScoreWeights) -> Self {
+        let details = &func.details;
+        let raw = func.cyclomatic_complexity * weights.cyclomatic
+            + func.cognitive_complexity * weights.cognitive
+            + details.loops * weights.loops
+            + details.max_nesting_depth * weights.nesting
+            + details.function_calls * weights.function_calls
+            + details.unsafe_blocks * weights.unsafe_blocks
+            + func.parameter_count * weights.parameters
+            + details.await_points * weights.await_points;
+
+        Self {
+            value: weights.cap().map_or(raw, |cap| raw.min(cap)),
+            raw,
+        }
+    }
+
+    pub fn is_capped(&self) -> bool {
+        self.value < self.raw
+    }
+}
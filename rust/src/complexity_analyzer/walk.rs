@@ -0,0 +1,133 @@
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use std::path::{Component, Path, PathBuf};
+
+/// Which files below a root directory are analyzed. Globs match paths
+/// relative to the root, e.g. `src/**/*.rs` or `generated/**`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSelection {
+    /// Honor `.gitignore`, `.ignore` and git exclude files, even outside a git repository
+    pub respect_ignore: bool,
+    /// Only files matching one of these globs; empty selects every file
+    pub include: Vec<String>,
+    /// Files and directories matching one of these globs are skipped
+    pub exclude: Vec<String>,
+}
+
+impl Default for FileSelection {
+    fn default() -> Self {
+        Self {
+            respect_ignore: true,
+            include: Vec::new(),
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// Result of [`rust_files`]
+#[derive(Debug, Default)]
+pub struct SourceFiles {
+    pub files: Vec<PathBuf>,
+    /// Entries that could not be read, e.g. broken symlinks or symlink loops
+    pub errors: Vec<(PathBuf, String)>,
+}
+
+fn glob_set(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("Invalid glob `{}`", pattern))?);
+    }
+    Ok(builder.build()?)
+}
+
+/// Normal components of a relative path, splitting on `\` too: Windows
+/// separators survive as part of a component on Unix
+pub(crate) fn path_parts(rel: &Path) -> impl Iterator<Item = String> + '_ {
+    rel.components().flat_map(|c| match c {
+        Component::Normal(part) => part
+            .to_string_lossy()
+            .split('\\')
+            .filter(|s| !s.is_empty() && *s != ".")
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
+/// Whether `rel`, relative to the walk root, lies in a `target/` directory
+/// below it. Only whole components count, so `target_data/` is kept, and a
+/// root that is itself inside `target/` is analyzed since the path above the
+/// root isn't looked at.
+pub fn in_target_dir(rel: &Path) -> bool {
+    path_parts(rel).any(|part| part == "target")
+}
+
+/// Generated code named `*.generated.rs`, by file name alone
+pub fn is_generated(path: &Path) -> bool {
+    path_parts(path)
+        .last()
+        .is_some_and(|name| name.ends_with(".generated.rs"))
+}
+
+/// Every `.rs` file below `root` that `selection` lets through, in path order.
+/// `target/` directories are always skipped; symlinks are followed with loop
+/// detection. `max_depth` counts `root` itself as depth 0.
+pub fn rust_files(
+    root: &Path,
+    selection: &FileSelection,
+    max_depth: Option<usize>,
+) -> Result<SourceFiles> {
+    let include = glob_set(&selection.include)?;
+    let exclude = glob_set(&selection.exclude)?;
+    let relative = |path: &Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+
+    let owned_root = root.to_path_buf();
+    let walker = WalkBuilder::new(root)
+        .standard_filters(false)
+        .git_ignore(selection.respect_ignore)
+        .git_exclude(selection.respect_ignore)
+        .ignore(selection.respect_ignore)
+        .parents(selection.respect_ignore)
+        .require_git(false)
+        .follow_links(true)
+        .max_depth(max_depth)
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .filter_entry(move |e| {
+            if e.depth() == 0 {
+                return true;
+            }
+            let rel = e.path().strip_prefix(&owned_root).unwrap_or(e.path());
+            !in_target_dir(rel) && !exclude.is_match(rel)
+        })
+        .build();
+
+    let mut sources = SourceFiles::default();
+    for entry in walker {
+        match entry {
+            Ok(entry) => {
+                let file = entry.path();
+                let is_file = entry.file_type().is_some_and(|t| t.is_file());
+                if is_file
+                    && file.extension().is_some_and(|e| e == "rs")
+                    && (include.is_empty() || include.is_match(relative(file)))
+                {
+                    sources.files.push(file.to_path_buf());
+                }
+            }
+            Err(e) => sources.errors.push((error_path(&e, root), e.to_string())),
+        }
+    }
+    Ok(sources)
+}
+
+fn error_path(error: &ignore::Error, root: &Path) -> PathBuf {
+    match error {
+        ignore::Error::WithPath { path, .. } => path.clone(),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            error_path(err, root)
+        }
+        ignore::Error::Loop { child, .. } => child.clone(),
+        _ => root.to_path_buf(),
+    }
+}
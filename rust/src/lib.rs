@@ -1,4 +1,5 @@
 pub mod analyzer;
+pub mod cli_style;
 pub mod complexity_analyzer;
 pub mod indexer;
 
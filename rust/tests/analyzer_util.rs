@@ -0,0 +1,274 @@
+use rust_copartner::analyzer::util::{
+    collect_idents, compact_whitespace, estimate_tokens, format_method_signature, format_receiver,
+    is_doc_hidden, merge_doc_comments, normalize_type_name, rel_module_path, strip_comments,
+    truncate_text,
+};
+use std::path::Path;
+
+#[test]
+fn rel_module_path_covers_common_layouts() {
+    let cases = [
+        ("src/lib.rs", "crate"),
+        ("src/main.rs", "crate"),
+        ("src/foo.rs", "crate::foo"),
+        ("src/foo/mod.rs", "crate::foo"),
+        ("src/foo/bar.rs", "crate::foo::bar"),
+        ("src/foo/bar/mod.rs", "crate::foo::bar"),
+        ("src/foo/lib.rs", "crate::foo::lib"),
+        ("src/foo/main.rs", "crate::foo::main"),
+        ("src/bin/tool.rs", "crate::bin::tool"),
+        ("src/bin/tool/main.rs", "crate::bin::tool"),
+        ("src/bin/tool/args.rs", "crate::bin::tool::args"),
+        ("foo/bar.rs", "crate::foo::bar"),
+        ("./src/foo.rs", "crate::foo"),
+        ("src\\foo\\mod.rs", "crate::foo"),
+        ("src\\foo\\bar.rs", "crate::foo::bar"),
+    ];
+    let root = Path::new("/project");
+    for (file, expected) in cases {
+        assert_eq!(
+            rel_module_path(root, &root.join(file)),
+            expected,
+            "layout {file}"
+        );
+    }
+}
+
+fn attrs(source: &str) -> Vec<syn::Attribute> {
+    syn::parse_str::<syn::ItemStruct>(source).unwrap().attrs
+}
+
+#[test]
+fn merge_doc_comments_handles_every_doc_form() {
+    let cases = [
+        (
+            "/// First line\n/// Second line\nstruct S;",
+            "First line\nSecond line",
+        ),
+        ("/**\n * Block docs\n */\nstruct S;", "* Block docs"),
+        ("/** Inline block */\nstruct S;", "Inline block"),
+        ("#[doc = \"Attribute docs\"]\nstruct S;", "Attribute docs"),
+        (
+            "/// Says \"hello\" to `Point`\n#[doc = r#\"raw \"quoted\" line\"#]\nstruct S;",
+            "Says \"hello\" to `Point`\nraw \"quoted\" line",
+        ),
+        ("#[derive(Debug)]\nstruct S;", ""),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(merge_doc_comments(&attrs(source)), expected, "{source}");
+    }
+}
+
+#[test]
+fn is_doc_hidden_detects_hidden_items() {
+    assert!(is_doc_hidden(&attrs("#[doc(hidden)]\nstruct S;")));
+    assert!(is_doc_hidden(&attrs("/// Docs\n#[doc(hidden)]\nstruct S;")));
+    assert!(!is_doc_hidden(&attrs("/// hidden\nstruct S;")));
+    assert!(!is_doc_hidden(&attrs(
+        "#[doc(alias = \"hidden\")]\nstruct S;"
+    )));
+}
+
+#[test]
+fn normalize_type_name_cleans_up_self_types() {
+    let cases = [
+        ("DataProcessor<T>", "DataProcessor<T>"),
+        ("HashMap<String, Vec<u8>>", "HashMap<String, Vec<u8>>"),
+        ("Parser<'a, T>", "Parser<T>"),
+        ("Cow<'static, str>", "Cow<str>"),
+        ("Token<'_>", "Token"),
+        ("&'a mut Buffer", "&mut Buffer"),
+        ("&str", "&str"),
+        ("module::Foo", "module::Foo"),
+        ("crate::shapes::Point<f64>", "crate::shapes::Point<f64>"),
+        ("[u8; 4]", "[u8; 4]"),
+        ("(A, B)", "(A, B)"),
+        (
+            "Box<dyn Fn(u8) -> (A, B) + Send + 'a>",
+            "Box<dyn Fn(u8) -> (A, B) + Send>",
+        ),
+        ("&'a dyn Trait", "&dyn Trait"),
+    ];
+    for (source, expected) in cases {
+        let ty: syn::Type = syn::parse_str(source).unwrap();
+        assert_eq!(normalize_type_name(&ty), expected, "{source}");
+    }
+}
+
+#[test]
+fn estimate_tokens_stays_in_a_sane_band() {
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(estimate_tokens("   \n\t"), 0);
+    assert_eq!(estimate_tokens("fn"), 1);
+    // fn, new, (, x, :, i32, ), -, >, Self
+    assert_eq!(estimate_tokens("fn new(x: i32) -> Self"), 10);
+    assert_eq!(estimate_tokens("extraordinarily"), 4);
+
+    let prose = "Create a new point from two coordinates and return it to the caller";
+    let words = prose.split_whitespace().count();
+    let estimate = estimate_tokens(prose);
+    assert!((words..=words * 2).contains(&estimate), "{estimate}");
+
+    let code = include_str!("fixtures/point.rs");
+    let estimate = estimate_tokens(code);
+    assert!(
+        (code.len() / 6..=code.len() / 2).contains(&estimate),
+        "{estimate} for {} chars",
+        code.len()
+    );
+}
+
+#[test]
+fn strip_comments_leaves_literals_alone() {
+    let cases = [
+        ("let a = 1; // one", "let a = 1; "),
+        ("/// Docs\nfn f() {}", "\nfn f() {}"),
+        (
+            r#"let url = "https://example.com"; // home"#,
+            r#"let url = "https://example.com"; "#,
+        ),
+        (r#"let g = "src/**/*.rs";"#, r#"let g = "src/**/*.rs";"#),
+        (
+            r#"let s = "/* not a comment */";"#,
+            r#"let s = "/* not a comment */";"#,
+        ),
+        (
+            r#"let q = "say \"hi\" // still text";"#,
+            r#"let q = "say \"hi\" // still text";"#,
+        ),
+        (
+            r###"let r = r#"quote " then // and /* */"#; // gone"###,
+            r###"let r = r#"quote " then // and /* */"#; "###,
+        ),
+        (r#"let b = br"//raw bytes";"#, r#"let b = br"//raw bytes";"#),
+        ("let c = '/'; // slash", "let c = '/'; "),
+        ("let d = '\"'; // quote", "let d = '\"'; "),
+        ("let e = '\\''; /* escaped */", "let e = '\\''; "),
+        (
+            "fn f<'a>(x: &'a str) /* lifetimes */ {}",
+            "fn f<'a>(x: &'a str)  {}",
+        ),
+        ("a /* outer /* inner */ still outer */ b", "a  b"),
+        ("a /* unterminated", "a "),
+        ("let r#type = 1; // raw ident", "let r#type = 1; "),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(strip_comments(source), expected, "{source}");
+    }
+    assert_eq!(
+        compact_whitespace(&strip_comments(
+            "let u = \"http://x\"; // trailing\n  next();"
+        )),
+        "let u = \"http://x\"; next();"
+    );
+}
+
+#[test]
+fn compact_whitespace_keeps_literal_contents() {
+    let cases = [
+        ("  fn f()\n{\n    g();\t}\n", "fn f() { g(); }"),
+        (
+            "let s =   \"hello   world\\n\";",
+            "let s = \"hello   world\\n\";",
+        ),
+        (
+            "let s = \"line one\n    line two\";\n\n  f(s);",
+            "let s = \"line one\n    line two\"; f(s);",
+        ),
+        (
+            "let r = r#\"raw\n   \"keeps\"   gaps\"#;   done();",
+            "let r = r#\"raw\n   \"keeps\"   gaps\"#; done();",
+        ),
+        (
+            "println!(\"{:>8}  |  {}\",\n    name,   value);",
+            "println!(\"{:>8}  |  {}\", name, value);",
+        ),
+        (
+            "let c = ' ';   let t = '\\t';",
+            "let c = ' '; let t = '\\t';",
+        ),
+        ("fn f<'a>(x:   &'a str) {}", "fn f<'a>(x: &'a str) {}"),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(compact_whitespace(source), expected, "{source}");
+    }
+}
+
+#[test]
+fn collect_idents_accepts_any_token_stream() {
+    let cases = [
+        ("a + b * f(a, c)", "a b f c"),
+        (
+            "fn get<'a>(map: &'a HashMap<K, V>, key: &K) -> Option<&'a V> { map.get(key) }",
+            "get a map HashMap K V key Option",
+        ),
+        (
+            "impl<T: Clone> Wrapper<T> where T: Debug { fn inner(&self) -> &T { &self.0 } }",
+            "T Clone Wrapper Debug inner self",
+        ),
+        (
+            "x => { println!(\"{}\", x?); Ok::<_, Error>(()) } ;; ## @",
+            "x println Ok Error",
+        ),
+        (
+            "#[derive(Debug)] pub struct S { pub(crate) v: Self }",
+            "derive Debug S crate v Self",
+        ),
+        ("", ""),
+    ];
+    for (source, expected) in cases {
+        let tokens: proc_macro2::TokenStream = source.parse().unwrap();
+        assert_eq!(collect_idents(&tokens), expected, "{source}");
+    }
+}
+
+#[test]
+fn truncate_text_cuts_at_char_boundaries() {
+    assert_eq!(truncate_text("short", 5), None);
+    assert_eq!(
+        truncate_text("abcdef", 4).as_deref(),
+        Some("abcd… [truncated 2 bytes]")
+    );
+    // 'é' is two bytes; cutting inside it backs off to before it
+    assert_eq!(
+        truncate_text("café au lait", 4).as_deref(),
+        Some("caf… [truncated 10 bytes]")
+    );
+    assert_eq!(
+        truncate_text("🦀🦀", 5).as_deref(),
+        Some("🦀… [truncated 4 bytes]")
+    );
+    assert_eq!(
+        truncate_text("🦀", 0).as_deref(),
+        Some("… [truncated 4 bytes]")
+    );
+}
+
+#[test]
+fn format_receiver_names_how_self_is_taken() {
+    let cases = [
+        ("fn f(self)", Some("self")),
+        ("fn f(mut self)", Some("self")),
+        ("fn f(&self)", Some("&self")),
+        ("fn f(&'a self, x: u8)", Some("&self")),
+        ("fn f(&mut self)", Some("&mut self")),
+        ("fn f(self: Box<Self>)", Some("self: Box<Self>")),
+        ("fn f(x: i32)", None),
+        ("fn f()", None),
+    ];
+    for (src, expected) in cases {
+        let sig: syn::Signature = syn::parse_str(src).unwrap();
+        assert_eq!(format_receiver(&sig).as_deref(), expected, "{src}");
+    }
+}
+
+#[test]
+fn format_method_signature_normalizes_the_receiver() {
+    let sig: syn::Signature = syn::parse_str("fn sum(&'a mut self, n: i32) -> i32").unwrap();
+    assert_eq!(
+        format_method_signature(&sig),
+        "fn sum (&mut self , n : i32) -> i32"
+    );
+    let sig: syn::Signature = syn::parse_str("fn new(x: i32) -> Self").unwrap();
+    assert_eq!(format_method_signature(&sig), "fn new (x : i32) -> Self");
+}
@@ -0,0 +1,68 @@
+use std::process::{Command, Output};
+
+/// Run a binary with colors forced on, as if attached to a terminal
+fn run(bin: &str, args: &[&str], envs: &[(&str, &str)]) -> Output {
+    Command::new(bin)
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env_remove("NO_COLOR")
+        .env("CLICOLOR_FORCE", "1")
+        .envs(envs.iter().copied())
+        .output()
+        .expect("binary should run")
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn assert_plain(text: &str) {
+    assert!(!text.contains('\u{1b}'), "ANSI escape in:\n{text}");
+    assert!(text.is_ascii(), "non-ASCII marker in:\n{text}");
+}
+
+const SAMPLE: &str = "tests/samples/complex_example.rs";
+
+#[test]
+fn complexity_cli_drops_colors_and_emoji() {
+    let bin = env!("CARGO_BIN_EXE_complexity_cli");
+    let styled = stdout(&run(bin, &["dir", "-p", "tests/samples"], &[]));
+    assert!(styled.contains('\u{1b}'));
+    assert!(styled.contains('📁'));
+
+    let flag = stdout(&run(
+        bin,
+        &["dir", "-p", "tests/samples", "--no-color"],
+        &[],
+    ));
+    assert_plain(&flag);
+    assert!(flag.contains("  - "));
+
+    let env = stdout(&run(
+        bin,
+        &["file", "-p", SAMPLE, "-d"],
+        &[("NO_COLOR", "1")],
+    ));
+    assert_plain(&env);
+    assert!(env.contains("high_complexity"));
+}
+
+#[test]
+fn other_binaries_honor_no_color() {
+    let performance = stdout(&run(
+        env!("CARGO_BIN_EXE_performance_analyzer"),
+        &["-p", SAMPLE, "--threshold", "1", "--no-color"],
+        &[],
+    ));
+    assert_plain(&performance);
+    assert!(performance.contains("* Performance Analysis Tool"));
+
+    let test_gen = stdout(&run(
+        env!("CARGO_BIN_EXE_simple_ai_test_gen"),
+        &["-f", SAMPLE, "--dry-run"],
+        &[("NO_COLOR", "1"), ("OPENROUTER_API_KEY", "unused")],
+    ));
+    assert_plain(&test_gen);
+    assert!(test_gen.contains("[H] high_complexity"));
+}
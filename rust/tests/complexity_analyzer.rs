@@ -0,0 +1,1488 @@
+use rust_copartner::complexity_analyzer::walk::{in_target_dir, is_generated};
+use rust_copartner::complexity_analyzer::{
+    AnalysisCache, AnalyzeOptions, ComplexityAnalyzer, ComplexityRating, ComplexityReportExt,
+    ComplexitySummary, ComplexityThresholds, FileSelection, FunctionComplexity, Metric, TypeKind,
+};
+
+use regex::Regex;
+use std::path::Path;
+
+fn analyze(src: &str) -> Vec<FunctionComplexity> {
+    ComplexityAnalyzer::analyze_file(src).expect("analyze should succeed")
+}
+
+fn find<'a>(functions: &'a [FunctionComplexity], name: &str) -> &'a FunctionComplexity {
+    functions
+        .iter()
+        .find(|f| f.name == name)
+        .unwrap_or_else(|| panic!("function {} not found", name))
+}
+
+#[test]
+fn line_count_follows_source_spans() {
+    let functions = analyze(include_str!("samples/complex_example.rs"));
+
+    // (name, actual lines in the sample file)
+    for (name, expected) in [
+        ("simple_function", 3usize),
+        ("medium_complexity", 11),
+        ("high_complexity", 60),
+    ] {
+        let func = find(&functions, name);
+        assert!(
+            func.line_count.abs_diff(expected) <= 1,
+            "{}: expected ~{} lines, got {}",
+            name,
+            expected,
+            func.line_count
+        );
+    }
+}
+
+#[test]
+fn boolean_operators_add_to_cyclomatic() {
+    let functions = analyze(
+        r#"
+        fn single(a: bool) -> bool { if a { return true; } false }
+        fn same_run(a: bool, b: bool, c: bool) -> bool { if a && b && c { return true; } false }
+        fn mixed(a: bool, b: bool, c: bool) -> bool { if a && b || c { return true; } false }
+        "#,
+    );
+    let single = find(&functions, "single");
+    let same_run = find(&functions, "same_run");
+    let mixed = find(&functions, "mixed");
+
+    assert_eq!(single.details.boolean_operators, 0);
+    assert_eq!(same_run.details.boolean_operators, 2);
+    assert_eq!(mixed.details.boolean_operators, 2);
+    assert_eq!(
+        same_run.cyclomatic_complexity,
+        single.cyclomatic_complexity + 2
+    );
+    assert_eq!(
+        mixed.cyclomatic_complexity,
+        single.cyclomatic_complexity + 2
+    );
+
+    // Cognitive complexity counts runs of identical operators, not each operator
+    assert_eq!(
+        same_run.cognitive_complexity,
+        single.cognitive_complexity + 1
+    );
+    assert_eq!(mixed.cognitive_complexity, single.cognitive_complexity + 2);
+}
+
+#[test]
+fn boolean_operators_in_match_guards_are_counted() {
+    let functions = analyze(
+        r#"
+        fn guarded(x: i32, a: bool, b: bool) -> i32 {
+            match x {
+                0 if a && (b || x > 1) => 1,
+                _ => 2,
+            }
+        }
+        "#,
+    );
+    let guarded = find(&functions, "guarded");
+
+    assert_eq!(guarded.details.boolean_operators, 2);
+    assert_eq!(guarded.details.match_guards, 1);
+    // base + two arms + two operators + one guard
+    assert_eq!(guarded.cyclomatic_complexity, 1 + 2 + 2 + 1);
+}
+
+#[test]
+fn try_operators_are_opt_in_decision_points() {
+    let src = include_str!("samples/try_operators.rs");
+
+    let default = ComplexityAnalyzer::analyze_file(src).unwrap();
+    let load_all = find(&default, "load_all");
+    assert_eq!(load_all.details.try_operators, 10);
+    assert_eq!(load_all.cyclomatic_complexity, 1);
+
+    let options = AnalyzeOptions {
+        count_try: true,
+        ..Default::default()
+    };
+    let counted = ComplexityAnalyzer::analyze_file_with(src, &options).unwrap();
+    assert_eq!(find(&counted, "load_all").cyclomatic_complexity, 11);
+}
+
+#[test]
+fn let_else_counts_as_branch() {
+    let functions = analyze(include_str!("samples/let_else.rs"));
+
+    let parse_pair = find(&functions, "parse_pair");
+    assert_eq!(parse_pair.details.let_else, 3);
+    assert_eq!(parse_pair.cyclomatic_complexity, 4);
+
+    // The else block participates in nesting
+    let lookup = find(&functions, "lookup");
+    assert_eq!(lookup.details.let_else, 1);
+    assert_eq!(lookup.details.if_statements, 1);
+    assert_eq!(lookup.details.max_nesting_depth, 2);
+    assert_eq!(lookup.cyclomatic_complexity, 3);
+
+    let plain_let = find(&functions, "plain_let");
+    assert_eq!(plain_let.details.let_else, 0);
+    assert_eq!(plain_let.cyclomatic_complexity, 1);
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 1e-9,
+        "expected {}, got {}",
+        expected,
+        actual
+    );
+}
+
+#[test]
+fn halstead_metrics_for_tiny_functions() {
+    let functions = analyze(
+        r#"
+        fn add(a: i32, b: i32) -> i32 { a + b }
+        fn square_plus_one() -> i32 { let x = 1; x * x + 1 }
+        "#,
+    );
+
+    // Body `{ a + b }`: operators {}, + ; operands a, b
+    let add = &find(&functions, "add").halstead;
+    assert_eq!((add.distinct_operators, add.total_operators), (2, 2));
+    assert_eq!((add.distinct_operands, add.total_operands), (2, 2));
+    assert_close(add.volume, 8.0); // N = 4, n = 4 -> 4 * log2(4)
+    assert_close(add.difficulty, 1.0); // (2 / 2) * (2 / 2)
+    assert_close(add.effort, 8.0);
+
+    // Body `{ let x = 1; x * x + 1 }`:
+    // operators {}, let, =, ;, *, + ; operands x (3x), 1 (2x)
+    let sq = &find(&functions, "square_plus_one").halstead;
+    assert_eq!((sq.distinct_operators, sq.total_operators), (6, 6));
+    assert_eq!((sq.distinct_operands, sq.total_operands), (2, 5));
+    assert_close(sq.volume, 33.0); // N = 11, n = 8 -> 11 * 3
+    assert_close(sq.difficulty, 7.5); // (6 / 2) * (5 / 2)
+    assert_close(sq.effort, 247.5);
+}
+
+#[test]
+fn halstead_joins_multi_character_operators() {
+    let functions =
+        analyze("fn f(a: bool, b: bool) -> bool { a && b || std::mem::take(&mut false) }");
+    let h = &find(&functions, "f").halstead;
+
+    // {}, &&, ||, ::, (), &, mut
+    assert_eq!(h.distinct_operators, 7);
+    // a, b, std, mem, take, false
+    assert_eq!(h.distinct_operands, 6);
+}
+
+#[test]
+fn maintainability_index_is_clamped() {
+    let functions = analyze("fn tiny() {}");
+    let tiny = find(&functions, "tiny");
+    assert_close(tiny.maintainability_index(), 100.0);
+
+    let mut huge = tiny.clone();
+    huge.halstead.volume = 1e12;
+    huge.line_count = 100_000;
+    huge.cyclomatic_complexity = 500;
+    assert_close(huge.maintainability_index(), 0.0);
+}
+
+#[test]
+fn trivial_file_is_more_maintainable_than_complex_example() {
+    let trivial = ComplexityAnalyzer::analyze_file_summary(
+        r#"
+        fn one() -> i32 {
+            1
+        }
+
+        fn add(a: i32, b: i32) -> i32 {
+            a + b
+        }
+        "#,
+    )
+    .unwrap();
+    let complex =
+        ComplexityAnalyzer::analyze_file_summary(include_str!("samples/complex_example.rs"))
+            .unwrap();
+
+    assert_eq!(trivial.functions.len(), 2);
+    assert_eq!(complex.functions.len(), 3);
+    assert!(trivial.maintainability_index > complex.maintainability_index);
+    assert!(complex.max_cyclomatic >= complex.average_cyclomatic as usize);
+}
+
+#[test]
+fn cognitive_complexity_penalizes_nesting_per_structure() {
+    let functions = analyze(
+        r#"
+        fn flat(a: bool, b: bool, c: bool) -> i32 {
+            let mut n = 0;
+            if a { n += 1; }
+            if b { n += 1; }
+            if c { n += 1; }
+            n
+        }
+
+        fn nested(a: bool, b: bool, c: bool) -> i32 {
+            let mut n = 0;
+            if a {
+                if b {
+                    if c {
+                        n += 1;
+                    }
+                }
+            }
+            n
+        }
+        "#,
+    );
+    let flat = find(&functions, "flat");
+    let nested = find(&functions, "nested");
+
+    assert_eq!(flat.cyclomatic_complexity, nested.cyclomatic_complexity);
+    assert_eq!(flat.cognitive_complexity, 3); // 1 + 1 + 1
+    assert_eq!(nested.cognitive_complexity, 6); // 1 + 2 + 3
+    assert!(nested.cognitive_complexity > flat.cognitive_complexity);
+}
+
+#[test]
+fn cognitive_complexity_else_branches_have_no_nesting_penalty() {
+    let functions = analyze(
+        r#"
+        fn ladder(x: i32) -> i32 {
+            for _ in 0..3 {
+                if x > 10 {
+                    return 1;
+                } else if x > 5 {
+                    return 2;
+                } else {
+                    return 3;
+                }
+            }
+            0
+        }
+        "#,
+    );
+    let ladder = find(&functions, "ladder");
+
+    // for (+1), nested if (+1 +1 nesting), else if (+1), else (+1),
+    // three early returns (+1 at half a point each)
+    assert_eq!(ladder.cognitive_complexity, 6);
+    // Legacy formula: 2 ifs + 1 loop + max nesting 2 * 2
+    assert_eq!(ladder.legacy_cognitive, 7);
+}
+
+#[test]
+fn functions_record_source_locations() {
+    let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/samples/shapes.rs");
+    let functions = ComplexityAnalyzer::analyze_file_at(&path).unwrap();
+
+    let news: Vec<_> = functions
+        .iter()
+        .filter(|f| f.name.ends_with("new"))
+        .collect();
+    assert_eq!(news.len(), 2);
+    assert_eq!((news[0].start_line, news[0].end_line), (16, 18));
+    assert_eq!((news[1].start_line, news[1].end_line), (22, 27));
+    assert_ne!(news[0].location(), news[1].location());
+
+    for func in &functions {
+        assert_eq!(func.file.as_deref(), Some(path.as_path()));
+        assert_eq!(func.line_count, func.end_line - func.start_line + 1);
+    }
+    assert!(news[0].location().ends_with("shapes.rs:16"));
+
+    // Content-based analysis has no file
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(analyze(&content).iter().all(|f| f.file.is_none()));
+}
+
+#[test]
+fn methods_are_qualified_with_their_impl_type() {
+    let functions = analyze(include_str!("samples/shapes.rs"));
+    let names: Vec<_> = functions.iter().map(|f| f.name.as_str()).collect();
+
+    assert_eq!(
+        names,
+        vec!["Point::new", "Circle::new", "<Point as Display>::fmt"]
+    );
+    assert_eq!(
+        find(&functions, "<Point as Display>::fmt").short_name(),
+        "fmt"
+    );
+
+    let generic = analyze(include_str!("samples/advanced_features.rs"));
+    assert!(generic.iter().any(|f| f.name == "DataProcessor::new"));
+    // Free functions keep their bare name
+    assert!(generic.iter().any(|f| f.name == "debug_info"));
+}
+
+#[test]
+fn signature_qualifiers_and_visibility_are_recorded() {
+    let functions = analyze(
+        r#"
+        pub(crate) async fn fetch() {}
+        pub const unsafe fn raw() {}
+        fn helper() {}
+        pub(in crate::net) fn scoped() {}
+        "#,
+    );
+
+    let fetch = find(&functions, "fetch");
+    assert!(fetch.is_async);
+    assert!(!fetch.is_const && !fetch.is_unsafe);
+    assert_eq!(fetch.visibility, "pub(crate)");
+    assert!(!fetch.is_public());
+
+    let raw = find(&functions, "raw");
+    assert!(raw.is_const && raw.is_unsafe && !raw.is_async);
+    assert_eq!(raw.visibility, "pub");
+
+    assert_eq!(find(&functions, "helper").visibility, "private");
+    assert_eq!(find(&functions, "scoped").visibility, "pub(in crate::net)");
+
+    // What `--public-only` keeps
+    let public: Vec<_> = functions.iter().filter(|f| f.is_public()).collect();
+    assert_eq!(public.len(), 1);
+    assert_eq!(public[0].name, "raw");
+}
+
+#[test]
+fn snippets_are_analyzed_without_a_full_file() {
+    let lone_fn =
+        ComplexityAnalyzer::analyze_snippet("fn area(&self) -> f64 { self.w * self.h }").unwrap();
+    assert_eq!(lone_fn.len(), 1);
+    assert_eq!(lone_fn[0].name, "area");
+
+    let lone_impl = ComplexityAnalyzer::analyze_snippet(
+        "impl Shape {\n    fn grow(&mut self) {\n        if self.w < 10 { self.w += 1; }\n    }\n}",
+    )
+    .unwrap();
+    assert_eq!(lone_impl.len(), 1);
+    assert_eq!(lone_impl[0].name, "Shape::grow");
+    assert_eq!(lone_impl[0].cyclomatic_complexity, 2);
+
+    let statements = ComplexityAnalyzer::analyze_snippet(
+        "let total = compute();\nif total > 0 {\n    log(total);\n}\nfor i in 0..total {\n    step(i);\n}",
+    )
+    .unwrap();
+    assert_eq!(statements.len(), 1);
+    let snippet = &statements[0];
+    assert_eq!(snippet.name, rust_copartner::SNIPPET_FN_NAME);
+    assert_eq!(snippet.cyclomatic_complexity, 3);
+    assert_eq!(snippet.start_line, 1);
+    assert_eq!(snippet.details.loops, 1);
+
+    assert!(ComplexityAnalyzer::analyze_snippet("let = ;").is_err());
+}
+
+#[test]
+fn function_attributes_are_recorded_without_docs() {
+    let functions = analyze(include_str!("samples/attributes.rs"));
+
+    assert_eq!(find(&functions, "small").details.attributes, vec!["inline"]);
+    assert_eq!(
+        find(&functions, "main").details.attributes,
+        vec!["tokio::main"]
+    );
+    assert_eq!(
+        find(&functions, "list_users").details.attributes,
+        vec!["route(GET , \"/users\")", "inline(always)"]
+    );
+    assert_eq!(
+        find(&functions, "plain").details.attributes,
+        vec!["must_use = \"check the result\""]
+    );
+}
+
+#[test]
+fn functions_in_nested_modules_carry_the_module_path() {
+    let src = include_str!("samples/nested_modules.rs");
+    let with_tests = analyze(src);
+    let names: Vec<_> = with_tests.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "handle",
+            "handlers::handle",
+            "handlers::admin::handle",
+            "handlers::admin::Session::handle",
+            "handlers::admin::tests::handle",
+        ]
+    );
+    assert!(with_tests.iter().all(|f| f.short_name() == "handle"));
+
+    // Skipping the test module leaves the path of the modules around it intact
+    let options = AnalyzeOptions {
+        include_tests: false,
+        ..Default::default()
+    };
+    let without_tests = ComplexityAnalyzer::analyze_file_with(src, &options).unwrap();
+    assert_eq!(without_tests.len(), 4);
+    assert_eq!(
+        without_tests.last().unwrap().name,
+        "handlers::admin::Session::handle"
+    );
+}
+
+#[test]
+fn impl_blocks_aggregate_per_type_and_trait() {
+    let impls = ComplexityAnalyzer::analyze_impls(include_str!("samples/shapes.rs")).unwrap();
+    let names: Vec<_> = impls.iter().map(|i| i.display_name()).collect();
+
+    // Sorted by total complexity; Circle::new has an early return
+    assert_eq!(names, vec!["Circle", "Point", "<Point as Display>"]);
+
+    let inherent = &impls[1];
+    assert_eq!(inherent.type_name, "Point");
+    assert_eq!(inherent.trait_name, None);
+    assert_eq!(inherent.method_count, 1);
+    assert_eq!(inherent.total_cyclomatic, 1);
+
+    let display = &impls[2];
+    assert_eq!(display.type_name, "Point");
+    assert_eq!(display.trait_name.as_deref(), Some("Display"));
+    assert_eq!(display.method_count, 1);
+
+    assert_eq!(impls[0].total_cyclomatic, 2);
+    assert_eq!(impls[0].max_cyclomatic, 2);
+    assert_eq!(impls[0].rating, ComplexityRating::Low);
+}
+
+#[test]
+fn test_code_is_skipped_unless_included() {
+    let src = include_str!("samples/test_module.rs");
+    let options = AnalyzeOptions {
+        include_tests: false,
+        ..Default::default()
+    };
+    let without_tests = ComplexityAnalyzer::analyze_file_with(src, &options).unwrap();
+    // `not(test)` code and `any(test, ..)` code can build outside tests
+    let names: Vec<&str> = without_tests.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["production_only", "fake_clock"]);
+
+    let options = AnalyzeOptions {
+        include_tests: true,
+        ..Default::default()
+    };
+    let with_tests = ComplexityAnalyzer::analyze_file_with(src, &options).unwrap();
+    assert_eq!(with_tests.len(), 6);
+}
+
+#[test]
+fn rating_thresholds_are_inclusive_upper_bounds() {
+    let thresholds = ComplexityThresholds {
+        low_max: 3,
+        medium_max: 8,
+        high_max: 12,
+    };
+    let cases = [
+        (1, ComplexityRating::Low),
+        (3, ComplexityRating::Low),
+        (4, ComplexityRating::Medium),
+        (8, ComplexityRating::Medium),
+        (9, ComplexityRating::High),
+        (12, ComplexityRating::High),
+        (13, ComplexityRating::VeryHigh),
+    ];
+    for (score, expected) in cases {
+        assert_eq!(
+            ComplexityRating::from_score_with(score, &thresholds),
+            expected,
+            "score {}",
+            score
+        );
+    }
+
+    // Defaults keep the historical buckets
+    assert_eq!(ComplexityRating::from_score(5), ComplexityRating::Low);
+    assert_eq!(ComplexityRating::from_score(6), ComplexityRating::Medium);
+    assert_eq!(ComplexityRating::from_score(20), ComplexityRating::High);
+    assert_eq!(ComplexityRating::from_score(21), ComplexityRating::VeryHigh);
+}
+
+#[test]
+fn analysis_applies_custom_thresholds() {
+    let src = include_str!("samples/complex_example.rs");
+    let default = analyze(src);
+    assert_eq!(
+        find(&default, "medium_complexity").return_complexity,
+        ComplexityRating::Low
+    );
+
+    let options = AnalyzeOptions {
+        thresholds: ComplexityThresholds {
+            low_max: 2,
+            medium_max: 3,
+            high_max: 8,
+        },
+        ..Default::default()
+    };
+    let strict = ComplexityAnalyzer::analyze_file_with(src, &options).unwrap();
+    assert_eq!(
+        find(&strict, "medium_complexity").return_complexity,
+        ComplexityRating::Medium
+    );
+    assert_eq!(
+        find(&strict, "high_complexity").return_complexity,
+        ComplexityRating::VeryHigh
+    );
+}
+
+#[test]
+fn thresholds_must_increase() {
+    assert!(ComplexityThresholds::default().validate().is_ok());
+    let bad = ComplexityThresholds {
+        low_max: 8,
+        medium_max: 8,
+        high_max: 20,
+    };
+    assert!(bad.validate().is_err());
+}
+
+#[test]
+fn analyze_project_aggregates_generated_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let file_count = 300;
+    for i in 0..file_count {
+        let sub = root.join(format!("module_{}", i % 10));
+        std::fs::create_dir_all(&sub).unwrap();
+        // Two functions per file: one with a single branch, one with none
+        let src = format!(
+            "pub fn branch_{i}(x: i32) -> i32 {{ if x > {i} {{ 1 }} else {{ 0 }} }}\n\
+             pub fn plain_{i}() -> i32 {{ {i} }}\n"
+        );
+        std::fs::write(sub.join(format!("file_{}.rs", i)), src).unwrap();
+    }
+    // Build output and broken files must not abort the run
+    std::fs::create_dir_all(root.join("target/debug")).unwrap();
+    std::fs::write(root.join("target/debug/build.rs"), "fn ignored() {}").unwrap();
+    std::fs::write(root.join("broken.rs"), "fn broken( {").unwrap();
+
+    let project =
+        ComplexityAnalyzer::analyze_project(root, &AnalyzeOptions::default(), None).unwrap();
+
+    assert_eq!(project.files.len(), file_count);
+    assert_eq!(project.total_functions, file_count * 2);
+    assert_eq!(project.distribution.low, file_count * 2);
+    assert_eq!(project.skipped.len(), 1);
+    assert!(project.skipped[0].0.ends_with("broken.rs"));
+    assert_close(project.average_cyclomatic, 1.5);
+    assert!(project.files.keys().all(|k| k.is_relative()));
+    assert!(project.functions().all(|f| f.file.is_some()));
+}
+
+#[test]
+fn await_points_are_counted_for_async_functions() {
+    let functions = analyze(include_str!("samples/advanced_features.rs"));
+
+    assert_eq!(
+        find(&functions, "DataProcessor::process_with_macros")
+            .details
+            .await_points,
+        1
+    );
+    assert_eq!(
+        find(&functions, "DataProcessor::transform_data")
+            .details
+            .await_points,
+        2
+    );
+    assert_eq!(
+        find(&functions, "DataProcessor::validate_input")
+            .details
+            .await_points,
+        0
+    );
+    assert_eq!(find(&functions, "debug_info").details.await_points, 0);
+}
+
+#[test]
+fn signature_complexity_reflects_return_type_and_bounds() {
+    let plain = analyze("fn f() -> i32 { 0 }");
+    assert_eq!(find(&plain, "f").details.signature_complexity, 0);
+
+    let nested = analyze(
+        "fn g<T>() -> Result<HashMap<String, Vec<Arc<Mutex<T>>>>, Box<dyn Error>> { todo!() }",
+    );
+    assert_eq!(find(&nested, "g").details.signature_complexity, 5);
+
+    // Result<(T, U, V), String> is two levels deep, plus five where-clause bounds
+    let advanced = analyze(include_str!("samples/advanced_features.rs"));
+    let generic = find(&advanced, "complex_generic_function");
+    assert_eq!(generic.details.signature_complexity, 7);
+    assert!(generic.details.signature_complexity > find(&plain, "f").details.signature_complexity);
+}
+
+#[test]
+fn early_returns_exclude_the_trailing_return() {
+    let functions = analyze(
+        r#"
+        fn guard_clauses(a: Option<i32>, b: i32) -> i32 {
+            let Some(a) = a else { return 0; };
+            if b < 0 {
+                return -1;
+            }
+            if a == b {
+                return 1;
+            }
+            let add = |x: i32| { return x + 1; };
+            return add(a);
+        }
+
+        fn single_exit(a: i32) -> i32 {
+            let result = if a > 0 { a } else { -a };
+            result
+        }
+        "#,
+    );
+    let guards = find(&functions, "guard_clauses");
+    let single = find(&functions, "single_exit");
+
+    // Three guard returns; the closure's return and the trailing return don't count
+    assert_eq!(guards.details.early_returns, 3);
+    assert_eq!(single.details.early_returns, 0);
+}
+
+#[test]
+fn direct_recursion_is_detected() {
+    let functions = analyze(
+        r#"
+        fn factorial(n: u64) -> u64 {
+            if n == 0 {
+                1
+            } else {
+                n * factorial(n - 1)
+            }
+        }
+
+        fn iterative(n: u64) -> u64 {
+            (1..=n).product()
+        }
+
+        struct Tree;
+
+        impl Tree {
+            fn itself(&self, depth: usize) -> usize {
+                Self::itself(self, depth - 1)
+            }
+
+            fn walk(&self, depth: usize) -> usize {
+                self.walk(depth - 1)
+            }
+
+            fn caller(&self) -> usize {
+                Self::helper()
+            }
+
+            fn helper() -> usize {
+                0
+            }
+
+            fn named(depth: usize) -> usize {
+                Tree::named(depth - 1)
+            }
+
+            // Calls the free function below, not itself
+            fn size(&self) -> usize {
+                size(self)
+            }
+        }
+
+        fn size(_tree: &Tree) -> usize {
+            0
+        }
+        "#,
+    );
+
+    let factorial = find(&functions, "factorial");
+    assert!(factorial.details.is_recursive);
+    // if (+1), else (+1), recursion (+1)
+    assert_eq!(factorial.cognitive_complexity, 3);
+    assert!(!find(&functions, "iterative").details.is_recursive);
+
+    assert!(find(&functions, "Tree::itself").details.is_recursive);
+    assert!(find(&functions, "Tree::walk").details.is_recursive);
+    assert!(!find(&functions, "Tree::caller").details.is_recursive);
+    assert!(!find(&functions, "Tree::helper").details.is_recursive);
+    assert!(find(&functions, "Tree::named").details.is_recursive);
+    assert!(!find(&functions, "Tree::size").details.is_recursive);
+    assert!(!find(&functions, "size").details.is_recursive);
+}
+
+#[test]
+fn type_complexity_rates_large_enums_above_small_structs() {
+    let source = r#"
+        #[derive(Debug, Clone, PartialEq)]
+        struct Pair<T> {
+            left: T,
+            right: T,
+        }
+
+        enum Opcode {
+VARIANTS
+        }
+
+        enum Shape {
+            Circle(f64),
+            Rect { w: f64, h: f64 },
+            Empty,
+        }
+    "#
+    .replace(
+        "VARIANTS",
+        &(0..30)
+            .map(|i| format!("V{i},"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    );
+    let types = ComplexityAnalyzer::analyze_types(&source).unwrap();
+    let by_name = |name: &str| types.iter().find(|t| t.name == name).unwrap();
+
+    let pair = by_name("Pair");
+    assert_eq!(pair.kind, TypeKind::Struct);
+    assert_eq!(pair.field_count, 2);
+    assert_eq!(pair.generic_parameters, 1);
+    assert_eq!(pair.derive_count, 3);
+    assert_eq!(pair.rating, ComplexityRating::Low);
+
+    let opcode = by_name("Opcode");
+    assert_eq!(opcode.kind, TypeKind::Enum);
+    assert_eq!(opcode.variant_count, 30);
+    assert_eq!(opcode.rating, ComplexityRating::High);
+
+    let shape = by_name("Shape");
+    assert_eq!(shape.variant_count, 3);
+    assert_eq!(shape.field_count, 3);
+}
+
+#[test]
+fn error_handling_smells_are_counted() {
+    let functions = analyze(
+        r#"
+        fn risky(input: Option<&str>, config: Config) -> u32 {
+            let text = input.unwrap();
+            let value: u32 = text.parse().expect("not a number");
+            if value == 0 {
+                panic!("zero");
+            }
+            match value {
+                1 => todo!(),
+                2 => unimplemented!("two"),
+                3 => std::unreachable!(),
+                _ => value,
+            }
+        }
+
+        fn careful(config: Config) -> Option<u32> {
+            let loaded = config.unwrap_config();
+            let fallback = loaded.value.unwrap_or(0);
+            let checked = loaded.expect_field("name", 1);
+            Some(fallback + checked)
+        }
+        "#,
+    );
+    let risky = find(&functions, "risky");
+    assert_eq!(risky.details.unwrap_calls, 1);
+    assert_eq!(risky.details.expect_calls, 1);
+    assert_eq!(risky.details.panic_macros, 4);
+    assert_eq!(risky.details.panic_points(), 6);
+
+    let careful = find(&functions, "careful");
+    assert_eq!(careful.details.unwrap_calls, 0);
+    assert_eq!(careful.details.expect_calls, 0);
+    assert_eq!(careful.details.panic_points(), 0);
+}
+
+#[test]
+fn nested_functions_and_generics_count_only_real_items() {
+    let functions = analyze(
+        r#"
+        fn plain(x: i32) -> i32 {
+            x + 1
+        }
+
+        fn outer<T: Clone>(value: T) -> T {
+            fn helper<U>(u: U) -> U {
+                u
+            }
+            helper(value)
+        }
+        "#,
+    );
+    let plain = find(&functions, "plain");
+    assert_eq!(plain.details.nested_functions, 0);
+    assert_eq!(plain.details.generic_parameters, 0);
+
+    let outer = find(&functions, "outer");
+    assert_eq!(outer.details.nested_functions, 1);
+    assert_eq!(outer.details.generic_parameters, 2);
+
+    let source = std::fs::read_to_string("tests/samples/advanced_features.rs").unwrap();
+    let sample = analyze(&source);
+    let method = find(&sample, "DataProcessor::process_with_macros");
+    assert!(method.details.generic_parameters >= 1);
+    assert_eq!(method.details.nested_functions, 0);
+}
+
+#[test]
+fn method_calls_are_part_of_the_call_chain() {
+    let source = std::fs::read_to_string("tests/samples/advanced_features.rs").unwrap();
+    let functions = analyze(&source);
+    let method = find(&functions, "DataProcessor::process_with_macros");
+
+    let chain = &method.details.function_call_chain;
+    for expected in ["validate_input", "transform_data", "cache_result"] {
+        assert!(
+            chain.iter().any(|name| name == expected),
+            "{expected} missing from {chain:?}"
+        );
+    }
+    assert!(method.details.function_calls >= 3);
+    let position = |name: &str| chain.iter().position(|n| n == name).unwrap();
+    assert!(position("validate_input") < position("transform_data"));
+
+    let chained = analyze("fn run(s: &S) -> R { s.load()?.transform().finish() }");
+    assert_eq!(
+        chained[0].details.function_call_chain,
+        ["load", "transform", "finish"]
+    );
+}
+
+#[test]
+fn call_chain_keeps_qualified_paths() {
+    let functions = analyze(
+        r#"
+        fn sizes<T>() -> usize {
+            let map = HashMap::<u32, u32>::new();
+            std::mem::size_of::<T>() + size_of() + ::core::mem::align_of::<T>() + map.len()
+        }
+        "#,
+    );
+    assert_eq!(
+        functions[0].details.function_call_chain,
+        [
+            "HashMap::new",
+            "std::mem::size_of",
+            "size_of",
+            "::core::mem::align_of",
+            "len"
+        ]
+    );
+}
+
+#[test]
+fn doc_coverage_and_comment_density() {
+    let documented =
+        ComplexityAnalyzer::analyze_file_at(Path::new("tests/fixtures/point.rs")).unwrap();
+    assert_eq!(documented.len(), 7);
+    assert!(documented.iter().all(|f| f.has_doc_comment));
+
+    let sample =
+        ComplexityAnalyzer::analyze_file_at(Path::new("tests/samples/complex_example.rs")).unwrap();
+    assert!(!sample.is_empty());
+    assert!(sample.iter().all(|f| !f.has_doc_comment));
+
+    let functions = analyze(
+        r#"
+        fn commented(x: i32) -> i32 {
+            // first
+            /* second
+               third */
+            x + 1 // trailing
+        }
+        "#,
+    );
+    // 4 of 6 lines carry a comment
+    assert!((functions[0].comment_density - 4.0 / 6.0).abs() < 1e-6);
+
+    let project = ComplexityAnalyzer::analyze_project(
+        Path::new("tests/fixtures"),
+        &AnalyzeOptions::default(),
+        None,
+    )
+    .unwrap();
+    assert_close(project.doc_coverage, 100.0);
+}
+
+#[test]
+fn logical_lines_ignore_comments_and_blank_lines() {
+    let functions = analyze(
+        r#"
+        fn commented(x: i32) -> i32 {
+            // Explain the setup at length.
+            // The next line is important because
+            // reasons that span several lines.
+
+            let y = x * 2;
+
+            /*
+             * More commentary on the result.
+             */
+
+            y + 1
+        }
+
+        fn dense(s: &S) -> R { s.load()?.transform().finish() }
+        "#,
+    );
+    let commented = find(&functions, "commented");
+    assert_eq!(commented.line_count, 13);
+    assert_eq!(commented.logical_lines, 2);
+
+    let dense = find(&functions, "dense");
+    assert_eq!(dense.line_count, 1);
+    assert_eq!(dense.logical_lines, 1);
+}
+
+#[test]
+fn expression_depth_separates_flat_and_nested_code() {
+    let functions = analyze(
+        r#"
+        fn flat(x: i32) -> i32 {
+            let a = qux(x);
+            let b = baz(a);
+            let c = bar(b);
+            let d = foo(c);
+            let e = wrap(d);
+            finish(e)
+        }
+
+        fn nested(x: i32) -> i32 {
+            finish(wrap(foo(bar(baz(qux(x))))))
+        }
+        "#,
+    );
+    let flat = find(&functions, "flat");
+    let nested = find(&functions, "nested");
+
+    // call -> argument path
+    assert_eq!(flat.details.max_expression_depth, 2);
+    // six nested calls plus the innermost path
+    assert_eq!(nested.details.max_expression_depth, 7);
+    assert_eq!(flat.cognitive_complexity, 0);
+    assert_eq!(nested.cognitive_complexity, 1);
+}
+
+#[test]
+fn summary_distribution_and_top_functions() {
+    let source = r#"
+        fn a(x: bool) -> i32 { if x { 1 } else { 0 } }
+        fn b(x: bool) -> i32 { if x { 1 } else { 0 } }
+        fn c() -> i32 { 0 }
+        fn d(x: i32) -> i32 {
+            match x { 1 => 1, 2 => 2, 3 => 3, 4 => 4, 5 => 5, 6 => 6, _ => 0 }
+        }
+        fn e(x: bool) -> i32 { if x { 1 } else { 0 } }
+    "#;
+    let functions = analyze(source);
+    let summary = ComplexitySummary::with_top_n(&functions, 4);
+
+    assert_eq!(summary.total_functions, 5);
+    let percentages: f64 = [
+        ComplexityRating::Low,
+        ComplexityRating::Medium,
+        ComplexityRating::High,
+        ComplexityRating::VeryHigh,
+    ]
+    .iter()
+    .map(|r| summary.distribution.percentage(r))
+    .sum();
+    assert!((percentages - 100.0).abs() < 1e-9);
+    assert_eq!(summary.distribution.medium, 1);
+    assert_eq!(summary.high_complexity_count(), 0);
+
+    // d is the most complex; a, b and e tie and keep their source order
+    let names: Vec<_> = summary
+        .top_functions
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect();
+    assert_eq!(names, ["d", "a", "b", "e"]);
+    assert_eq!(summary.max_cyclomatic, 8);
+
+    let empty = ComplexitySummary::from_functions(&[]);
+    assert_eq!(empty.total_functions, 0);
+    assert_eq!(empty.distribution.percentage(&ComplexityRating::Low), 0.0);
+}
+
+#[test]
+fn lenient_analysis_recovers_from_a_broken_item() {
+    let source = r#"
+/// Says "{" a lot
+fn first(x: i32) -> i32 {
+    let brace = '{';
+    let quote = '\'';
+    let text = r"}}";
+    if x > 0 { x } else { -x }
+}
+
+use std::collections::{HashMap, HashSet};
+
+const ORIGIN: Point = Point { x: 0, y: 0 };
+
+fn broken(x: i32) -> i32 {
+    let y = (;
+    y
+}
+
+struct Marker;
+
+impl Marker {
+    fn third(&self, v: &[u8]) -> usize {
+        v.iter().filter(|b| **b == b'}').count()
+    }
+}
+
+/* outer /* inner } */ still { a comment */
+fn last() {}
+"#;
+    assert!(ComplexityAnalyzer::analyze_file(source).is_err());
+
+    let analysis = ComplexityAnalyzer::analyze_file_lenient(source);
+    let names: Vec<_> = analysis.functions.iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["first", "Marker::third", "last"]);
+    assert_eq!(analysis.unparsed, [(14, 17)]);
+
+    // Line numbers refer to the original file
+    let first = find(&analysis.functions, "first");
+    assert_eq!((first.start_line, first.end_line), (3, 8));
+    assert_eq!(first.cyclomatic_complexity, 2);
+
+    // Files that parse are analyzed as a whole
+    let clean = ComplexityAnalyzer::analyze_file_lenient("fn ok() {}");
+    assert_eq!(clean.functions.len(), 1);
+    assert!(clean.unparsed.is_empty());
+}
+
+#[test]
+fn bound_count_covers_inline_and_where_clause_bounds() {
+    let source = std::fs::read_to_string("tests/samples/advanced_features.rs").unwrap();
+    let functions = analyze(&source);
+    // T: Clone + Debug, U: Send + Sync, V: Default
+    assert_eq!(
+        find(&functions, "complex_generic_function")
+            .details
+            .bound_count,
+        5
+    );
+
+    let functions = analyze(
+        r#"
+        fn inline<'a, T: Clone + 'a, U: Into<String>>(t: &'a T, u: U) where T: Send {}
+        fn unbounded<T>(t: T) {}
+        "#,
+    );
+    // Clone, Into<String> and Send; the 'a lifetime bound is not counted
+    assert_eq!(find(&functions, "inline").details.bound_count, 3);
+    assert_eq!(find(&functions, "unbounded").details.bound_count, 0);
+}
+
+#[test]
+fn diff_reports_renamed_impl_methods_between_scene_versions() {
+    let scene = Path::new(env!("CARGO_MANIFEST_DIR")).join("../e2e_tests/interactive/scene1");
+    let expect = ComplexityAnalyzer::analyze_file_at(&scene.join("expect/main.rs")).unwrap();
+    let edited = ComplexityAnalyzer::analyze_file_at(&scene.join("edited/main.rs")).unwrap();
+
+    let diff = ComplexityAnalyzer::diff(&expect, &edited);
+    assert!(diff.added.is_empty());
+    assert!(diff.removed.is_empty());
+    // `main` only changes its call arguments, which no tracked metric sees
+    assert!(diff.changed.is_empty());
+
+    assert_eq!(diff.renamed.len(), 1);
+    let new = &diff.renamed[0];
+    assert_eq!(new.old_name, "Point3D::new");
+    assert_eq!(new.new_name, "Point::new");
+    assert_eq!(new.before.parameter_count, 3);
+    assert_eq!(new.after.parameter_count, 2);
+
+    let json = serde_json::to_value(&diff).unwrap();
+    assert_eq!(json["renamed"][0]["before"]["parameter_count"], 3);
+}
+
+#[test]
+fn diff_matches_by_name_then_body_hash() {
+    let old = analyze(
+        r#"
+        fn parse_config(x: i32) -> i32 { if x > 0 { 1 } else { 0 } }
+        fn helper(v: &[i32]) -> i32 { v.iter().sum() }
+        fn gone() { cleanup() }
+        "#,
+    );
+    let new = analyze(
+        r#"
+        fn parse_config(x: i32) -> i32 {
+            if x > 0 && x < 10 { 1 } else if x > 100 { 2 } else { 0 }
+        }
+        // Reformatted, but the same tokens
+        fn total(v: &[i32]) -> i32 {
+            v.iter()
+                .sum()
+        }
+        fn fresh() { start() }
+        "#,
+    );
+
+    let diff = ComplexityAnalyzer::diff(&old, &new);
+    assert_eq!(diff.changed.len(), 1);
+    let change = &diff.changed[0];
+    assert_eq!(change.new_name, "parse_config");
+    assert_eq!(change.before.cyclomatic_complexity, 2);
+    assert_eq!(change.after.cyclomatic_complexity, 4);
+    assert_eq!(change.cyclomatic_delta(), 2);
+    assert_eq!(
+        change.to_string(),
+        "`parse_config`: complexity went from 2 to 4, cognitive from 2 to 4"
+    );
+
+    assert_eq!(diff.renamed.len(), 1);
+    assert_eq!(diff.renamed[0].old_name, "helper");
+    assert_eq!(diff.renamed[0].new_name, "total");
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(diff.removed[0].name, "gone");
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].name, "fresh");
+
+    assert!(ComplexityAnalyzer::diff(&new, &new).is_empty());
+}
+
+fn generated_functions() -> Vec<FunctionComplexity> {
+    // Cyclomatic complexity 1..=13 cycling, names alternate between two prefixes
+    let source: String = (0..40)
+        .map(|i| {
+            let branches = "if x > 0 { x += 1; } ".repeat(i % 13);
+            let prefix = if i % 2 == 0 { "parse" } else { "render" };
+            format!("fn {prefix}_{i}(mut x: i32) -> i32 {{ {branches} x }}\n")
+        })
+        .collect();
+    analyze(&source)
+}
+
+#[test]
+fn query_filters_compose_in_any_order() {
+    let functions = generated_functions();
+    let pattern = Regex::new("^parse_").unwrap();
+
+    for min in [
+        ComplexityRating::Low,
+        ComplexityRating::Medium,
+        ComplexityRating::High,
+    ] {
+        let name_first = functions
+            .clone()
+            .filter_name(&pattern)
+            .filter_rating(min.clone());
+        let rating_first = functions
+            .clone()
+            .filter_rating(min.clone())
+            .filter_name(&pattern);
+        let names =
+            |fs: &[FunctionComplexity]| fs.iter().map(|f| f.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&name_first), names(&rating_first));
+        assert!(name_first
+            .iter()
+            .all(|f| f.name.starts_with("parse_") && f.return_complexity >= min));
+    }
+
+    let high = functions.clone().filter_rating(ComplexityRating::High);
+    let by_threshold = functions.clone().filter_min(Metric::Cyclomatic, 11);
+    assert_eq!(high.len(), by_threshold.len());
+}
+
+#[test]
+fn query_sorting_is_stable_and_highest_first() {
+    let functions = generated_functions();
+    let refs: Vec<&FunctionComplexity> = functions.iter().collect();
+
+    for metric in [
+        Metric::Cyclomatic,
+        Metric::Cognitive,
+        Metric::Lines,
+        Metric::Parameters,
+    ] {
+        let sorted = refs.clone().sort_by_metric(metric);
+        for pair in sorted.windows(2) {
+            let (a, b) = (metric.value(pair[0]), metric.value(pair[1]));
+            assert!(a >= b);
+            if a == b {
+                // Ties keep source order
+                assert!(pair[0].start_line < pair[1].start_line);
+            }
+        }
+    }
+
+    let top = refs.clone().top_by_cyclomatic(3);
+    assert_eq!(top.len(), 3);
+    assert!(top.iter().all(|f| f.cyclomatic_complexity == 13));
+    assert_eq!(top[0].name, "parse_12");
+}
+
+#[test]
+fn analysis_cache_skips_unchanged_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("project");
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    for i in 0..5 {
+        std::fs::write(
+            root.join(format!("src/file_{i}.rs")),
+            format!("fn f{i}(x: i32) -> i32 {{ if x > {i} {{ x }} else {{ 0 }} }}"),
+        )
+        .unwrap();
+    }
+    let cache_dir = dir.path().join(AnalysisCache::DEFAULT_DIR);
+    let options = AnalyzeOptions::default();
+
+    let mut cache = AnalysisCache::load(&cache_dir).unwrap();
+    let first = ComplexityAnalyzer::analyze_project(&root, &options, Some(&mut cache)).unwrap();
+    assert_eq!((cache.hits, cache.misses), (0, 5));
+    cache.save(&cache_dir).unwrap();
+
+    // Every lookup of an unchanged project is a hit, so nothing is parsed
+    let mut cache = AnalysisCache::load(&cache_dir).unwrap();
+    let second = ComplexityAnalyzer::analyze_project(&root, &options, Some(&mut cache)).unwrap();
+    assert_eq!((cache.hits, cache.misses), (5, 0));
+    assert_eq!(first.total_functions, second.total_functions);
+    let cached = find(&second.files[Path::new("src/file_3.rs")].functions, "f3");
+    assert_eq!(cached.cyclomatic_complexity, 2);
+    assert_eq!(
+        cached.file.as_deref(),
+        Some(root.join("src/file_3.rs").as_path())
+    );
+    cache.save(&cache_dir).unwrap();
+
+    // Editing one file only invalidates its entry
+    std::fs::write(root.join("src/file_3.rs"), "fn f3() {}").unwrap();
+    let mut cache = AnalysisCache::load(&cache_dir).unwrap();
+    let third = ComplexityAnalyzer::analyze_project(&root, &options, Some(&mut cache)).unwrap();
+    assert_eq!((cache.hits, cache.misses), (4, 1));
+    let edited = find(&third.files[Path::new("src/file_3.rs")].functions, "f3");
+    assert_eq!(edited.cyclomatic_complexity, 1);
+
+    // The stale entry is dropped once unused entries are pruned
+    assert_eq!(cache.len(), 6);
+    cache.prune_unused();
+    assert_eq!(cache.len(), 5);
+}
+
+#[test]
+fn suppression_by_attribute_or_adjacent_comment() {
+    let functions = analyze(
+        r#"
+#[allow(copartner::complexity)]
+fn by_attribute(state: u8) -> u8 {
+    match state { 0 => 1, 1 => 2, 2 => 3, _ => 0 }
+}
+
+/// Documented state machine
+// copartner:ignore-complexity: one arm per opcode
+#[inline]
+fn by_comment(state: u8) -> u8 {
+    match state { 0 => 1, 1 => 2, 2 => 3, _ => 0 }
+}
+
+// copartner:ignore-complexity
+
+fn separated_by_blank_line(state: u8) -> u8 {
+    match state { 0 => 1, 1 => 2, 2 => 3, _ => 0 }
+}
+
+#[allow(dead_code)]
+fn plain(state: u8) -> u8 {
+    match state { 0 => 1, 1 => 2, 2 => 3, _ => 0 }
+}
+"#,
+    );
+    assert!(find(&functions, "by_attribute").suppressed);
+    assert!(find(&functions, "by_comment").suppressed);
+    assert!(!find(&functions, "separated_by_blank_line").suppressed);
+    assert!(!find(&functions, "plain").suppressed);
+
+    // Still analyzed and counted, but left out of the ranking
+    let summary = ComplexitySummary::from_functions(&functions);
+    assert_eq!(summary.total_functions, 4);
+    assert_eq!(summary.suppressed, 2);
+    let top: Vec<_> = summary
+        .top_functions
+        .iter()
+        .map(|f| f.name.as_str())
+        .collect();
+    assert_eq!(top, ["separated_by_blank_line", "plain"]);
+}
+
+#[test]
+fn match_guards_add_branches_and_else_if_does_not_nest() {
+    let functions = analyze(
+        r#"
+        fn guarded(x: i32) -> &'static str {
+            match x {
+                n if n < 0 => "negative",
+                0 => "zero",
+                n if n % 2 == 0 => "even",
+                n if n > 100 => "large",
+                _ => "odd",
+            }
+        }
+
+        fn single(x: i32) -> i32 {
+            if x > 0 { 1 } else { 0 }
+        }
+
+        fn ladder(x: i32) -> i32 {
+            if x > 30 {
+                4
+            } else if x > 20 {
+                3
+            } else if x > 10 {
+                2
+            } else {
+                1
+            }
+        }
+        "#,
+    );
+    let guarded = find(&functions, "guarded");
+    assert_eq!(guarded.details.match_arms, 5);
+    assert_eq!(guarded.details.match_guards, 3);
+    assert_eq!(guarded.cyclomatic_complexity, 1 + 5 + 3);
+
+    let single = find(&functions, "single");
+    let ladder = find(&functions, "ladder");
+    // Each `else if` used to open another level, reporting a depth of 3
+    assert_eq!(ladder.details.max_nesting_depth, 1);
+    assert_eq!(
+        ladder.details.max_nesting_depth,
+        single.details.max_nesting_depth
+    );
+    assert_eq!(ladder.details.if_statements, 3);
+    assert_eq!(ladder.cyclomatic_complexity, 4);
+    // if (+1), two else ifs (+1 each), else (+1)
+    assert_eq!(ladder.cognitive_complexity, 4);
+}
+
+fn ignore_project() -> tempfile::TempDir {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    for file in [
+        "src/lib.rs",
+        "src/generated.rs",
+        "src/vendor/dep.rs",
+        "target/debug/build.rs",
+    ] {
+        std::fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+        std::fs::write(root.join(file), "fn f() {}").unwrap();
+    }
+    std::fs::write(root.join(".gitignore"), "generated.rs\n").unwrap();
+    dir
+}
+
+fn analyzed_files(root: &Path, selection: FileSelection) -> Vec<String> {
+    let options = AnalyzeOptions {
+        selection,
+        ..AnalyzeOptions::default()
+    };
+    let project = ComplexityAnalyzer::analyze_project(root, &options, None).unwrap();
+    project
+        .files
+        .keys()
+        .map(|file| file.to_string_lossy().replace('\\', "/"))
+        .collect()
+}
+
+#[test]
+fn project_walk_respects_gitignore_and_globs() {
+    let dir = ignore_project();
+    let root = dir.path();
+
+    // No git repository needed; target/ is skipped regardless
+    assert_eq!(
+        analyzed_files(root, FileSelection::default()),
+        ["src/lib.rs", "src/vendor/dep.rs"]
+    );
+
+    let no_ignore = FileSelection {
+        respect_ignore: false,
+        ..FileSelection::default()
+    };
+    assert_eq!(
+        analyzed_files(root, no_ignore),
+        ["src/generated.rs", "src/lib.rs", "src/vendor/dep.rs"]
+    );
+
+    let exclude = FileSelection {
+        exclude: vec!["src/vendor".to_string()],
+        ..FileSelection::default()
+    };
+    assert_eq!(analyzed_files(root, exclude), ["src/lib.rs"]);
+
+    let include = FileSelection {
+        include: vec!["**/vendor/*.rs".to_string()],
+        ..FileSelection::default()
+    };
+    assert_eq!(analyzed_files(root, include), ["src/vendor/dep.rs"]);
+
+    let invalid = AnalyzeOptions {
+        selection: FileSelection {
+            exclude: vec!["src/[".to_string()],
+            ..FileSelection::default()
+        },
+        ..AnalyzeOptions::default()
+    };
+    let err = ComplexityAnalyzer::analyze_project(root, &invalid, None).unwrap_err();
+    assert!(err.to_string().contains("`src/[`"), "{err}");
+}
+
+#[test]
+fn target_and_generated_paths_match_whole_components() {
+    for (rel, expected) in [
+        ("target/debug/build.rs", true),
+        ("crates/app/target/debug/build.rs", true),
+        (r"crates\app\target\debug\build.rs", true),
+        (r"target\x.rs", true),
+        ("src/lib.rs", false),
+        (r"src\lib.rs", false),
+        ("target_data/src/lib.rs", false),
+        ("src/target.rs", false),
+        ("src/my_target/lib.rs", false),
+    ] {
+        assert_eq!(in_target_dir(Path::new(rel)), expected, "{rel}");
+    }
+    for (path, expected) in [
+        ("api/client.generated.rs", true),
+        (r"api\client.generated.rs", true),
+        ("client.generated.rs", true),
+        ("src/generated.rs", false),
+        ("src.generated.rs/lib.rs", false),
+        (r"src.generated.rs\lib.rs", false),
+    ] {
+        assert_eq!(is_generated(Path::new(path)), expected, "{path}");
+    }
+}
+
+#[test]
+fn project_walk_skips_target_dirs_only_below_the_root() {
+    let dir = tempfile::tempdir().unwrap();
+    for file in [
+        "target/src/lib.rs",
+        "target/src/nested/target/out.rs",
+        "target_data/src/lib.rs",
+    ] {
+        let path = dir.path().join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "fn f() {}").unwrap();
+    }
+
+    // Pointed at directly, sources inside a target/ directory are analyzed
+    assert_eq!(
+        analyzed_files(&dir.path().join("target/src"), FileSelection::default()),
+        ["lib.rs"]
+    );
+    assert_eq!(
+        analyzed_files(dir.path(), FileSelection::default()),
+        ["target_data/src/lib.rs"]
+    );
+}
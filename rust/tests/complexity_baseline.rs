@@ -0,0 +1,84 @@
+use rust_copartner::complexity_analyzer::baseline::Regression;
+use rust_copartner::complexity_analyzer::lint::LintMetric;
+use rust_copartner::complexity_analyzer::{
+    Baseline, BaselineStatus, ComplexityAnalyzer, FunctionComplexity,
+};
+use std::path::{Path, PathBuf};
+
+fn analyze_at(src: &str, file: &str) -> Vec<FunctionComplexity> {
+    let mut functions = ComplexityAnalyzer::analyze_file(src).unwrap();
+    for func in &mut functions {
+        func.file = Some(PathBuf::from(file));
+    }
+    functions
+}
+
+fn find<'a>(functions: &'a [FunctionComplexity], name: &str) -> &'a FunctionComplexity {
+    functions.iter().find(|f| f.name == name).unwrap()
+}
+
+#[test]
+fn baseline_accepts_recorded_functions_until_they_regress() {
+    let before = analyze_at(
+        "fn legacy(x: i32) -> i32 { if x > 0 { 1 } else { 0 } }\nmod m { fn other() {} }",
+        "/work/project/src/lib.rs",
+    );
+    let root = Path::new("/work/project");
+    let baseline = Baseline::from_functions(&before, root);
+    assert_eq!(baseline.functions[0].file, Path::new("src/lib.rs"));
+    assert_eq!(baseline.functions[0].function, "legacy");
+    assert_eq!(baseline.functions[1].function, "m::other");
+
+    // The same tree checked out elsewhere still matches
+    let moved = analyze_at(
+        "fn legacy(x: i32) -> i32 { if x > 0 { 1 } else { 0 } }\nfn fresh() {}",
+        "/ci/checkout/src/lib.rs",
+    );
+    let ci_root = Path::new("/ci/checkout");
+    assert_eq!(
+        baseline.status(find(&moved, "legacy"), ci_root),
+        BaselineStatus::Accepted
+    );
+    assert_eq!(
+        baseline.status(find(&moved, "fresh"), ci_root),
+        BaselineStatus::New
+    );
+
+    let worse = analyze_at(
+        "fn legacy(x: i32) -> i32 { if x > 0 { 1 } else if x < 0 { 2 } else { 0 } }",
+        "/work/project/src/lib.rs",
+    );
+    assert_eq!(
+        baseline.status(&worse[0], root),
+        BaselineStatus::Regressed(vec![
+            Regression {
+                metric: LintMetric::Cyclomatic,
+                recorded: 2,
+                actual: 3,
+            },
+            Regression {
+                metric: LintMetric::Cognitive,
+                recorded: 2,
+                actual: 3,
+            },
+        ])
+    );
+
+    // `m::other` is no longer among the failing functions
+    let fixed = baseline.fixed(&[&worse[0]], root);
+    assert_eq!(fixed.len(), 1);
+    assert_eq!(fixed[0].function, "m::other");
+}
+
+#[test]
+fn baseline_round_trips_through_json() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("baseline.json");
+    let functions = analyze_at("fn f(a: u8, b: u8) {}", "src/lib.rs");
+    let baseline = Baseline::from_functions(&functions, Path::new(""));
+    baseline.save(&path).unwrap();
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("\"parameters\": 2"), "{content}");
+    assert_eq!(Baseline::load(&path).unwrap(), baseline);
+}
@@ -0,0 +1,723 @@
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn run_cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_complexity_cli"))
+        .args(args)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("complexity_cli should run")
+}
+
+#[test]
+fn ndjson_output_is_one_record_per_line() {
+    let output = run_cli(&["dir", "-p", "tests/samples", "--format", "ndjson"]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let records: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("every stdout line is JSON"))
+        .collect();
+    assert!(records.len() > 5);
+    assert!(records
+        .iter()
+        .all(|r| r["name"].is_string() && r["cyclomatic_complexity"].is_u64()));
+    assert!(records.iter().any(|r| r["name"] == "high_complexity"
+        && Path::new(r["file"].as_str().unwrap()).ends_with("complex_example.rs")));
+
+    // Progress messages moved to stderr
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Analyzing directory"));
+}
+
+#[test]
+fn json_output_is_a_single_array() {
+    let output = run_cli(&[
+        "file",
+        "-p",
+        "tests/samples/complex_example.rs",
+        "--format",
+        "json",
+    ]);
+    assert!(output.status.success());
+
+    let records: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let names: Vec<_> = records
+        .iter()
+        .map(|r| r["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        vec!["simple_function", "medium_complexity", "high_complexity"]
+    );
+}
+
+#[test]
+fn fail_on_limits_exit_with_code_2() {
+    let output = run_cli(&[
+        "file",
+        "-p",
+        "tests/samples/complex_example.rs",
+        "--fail-on-cyclomatic",
+        "10",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("1 complexity limit violation"));
+    assert!(stderr.contains(
+        "tests/samples/complex_example.rs:19: `high_complexity` cyclomatic complexity is 19 (limit 10)"
+    ));
+
+    let output = run_cli(&["dir", "-p", "tests/samples", "--fail-on-rating", "high"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("`high_complexity` is rated High (fail on High)"));
+}
+
+#[test]
+fn fail_on_limits_pass_with_code_0() {
+    let output = run_cli(&[
+        "file",
+        "-p",
+        "tests/samples/complex_example.rs",
+        "--fail-on-cyclomatic",
+        "19",
+        "--fail-on-rating",
+        "very-high",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("violation"));
+}
+
+#[test]
+fn operational_errors_exit_with_code_1() {
+    let output = run_cli(&[
+        "file",
+        "-p",
+        "tests/samples/does_not_exist.rs",
+        "--fail-on-cyclomatic",
+        "10",
+    ]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn exclude_and_no_ignore_flags_select_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(root.join("src/lib.rs"), "fn kept() {}").unwrap();
+    std::fs::write(root.join("src/generated.rs"), "fn generated() {}").unwrap();
+    std::fs::write(root.join(".gitignore"), "generated.rs\n").unwrap();
+    let root = root.to_str().unwrap();
+
+    let names = |args: &[&str]| {
+        let mut all = vec!["dir", "-r", "-p", root, "--format", "ndjson"];
+        all.extend(args);
+        let output = run_cli(&all);
+        assert!(output.status.success());
+        let mut names: Vec<String> = String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let record: serde_json::Value = serde_json::from_str(line).unwrap();
+                record["name"].as_str().unwrap().to_string()
+            })
+            .collect();
+        names.sort();
+        names
+    };
+
+    assert_eq!(names(&[]), ["kept"]);
+    assert_eq!(names(&["--no-ignore"]), ["generated", "kept"]);
+    assert_eq!(
+        names(&["--no-ignore", "--exclude", "**/generated.rs"]),
+        ["kept"]
+    );
+}
+
+#[test]
+fn baseline_ratchet_only_fails_on_regressions() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("project");
+    std::fs::create_dir_all(&root).unwrap();
+    let branchy = |name: &str, branches: usize| {
+        let body: String = (0..branches)
+            .map(|i| format!("    if x == {i} {{ return {i}; }}\n"))
+            .collect();
+        format!("fn {name}(x: i32) -> i32 {{\n{body}    0\n}}\n")
+    };
+    let write = |sources: &[String]| std::fs::write(root.join("lib.rs"), sources.concat()).unwrap();
+    let baseline = dir.path().join("baseline.json");
+    let run = |extra: &[&str]| {
+        let mut args = vec![
+            "dir",
+            "-p",
+            root.to_str().unwrap(),
+            "--fail-on-cyclomatic",
+            "3",
+            "--baseline",
+            baseline.to_str().unwrap(),
+        ];
+        args.extend(extra);
+        run_cli(&args)
+    };
+
+    write(&[
+        branchy("legacy_a", 4),
+        branchy("legacy_b", 4),
+        branchy("small", 1),
+    ]);
+    assert_eq!(run(&["--update-baseline"]).status.code(), Some(0));
+    assert!(baseline.exists());
+    assert_eq!(run(&[]).status.code(), Some(0));
+
+    // Worsen one recorded function and fix the other
+    write(&[
+        branchy("legacy_a", 1),
+        branchy("legacy_b", 6),
+        branchy("small", 1),
+    ]);
+    let output = run(&[]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let failing: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.contains("cyclomatic complexity is"))
+        .collect();
+    assert_eq!(failing.len(), 1, "{stderr}");
+    assert!(failing[0].contains("`legacy_b`"));
+    assert!(failing[0].contains("regressed: cyclomatic complexity 5 -> 7"));
+    assert!(stderr.contains("can be removed from the baseline"));
+    assert!(stderr.contains("lib.rs: `legacy_a`"));
+
+    // A new complex function fails even when everything else is accepted
+    write(&[branchy("legacy_b", 4), branchy("fresh", 4)]);
+    let stderr = String::from_utf8(run(&[]).stderr).unwrap();
+    assert!(stderr.contains("`fresh` cyclomatic complexity is 5 (limit 3) (not in baseline)"));
+    assert!(!stderr.contains("`legacy_b` cyclomatic"));
+}
+
+fn git(repo: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .expect("git should run")
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn diff_against_revision_lists_changed_functions() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path();
+    let sample = std::fs::read_to_string(
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/samples/complex_example.rs"),
+    )
+    .unwrap();
+    std::fs::create_dir(repo.join("src")).unwrap();
+    git(repo, &["init", "-q"]);
+    std::fs::write(repo.join("src/complex_example.rs"), &sample).unwrap();
+    std::fs::write(repo.join("src/old.rs"), "fn gone() {}").unwrap();
+    git(repo, &["add", "."]);
+    git(repo, &["commit", "-qm", "first"]);
+
+    let worse = sample.replace(
+        "    x + 1\n",
+        "    if x > 0 {\n        x + 1\n    } else if x < 0 {\n        x - 1\n    } else {\n        0\n    }\n",
+    );
+    assert_ne!(worse, sample);
+    std::fs::write(repo.join("src/complex_example.rs"), worse).unwrap();
+    git(repo, &["commit", "-qam", "second"]);
+
+    // Deleted and untracked files in the working tree
+    std::fs::remove_file(repo.join("src/old.rs")).unwrap();
+    std::fs::write(repo.join("src/new.rs"), "fn fresh(x: bool) { if x {} }").unwrap();
+
+    let repo_arg = repo.to_str().unwrap();
+    let output = run_cli(&[
+        "diff",
+        "-p",
+        repo_arg,
+        "--against",
+        "HEAD~1",
+        "--format",
+        "ndjson",
+    ]);
+    assert!(output.status.success());
+    let records: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let summary: Vec<(&str, &str, &str, i64)> = records
+        .iter()
+        .map(|r| {
+            (
+                r["file"].as_str().unwrap(),
+                r["change"].as_str().unwrap(),
+                r["name"].as_str().unwrap(),
+                r["cyclomatic_delta"].as_i64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            ("src/complex_example.rs", "changed", "simple_function", 2),
+            ("src/new.rs", "added", "fresh", 2),
+            ("src/old.rs", "removed", "gone", -1),
+        ]
+    );
+    assert_eq!(records[1]["file_status"], "added");
+    assert_eq!(records[2]["file_status"], "deleted");
+
+    // Restricting the comparison to one file
+    let file_arg = repo.join("src/complex_example.rs");
+    let output = run_cli(&[
+        "diff",
+        "-p",
+        file_arg.to_str().unwrap(),
+        "--against",
+        "HEAD~1",
+    ]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("simple_function"));
+    assert!(!stdout.contains("fresh"));
+
+    let output = run_cli(&[
+        "diff",
+        "-p",
+        repo_arg,
+        "--against",
+        "HEAD~1",
+        "--fail-on-increase",
+        "1",
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("src/complex_example.rs: `simple_function` cyclomatic complexity +2"));
+    assert!(!stderr.contains("fresh"));
+
+    let output = run_cli(&[
+        "diff",
+        "-p",
+        repo_arg,
+        "--against",
+        "HEAD~1",
+        "--fail-on-increase",
+        "2",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = run_cli(&["diff", "-p", repo_arg, "--against", "no-such-ref"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("Unknown git revision: no-such-ref"));
+}
+
+fn ndjson_records(args: &[&str]) -> Vec<serde_json::Value> {
+    let output = run_cli(args);
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn dir_sorts_by_each_key_and_limits() {
+    let sorted = |key: &str, extra: &[&str]| {
+        let mut args = vec![
+            "dir",
+            "-p",
+            "tests/samples",
+            "--format",
+            "ndjson",
+            "--sort",
+            key,
+        ];
+        args.extend(extra);
+        ndjson_records(&args)
+    };
+
+    for (key, field) in [
+        ("cyclomatic", "cyclomatic_complexity"),
+        ("cognitive", "cognitive_complexity"),
+        ("lines", "line_count"),
+        ("params", "parameter_count"),
+    ] {
+        let values: Vec<u64> = sorted(key, &[])
+            .iter()
+            .map(|r| r[field].as_u64().unwrap())
+            .collect();
+        assert!(values.len() > 3);
+        assert!(values.windows(2).all(|w| w[0] >= w[1]), "{key}: {values:?}");
+
+        let reversed: Vec<u64> = sorted(key, &["--reverse"])
+            .iter()
+            .map(|r| r[field].as_u64().unwrap())
+            .collect();
+        assert!(
+            reversed.windows(2).all(|w| w[0] <= w[1]),
+            "{key}: {reversed:?}"
+        );
+    }
+
+    let names: Vec<String> = sorted("name", &[])
+        .iter()
+        .map(|r| r["name"].as_str().unwrap().to_string())
+        .collect();
+    assert!(names.windows(2).all(|w| w[0] <= w[1]), "{names:?}");
+
+    let locations: Vec<(String, u64)> = sorted("file", &[])
+        .iter()
+        .map(|r| {
+            (
+                r["file"].as_str().unwrap().to_string(),
+                r["start_line"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert!(locations.windows(2).all(|w| w[0] <= w[1]), "{locations:?}");
+
+    let limited = sorted("cyclomatic", &["--limit", "3"]);
+    assert_eq!(limited.len(), 3);
+    assert_eq!(limited[..], sorted("cyclomatic", &[])[..3]);
+}
+
+#[test]
+fn group_by_file_nests_functions_hottest_file_first() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("project");
+    std::fs::create_dir(&root).unwrap();
+    std::fs::write(
+        root.join("calm.rs"),
+        "fn calm(x: bool) -> u8 { if x { 1 } else { 0 } }",
+    )
+    .unwrap();
+    std::fs::write(
+        root.join("hot.rs"),
+        "fn plain() {}\nfn hot(x: u8) -> u8 { match x { 0 => 1, 1 => 2, 2 => 3, _ => 4 } }",
+    )
+    .unwrap();
+    let export = dir.path().join("export.json");
+    let root_arg = root.to_str().unwrap();
+
+    let groups = ndjson_records(&[
+        "dir",
+        "-p",
+        root_arg,
+        "--group-by-file",
+        "--format",
+        "ndjson",
+        "--export",
+        export.to_str().unwrap(),
+    ]);
+    let files: Vec<&str> = groups.iter().map(|g| g["file"].as_str().unwrap()).collect();
+    assert_eq!(files, ["hot.rs", "calm.rs"]);
+    assert_eq!(groups[0]["function_count"], 2);
+    assert_eq!(groups[0]["max_cyclomatic"], 5);
+    assert_eq!(groups[0]["average_cyclomatic"], 3.0);
+    let names: Vec<&str> = groups[0]["functions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["hot", "plain"]);
+
+    let exported: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&export).unwrap()).unwrap();
+    assert_eq!(exported[0]["file"], "hot.rs");
+    assert_eq!(exported[0]["functions"][0]["name"], "hot");
+    assert_eq!(exported[0]["functions"][0]["cyclomatic_complexity"], 5);
+    assert_eq!(exported[1]["file"], "calm.rs");
+    assert_eq!(exported[1]["functions"].as_array().unwrap().len(), 1);
+
+    let output = run_cli(&["dir", "-p", root_arg, "--group-by-file"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let hot = stdout.find("hot.rs").unwrap();
+    assert!(hot < stdout.find("calm.rs (1 functions").unwrap());
+    assert!(stdout.contains("(2 functions, average cyclomatic 3.00, max 5)"));
+}
+
+#[test]
+fn config_file_values_sit_between_flags_and_defaults() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    std::fs::create_dir(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "fn branchy(x: i32) -> i32 { if x > 0 { 1 } else if x < 0 { 2 } else { 0 } }",
+    )
+    .unwrap();
+    let src = root.join("src");
+    let src = src.to_str().unwrap();
+
+    // Default: no limits
+    assert_eq!(run_cli(&["dir", "-p", src]).status.code(), Some(0));
+
+    // Discovered from the parent directory
+    std::fs::write(
+        root.join("copartner.toml"),
+        "[fail_on]\ncyclomatic = 2\n\n[output]\nformat = \"ndjson\"\n",
+    )
+    .unwrap();
+    let output = run_cli(&["dir", "-p", src]);
+    assert_eq!(output.status.code(), Some(2));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let record: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(record["name"], "branchy");
+
+    // Flags win over the file
+    let output = run_cli(&[
+        "dir",
+        "-p",
+        src,
+        "--fail-on-cyclomatic",
+        "3",
+        "--format",
+        "pretty",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .contains("Function:"));
+
+    // An explicit file replaces the discovered one
+    let other = root.join("other.toml");
+    std::fs::write(&other, "[thresholds]\nlow_max = 1\nmedium_max = 2\n").unwrap();
+    let output = run_cli(&[
+        "dir",
+        "-p",
+        src,
+        "--config",
+        other.to_str().unwrap(),
+        "--format",
+        "json",
+    ]);
+    assert_eq!(output.status.code(), Some(0));
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(records[0]["return_complexity"], "High");
+
+    std::fs::write(
+        root.join("copartner.toml"),
+        "[fail_on]\nrating = \"extreme\"\n",
+    )
+    .unwrap();
+    let output = run_cli(&["dir", "-p", src]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Invalid value \"extreme\" for `fail_on.rating`"),
+        "{stderr}"
+    );
+}
+
+#[test]
+fn config_init_writes_a_commented_default_file() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("copartner.toml");
+    let path_arg = path.to_str().unwrap();
+
+    assert!(run_cli(&["config", "init", path_arg]).status.success());
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(content.contains("[thresholds]"));
+    assert!(content.contains("# medium_max = 10"));
+
+    let output = run_cli(&["config", "init", path_arg]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("--force"));
+    assert!(run_cli(&["config", "init", path_arg, "--force"])
+        .status
+        .success());
+}
+
+#[test]
+fn name_filter_narrows_listing_totals_and_limits() {
+    let sample = "tests/samples/complex_example.rs";
+    let output = run_cli(&["file", "-p", sample, "--name", "^(simple|medium)_"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("simple_function"));
+    assert!(stdout.contains("medium_complexity"));
+    assert!(!stdout.contains("high_complexity"));
+    assert!(stdout.contains("Total functions analyzed: 2"));
+
+    let names: Vec<String> = ndjson_records(&[
+        "dir",
+        "-p",
+        "tests/samples",
+        "--name",
+        "complexity$",
+        "--format",
+        "ndjson",
+    ])
+    .iter()
+    .map(|r| r["name"].as_str().unwrap().to_string())
+    .collect();
+    assert!(names.contains(&"high_complexity".to_string()));
+    assert!(
+        names.iter().all(|name| name.ends_with("complexity")),
+        "{names:?}"
+    );
+
+    // Limits only see the functions in scope
+    let failing = run_cli(&[
+        "file",
+        "-p",
+        sample,
+        "--name",
+        "high",
+        "--fail-on-cyclomatic",
+        "10",
+    ]);
+    assert_eq!(failing.status.code(), Some(2));
+    let passing = run_cli(&[
+        "file",
+        "-p",
+        sample,
+        "--name",
+        "simple",
+        "--fail-on-cyclomatic",
+        "10",
+    ]);
+    assert_eq!(passing.status.code(), Some(0));
+
+    let invalid = run_cli(&["dir", "-p", "tests/samples", "--name", "handle_("]);
+    assert_eq!(invalid.status.code(), Some(1));
+    assert!(String::from_utf8(invalid.stderr)
+        .unwrap()
+        .contains("Invalid --name pattern `handle_(`"));
+}
+
+#[test]
+fn dash_reads_source_from_stdin_and_exports_to_stdout() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_complexity_cli"))
+        .args(["file", "-p", "-", "--format", "json"])
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("complexity_cli should run");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(include_bytes!("samples/complex_example.rs"))
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let records: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let records = records.as_array().unwrap();
+    assert_eq!(records.len(), 3);
+    assert!(records.iter().all(|r| r["file"] == "<stdin>"));
+    let high = records
+        .iter()
+        .find(|r| r["name"] == "high_complexity")
+        .unwrap();
+    assert_eq!(high["start_line"], 19);
+
+    // With no records printed, stdout carries only the SARIF export
+    let output = run_cli(&[
+        "dir",
+        "-p",
+        "tests/samples",
+        "--format",
+        "ndjson",
+        "--limit",
+        "0",
+        "--export-sarif",
+        "-",
+    ]);
+    assert!(output.status.success());
+    let sarif: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(sarif["version"], "2.1.0");
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("written to: stdout"));
+}
+
+#[cfg(unix)]
+#[test]
+fn stats_terminates_on_symlink_loops_and_exports_the_summary() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("tree");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::write(root.join("top.rs"), "fn top() {}").unwrap();
+    std::fs::write(root.join("sub/deep.rs"), "fn deep(x: bool) { if x {} }").unwrap();
+    std::os::unix::fs::symlink("..", root.join("sub/up")).unwrap();
+    let export = dir.path().join("summary.json");
+    let root_arg = root.to_str().unwrap();
+
+    let output = run_cli(&[
+        "stats",
+        "-p",
+        root_arg,
+        "--export",
+        export.to_str().unwrap(),
+    ]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Skipped"), "{stdout}");
+    assert!(stdout.contains("loop"), "{stdout}");
+    let summary: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&export).unwrap()).unwrap();
+    assert_eq!(summary["total_functions"], 2);
+    assert_eq!(summary["max_cyclomatic"], 2);
+
+    let shallow = ndjson_records(&[
+        "stats",
+        "-p",
+        root_arg,
+        "--max-depth",
+        "0",
+        "--format",
+        "ndjson",
+    ]);
+    assert_eq!(shallow[0]["total_functions"], 1);
+
+    let excluded = ndjson_records(&[
+        "stats",
+        "-p",
+        root_arg,
+        "--exclude",
+        "sub",
+        "--format",
+        "ndjson",
+    ]);
+    assert_eq!(excluded[0]["total_functions"], 1);
+    assert_eq!(excluded[0]["top_functions"][0]["name"], "top");
+}
+
+#[test]
+fn types_export_matches_ndjson_records() {
+    let dir = tempfile::tempdir().unwrap();
+    let export = dir.path().join("types.json");
+    let records = ndjson_records(&[
+        "types",
+        "-p",
+        "tests/samples",
+        "--format",
+        "ndjson",
+        "--export",
+        export.to_str().unwrap(),
+    ]);
+    assert!(!records.is_empty());
+    let exported: Vec<serde_json::Value> =
+        serde_json::from_str(&std::fs::read_to_string(&export).unwrap()).unwrap();
+    assert_eq!(exported, records);
+}
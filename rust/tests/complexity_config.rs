@@ -0,0 +1,58 @@
+use rust_copartner::complexity_analyzer::CopartnerConfig;
+use std::path::Path;
+
+fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+#[test]
+fn template_parses_to_the_defaults() {
+    let config: CopartnerConfig = toml::from_str(CopartnerConfig::TEMPLATE).unwrap();
+    assert_eq!(config, CopartnerConfig::default());
+
+    // Uncommenting every setting yields a valid file as well
+    let uncommented: String = CopartnerConfig::TEMPLATE
+        .lines()
+        .map(|line| match line.strip_prefix("# ") {
+            Some(setting) if setting.contains(" = ") => setting,
+            _ => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let config: CopartnerConfig = toml::from_str(&uncommented).unwrap();
+    assert_eq!(config.thresholds.medium_max, Some(10));
+    assert_eq!(config.files.exclude, ["generated/**", "benches/**"]);
+    assert_eq!(config.fail_on.rating.as_deref(), Some("very-high"));
+}
+
+#[test]
+fn discover_finds_the_nearest_file_upwards() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    write(&root.join("src/nested/lib.rs"), "fn f() {}");
+    assert_eq!(CopartnerConfig::discover(&root.join("src/nested")), None);
+
+    write(&root.join(".copartner.toml"), "");
+    let found = CopartnerConfig::discover(&root.join("src/nested/lib.rs")).unwrap();
+    assert_eq!(found.file_name().unwrap(), ".copartner.toml");
+
+    write(&root.join("src/copartner.toml"), "");
+    let found = CopartnerConfig::discover(&root.join("src/nested")).unwrap();
+    assert!(found.ends_with("src/copartner.toml"));
+}
+
+#[test]
+fn malformed_config_errors_name_the_offending_key() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("copartner.toml");
+
+    write(&path, "[thresholds]\nmedium_max = \"ten\"\n");
+    let err = format!("{:#}", CopartnerConfig::load(&path).unwrap_err());
+    assert!(err.contains("Failed to parse config"), "{err}");
+    assert!(err.contains("medium_max"), "{err}");
+
+    write(&path, "[fail_on]\ncyclomatc = 10\n");
+    let err = format!("{:#}", CopartnerConfig::load(&path).unwrap_err());
+    assert!(err.contains("unknown field `cyclomatc`"), "{err}");
+}
@@ -0,0 +1,99 @@
+use rust_copartner::complexity_analyzer::lint::{
+    lint_file, lint_project, LintConfig, LintMetric, Violation,
+};
+
+const SOURCE: &str = r#"
+fn busy(a: i32, b: i32, c: i32, d: i32) -> i32 {
+    if a > 0 {
+        if b > 0 {
+            if c > 0 {
+                return d;
+            }
+        }
+    }
+    0
+}
+
+fn calm(x: i32) -> i32 {
+    x + 1
+}
+"#;
+
+#[test]
+fn only_exceeded_limits_are_reported() {
+    let config = LintConfig {
+        max_cyclomatic: Some(3),
+        max_params: Some(4),
+        max_nesting: Some(2),
+        ..Default::default()
+    };
+    let violations = lint_file(SOURCE, &config).unwrap();
+
+    // busy: cyclomatic 4 > 3 and nesting 3 > 2; its 4 params are within the limit
+    assert_eq!(
+        violations,
+        [
+            Violation {
+                function: "busy".to_string(),
+                metric: LintMetric::Cyclomatic,
+                actual: 4,
+                limit: 3,
+                file: None,
+                line: 2,
+            },
+            Violation {
+                function: "busy".to_string(),
+                metric: LintMetric::Nesting,
+                actual: 3,
+                limit: 2,
+                file: None,
+                line: 2,
+            },
+        ]
+    );
+    assert_eq!(
+        violations[0].to_string(),
+        "line 2: `busy` cyclomatic complexity is 4 (limit 3)"
+    );
+}
+
+#[test]
+fn empty_config_reports_nothing() {
+    assert!(lint_file(SOURCE, &LintConfig::default())
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn lint_project_reports_file_locations_and_rejects_broken_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("lib.rs"), SOURCE).unwrap();
+    let config = LintConfig {
+        max_lines: Some(5),
+        ..Default::default()
+    };
+
+    let violations = lint_project(dir.path(), &config).unwrap();
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].metric, LintMetric::Lines);
+    assert_eq!(
+        violations[0].file.as_deref(),
+        Some(dir.path().join("lib.rs").as_path())
+    );
+
+    std::fs::write(dir.path().join("broken.rs"), "fn broken( {").unwrap();
+    let err = lint_project(dir.path(), &config).unwrap_err();
+    assert!(err.to_string().contains("broken.rs"));
+}
+
+#[test]
+fn suppressed_functions_are_not_linted() {
+    let source = format!("// copartner:ignore-complexity{SOURCE}");
+    let config = LintConfig {
+        max_cyclomatic: Some(3),
+        ..Default::default()
+    };
+    assert_eq!(lint_file(SOURCE, &config).unwrap().len(), 1);
+    // The comment ends up directly above `busy` (SOURCE starts with a newline)
+    assert!(lint_file(&source, &config).unwrap().is_empty());
+}
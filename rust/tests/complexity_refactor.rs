@@ -0,0 +1,61 @@
+use rust_copartner::complexity_analyzer::refactor::{
+    find_candidates, Heuristic, HeuristicConfig, Suggestion,
+};
+use rust_copartner::complexity_analyzer::ComplexityAnalyzer;
+
+#[test]
+fn high_complexity_example_is_a_candidate() {
+    let functions =
+        ComplexityAnalyzer::analyze_file(include_str!("samples/complex_example.rs")).unwrap();
+    let candidates = find_candidates(&functions, &HeuristicConfig::default());
+
+    // simple_function and medium_complexity stay below every limit
+    assert_eq!(candidates.len(), 1);
+    let candidate = &candidates[0];
+    assert_eq!(candidate.function, "high_complexity");
+    assert_eq!(
+        candidate.heuristics,
+        vec![
+            Heuristic::HighCyclomatic,
+            Heuristic::LongFunction,
+            Heuristic::DeepNesting,
+            Heuristic::ManyLoops,
+        ]
+    );
+    assert_eq!(
+        candidate.suggestions,
+        vec![
+            Suggestion::SplitFunction,
+            Suggestion::ReduceNesting,
+            Suggestion::ExtractLoopBody,
+        ]
+    );
+}
+
+#[test]
+fn parameters_and_call_targets_have_their_own_heuristics() {
+    let functions = ComplexityAnalyzer::analyze_file(
+        r#"
+        fn wide(a: u8, b: u8, c: u8) { a(); a(); b(); c(); }
+        // copartner:ignore-complexity
+        fn ignored(a: u8, b: u8, c: u8) {}
+        "#,
+    )
+    .unwrap();
+    let config = HeuristicConfig {
+        max_params: 2,
+        max_call_targets: 2,
+        ..Default::default()
+    };
+    let candidates = find_candidates(&functions, &config);
+
+    assert_eq!(candidates.len(), 1);
+    assert_eq!(
+        candidates[0].heuristics,
+        vec![Heuristic::ManyParameters, Heuristic::ManyCallTargets]
+    );
+    assert_eq!(
+        candidates[0].suggestions,
+        vec![Suggestion::IntroduceStruct, Suggestion::SplitFunction]
+    );
+}
@@ -0,0 +1,46 @@
+use rust_copartner::complexity_analyzer::report::{escape_html, render_html, render_markdown};
+use rust_copartner::complexity_analyzer::{AnalyzeOptions, ComplexityAnalyzer, ProjectComplexity};
+use std::path::Path;
+
+fn fixture_project() -> ProjectComplexity {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/reports/report_project");
+    ComplexityAnalyzer::analyze_project(&root, &AnalyzeOptions::default(), None).unwrap()
+}
+
+#[test]
+fn markdown_report_matches_snapshot() {
+    let markdown = render_markdown(&fixture_project(), 20);
+    let snapshot = include_str!("reports/report_project.md");
+    assert_eq!(markdown, snapshot);
+}
+
+#[test]
+fn markdown_worst_functions_respect_the_limit() {
+    let markdown = render_markdown(&fixture_project(), 1);
+    let worst = markdown
+        .split("## Worst Functions")
+        .nth(1)
+        .unwrap()
+        .split("## Files")
+        .next()
+        .unwrap();
+    let rows: Vec<_> = worst.lines().filter(|l| l.starts_with("| `")).collect();
+    assert_eq!(rows.len(), 1);
+    assert!(rows[0].starts_with("| `route` | `lib.rs:3` | 6 |"));
+}
+
+#[test]
+fn html_report_is_self_contained_and_escaped() {
+    let html = render_html(&fixture_project(), 20);
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<style>"));
+    assert!(!html.contains("<link") && !html.contains("src=\""));
+    assert!(html.contains("<code>&lt;&amp; Wrapper as Display&gt;::fmt</code>"));
+    assert!(!html.contains("<& Wrapper"));
+
+    assert_eq!(
+        escape_html(r#"a<b && "c">'d'"#),
+        "a&lt;b &amp;&amp; &quot;c&quot;&gt;&#39;d&#39;"
+    );
+}
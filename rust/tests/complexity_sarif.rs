@@ -0,0 +1,88 @@
+use rust_copartner::complexity_analyzer::lint::{lint_file, LintConfig};
+use rust_copartner::complexity_analyzer::sarif::{to_sarif, write_sarif};
+use serde_json::Value;
+
+const SOURCE: &str = r#"
+fn busy(a: i32) -> i32 {
+    if a > 0 {
+        if a > 1 {
+            return 2;
+        }
+    }
+    0
+}
+"#;
+
+/// The properties the SARIF 2.1.0 schema marks as required, plus the ones
+/// code scanning needs to place a result
+fn assert_valid_sarif(log: &Value) {
+    assert_eq!(log["version"], "2.1.0");
+    assert!(log["$schema"].as_str().unwrap().contains("sarif-2.1.0"));
+
+    let runs = log["runs"].as_array().expect("runs is an array");
+    assert_eq!(runs.len(), 1);
+    let driver = &runs[0]["tool"]["driver"];
+    assert!(driver["name"].is_string());
+
+    let rules = driver["rules"].as_array().unwrap();
+    for rule in rules {
+        assert!(rule["id"].is_string());
+        assert!(rule["helpUri"].as_str().unwrap().starts_with("https://"));
+    }
+
+    for result in runs[0]["results"].as_array().expect("results is an array") {
+        assert!(result["message"]["text"].is_string());
+        let rule_id = result["ruleId"].as_str().unwrap();
+        assert!(rules.iter().any(|r| r["id"] == rule_id));
+        for location in result["locations"].as_array().unwrap() {
+            let physical = &location["physicalLocation"];
+            assert!(physical["artifactLocation"]["uri"].is_string());
+            assert!(physical["region"]["startLine"].as_u64().unwrap() >= 1);
+        }
+    }
+}
+
+#[test]
+fn violations_become_sarif_results() {
+    let config = LintConfig {
+        max_cyclomatic: Some(2),
+        max_nesting: Some(1),
+        ..Default::default()
+    };
+    let mut violations = lint_file(SOURCE, &config).unwrap();
+    for v in &mut violations {
+        v.file = Some("src\\busy.rs".into());
+    }
+    let log = to_sarif(&violations);
+    assert_valid_sarif(&log);
+
+    let results = log["runs"][0]["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["ruleId"], "copartner/cyclomatic-complexity");
+    assert_eq!(
+        results[0]["message"]["text"],
+        "`busy` cyclomatic complexity is 3 (limit 2)"
+    );
+    assert_eq!(results[1]["ruleId"], "copartner/nesting-depth");
+    let physical = &results[0]["locations"][0]["physicalLocation"];
+    assert_eq!(physical["artifactLocation"]["uri"], "src/busy.rs");
+    assert_eq!(physical["region"]["startLine"], 2);
+}
+
+#[test]
+fn empty_results_still_form_a_valid_log() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("report.sarif");
+    write_sarif(&[], &path).unwrap();
+
+    let log: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_valid_sarif(&log);
+    assert_eq!(log["runs"][0]["results"], Value::Array(Vec::new()));
+    assert_eq!(
+        log["runs"][0]["tool"]["driver"]["rules"]
+            .as_array()
+            .unwrap()
+            .len(),
+        5
+    );
+}
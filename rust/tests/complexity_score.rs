@@ -0,0 +1,79 @@
+use rust_copartner::complexity_analyzer::{
+    ComplexityAnalyzer, ComplexityScore, FunctionComplexity, ScoreWeights,
+};
+
+const SOURCE: &str = r#"
+fn looper(values: &[i32]) -> i32 {
+    let mut sum = 0;
+    for v in values {
+        sum += v;
+    }
+    sum
+}
+
+fn wide(a: i32, b: i32, c: i32, d: i32, e: i32, f: i32) -> i32 {
+    a + b + c + d + e + f
+}
+"#;
+
+fn ranked(weights: &ScoreWeights) -> Vec<String> {
+    let mut functions: Vec<FunctionComplexity> = ComplexityAnalyzer::analyze_file(SOURCE).unwrap();
+    functions.sort_by_key(|f| std::cmp::Reverse(ComplexityScore::compute(f, weights)));
+    functions.into_iter().map(|f| f.name).collect()
+}
+
+#[test]
+fn weights_change_the_ranking() {
+    assert_eq!(ranked(&ScoreWeights::default()), vec!["looper", "wide"]);
+
+    let parameter_heavy = ScoreWeights {
+        parameters: 10,
+        ..Default::default()
+    };
+    assert_eq!(ranked(&parameter_heavy), vec!["wide", "looper"]);
+}
+
+#[test]
+fn cap_is_configurable() {
+    let functions = ComplexityAnalyzer::analyze_file(SOURCE).unwrap();
+    let looper = &functions[0];
+
+    let capped = ComplexityScore::compute(
+        looper,
+        &ScoreWeights {
+            cap: Some(20),
+            ..Default::default()
+        },
+    );
+    assert_eq!(capped.value, 20);
+    assert!(capped.is_capped());
+
+    let uncapped = ComplexityScore::compute(
+        looper,
+        &ScoreWeights {
+            loops: 200,
+            cap: None,
+            ..Default::default()
+        },
+    );
+    assert!(uncapped.value > 200);
+    assert_eq!(uncapped.value, uncapped.raw);
+}
+
+#[test]
+fn weights_load_from_toml_with_defaults_for_missing_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("weights.toml");
+    std::fs::write(&path, "parameters = 10\ncap = 500\n").unwrap();
+
+    let weights = ScoreWeights::load(&path).unwrap();
+    assert_eq!(weights.parameters, 10);
+    assert_eq!(weights.cap, Some(500));
+    assert_eq!(weights.loops, ScoreWeights::default().loops);
+
+    std::fs::write(&path, "cap = 0\n").unwrap();
+    assert_eq!(ScoreWeights::load(&path).unwrap().cap(), None);
+
+    std::fs::write(&path, "loops = \"many\"\n").unwrap();
+    assert!(ScoreWeights::load(&path).is_err());
+}
@@ -0,0 +1,18 @@
+//! Facade over the geometry fixtures
+
+/// Geometry primitives
+pub mod point;
+
+/// The point type, at the crate root
+pub use point::Point;
+
+/// Everything most callers need
+pub mod prelude {
+    /// Constructors and types under their prelude names
+    pub(crate) use crate::point::{origin as zero, Placement};
+    /// Every item of the point module
+    pub use crate::point::*;
+}
+
+/// Only used here, so not re-exported
+use std::fmt;
@@ -26,3 +26,90 @@ pub fn origin() -> Point {
     Point { x: 0, y: 0 }
 }
 
+/// One step along the x axis
+pub fn unit_x() -> Point {
+    Point::new(1, 0)
+}
+
+/// Where a point lies relative to the origin
+pub enum Placement {
+    /// Exactly at the origin
+    Origin = 0,
+    /// On one of the axes
+    Axis(char),
+    /// Inside a quadrant
+    Quadrant {
+        /// Quadrant number, 1 through 4
+        index: u8,
+    },
+}
+
+/// Anything that can be measured from the origin
+pub trait Distance: Sized {
+    /// Unit the distance is expressed in
+    type Unit;
+
+    /// Squared distance from the origin
+    fn squared(&self) -> i32;
+
+    /// Whether the value sits exactly on the origin
+    fn is_origin(&self) -> bool {
+        self.squared() == 0
+    }
+}
+
+/// Largest coordinate value accepted by `Point::new`
+pub const MAX_COORD: i32 = 1 << 16;
+
+/// Point shared by every caller that needs the origin
+pub static ORIGIN: Point = Point { x: 0, y: 0 };
+
+/// Pair of points describing a segment
+pub type Segment = (Point, Point);
+
+/// Build a `Point` from two coordinates
+#[macro_export]
+macro_rules! point {
+    ($x:expr, $y:expr) => {
+        Point { x: $x, y: $y }
+    };
+}
+
+/// Rendering configuration
+pub mod config {
+    /// Tunables for drawing points
+    pub struct Settings {
+        /// Grid spacing in pixels
+        pub grid: u32,
+    }
+
+    /// Built-in configurations
+    pub mod defaults {
+        /// Settings used when nothing is configured
+        pub fn settings() -> super::Settings {
+            super::Settings { grid: 8 }
+        }
+    }
+}
+
+/// Clamp a coordinate into the range `Point::new` accepts
+#[inline]
+fn clamp_coord(v: i32) -> i32 {
+    v.clamp(-MAX_COORD, MAX_COORD)
+}
+
+/// Bounding box of a set of points
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub(crate) struct Bounds {
+    /// Lower-left corner
+    pub min: (i32, i32),
+    /// Upper-right corner
+    pub max: (i32, i32),
+}
+
+/// Growing a `Bounds` to cover more points
+impl Bounds {
+    /// Not implemented yet
+    pub fn extend(&mut self, _point: &Point) {}
+}
@@ -0,0 +1,11 @@
+//! Imports split up by other items
+use std::collections::HashMap;
+
+use std::fmt;
+
+/// Stays out of the uses record
+pub struct Registry {
+    entries: HashMap<String, usize>,
+}
+
+pub use std::io::Write;
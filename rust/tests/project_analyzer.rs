@@ -1,5 +1,10 @@
 use regex::Regex;
-use rust_copartner::analyzer::{analyze_project, AnalyzeConfig};
+use rust_copartner::analyzer::{
+    analyze_file, analyze_project, analyze_project_streaming, analyze_project_with_stats,
+    analyze_source, diff_exports, read_ndjson, write_json_array, write_ndjson, AnalyzeConfig,
+    AnalyzeStats, DeletedFile, OutputRecord, SkippedFile, VectorFields, ANALYZER_VERSION,
+    SCHEMA_VERSION,
+};
 use serde_json::Value;
 use std::fs;
 
@@ -12,20 +17,37 @@ fn emits_valid_ndjson_and_metadata() {
     let fixture = include_str!("fixtures/point.rs");
     fs::write(src_dir.join("point.rs"), fixture).unwrap();
 
-    let cfg = AnalyzeConfig {
-        path: src_dir.clone(),
-        repo_id: "test/repo".to_string(),
-    };
+    let cfg = AnalyzeConfig::new(src_dir.clone(), "test/repo");
     let records = analyze_project(&cfg).expect("analyze should succeed");
 
     assert!(!records.is_empty(), "should produce records");
 
-    // Validate NDJSON by serializing each record
+    // Every line of the NDJSON output is one complete record
+    let mut ndjson = Vec::new();
+    write_ndjson(&records, &mut ndjson).unwrap();
+    let ndjson = String::from_utf8(ndjson).unwrap();
+    assert!(ndjson.ends_with('\n'));
+    let lines: Vec<&str> = ndjson.lines().collect();
+    assert_eq!(lines.len(), records.len());
+    for (line, rec) in lines.iter().zip(&records) {
+        let v: Value = serde_json::from_str(line).unwrap();
+        assert_eq!(v["id"], rec.id.as_str());
+    }
+
+    let mut array = Vec::new();
+    write_json_array(&records, &mut array).unwrap();
+    let array: Value = serde_json::from_slice(&array).unwrap();
+    assert_eq!(array.as_array().unwrap().len(), records.len());
+
+    // Both formats read back to the same records
+    assert_eq!(read_ndjson(&mut ndjson.as_bytes()).unwrap(), records);
+    let mut legacy = Vec::new();
+    write_json_array(&records, &mut legacy).unwrap();
+    assert_eq!(read_ndjson(&mut legacy.as_slice()).unwrap(), records);
+
     let mut kinds = std::collections::HashSet::new();
     let hex64 = Regex::new(r"^[0-9a-f]{64}$").unwrap();
     for rec in &records {
-        let line = serde_json::to_string(rec).unwrap();
-        let _v: Value = serde_json::from_str(&line).unwrap();
         assert!(hex64.is_match(&rec.id), "id must be 64-char hex");
         assert!(
             !rec.vector_fields.signature.is_empty(),
@@ -43,3 +65,1890 @@ fn emits_valid_ndjson_and_metadata() {
     assert!(kinds.contains("impl"));
     assert!(kinds.contains("fn"));
 }
+
+fn point_records() -> Vec<OutputRecord> {
+    analyze_source(
+        std::path::Path::new("src"),
+        "point.rs",
+        include_str!("fixtures/point.rs"),
+        "test/repo",
+    )
+    .unwrap()
+}
+
+fn find<'a>(records: &'a [OutputRecord], qual: &str) -> &'a OutputRecord {
+    records
+        .iter()
+        .find(|r| r.payload.qual_symbol == qual)
+        .unwrap_or_else(|| panic!("missing record for {qual}"))
+}
+
+#[test]
+fn enum_records_describe_their_variants() {
+    let records = point_records();
+    let placement = records.iter().find(|r| r.payload.kind == "enum").unwrap();
+    assert_eq!(placement.payload.qual_symbol, "crate::point::Placement");
+    assert_eq!(
+        placement.vector_fields.doc_comment,
+        "Where a point lies relative to the origin"
+    );
+    let signature = &placement.vector_fields.signature;
+    assert!(signature.starts_with("pub enum Placement"), "{signature}");
+    assert!(signature.contains("Origin = 0"), "{signature}");
+    assert!(signature.contains("Axis (char)"), "{signature}");
+    assert!(signature.contains("index : u8"), "{signature}");
+    assert!(!signature.contains("doc"), "{signature}");
+    assert!(placement.payload.end_line > placement.payload.start_line);
+}
+
+#[test]
+fn traits_and_their_methods_become_records() {
+    let records = point_records();
+    let distance = find(&records, "crate::point::Distance");
+    assert_eq!(distance.payload.kind, "trait");
+    assert_eq!(distance.vector_fields.signature, "trait Distance: Sized");
+    assert!(distance.vector_fields.identifiers.contains("Unit"));
+
+    let squared = find(&records, "crate::point::Distance::squared");
+    assert_eq!(squared.payload.kind, "fn");
+    assert!(squared.vector_fields.code_body.is_empty());
+
+    let is_origin = find(&records, "crate::point::Distance::is_origin");
+    assert_eq!(is_origin.payload.kind, "fn");
+    assert!(is_origin.vector_fields.code_body.contains("squared"));
+}
+
+#[test]
+fn consts_statics_and_type_aliases_become_records() {
+    let records = point_records();
+    let max_coord = find(&records, "crate::point::MAX_COORD");
+    assert_eq!(max_coord.payload.kind, "const");
+    assert_eq!(
+        max_coord.vector_fields.signature,
+        "pub const MAX_COORD: i32"
+    );
+    assert_eq!(max_coord.vector_fields.code_body, "1 << 16");
+
+    let origin = find(&records, "crate::point::ORIGIN");
+    assert_eq!(origin.payload.kind, "static");
+    assert_eq!(origin.vector_fields.signature, "pub static ORIGIN: Point");
+    assert!(origin.vector_fields.code_body.contains("Point"));
+
+    let segment = find(&records, "crate::point::Segment");
+    assert_eq!(segment.payload.kind, "type_alias");
+    assert!(!segment.vector_fields.signature.is_empty());
+    assert!(segment.payload.text.contains("type Segment"));
+}
+
+#[test]
+fn macro_rules_become_macro_records() {
+    let records = point_records();
+    let point = find(&records, "crate::point::point!");
+    assert_eq!(point.payload.kind, "macro");
+    assert_eq!(
+        point.vector_fields.signature,
+        "#[macro_export] macro_rules! point"
+    );
+    assert!(point.payload.text.contains("macro_rules"));
+    assert!(point.vector_fields.identifiers.contains("Point"));
+    assert!(point.payload.end_line > point.payload.start_line);
+}
+
+#[test]
+fn inline_modules_are_walked() {
+    let records = point_records();
+    // Spans start at the first doc comment line
+    let fixture_line = |needle: &str| {
+        include_str!("fixtures/point.rs")
+            .lines()
+            .position(|line| line.contains(needle))
+            .unwrap()
+            + 1
+    };
+    let settings = find(&records, "crate::point::config::Settings");
+    assert_eq!(settings.payload.kind, "struct");
+    assert_eq!(
+        settings.payload.start_line,
+        fixture_line("Tunables for drawing points")
+    );
+    let defaults = find(&records, "crate::point::config::defaults::settings");
+    assert_eq!(defaults.payload.kind, "fn");
+    assert_eq!(
+        defaults.payload.start_line,
+        fixture_line("Settings used when nothing")
+    );
+}
+
+#[test]
+fn methods_keep_their_source_text() {
+    let records = point_records();
+    let new = find(&records, "crate::point::Point::new");
+    assert!(new.payload.text.starts_with("    /// Create a new Point\n"));
+    assert!(new
+        .payload
+        .text
+        .contains("\n    pub fn new(x: i32, y: i32) -> Self {\n        Self { x, y }\n    }"));
+}
+
+#[test]
+fn inner_doc_comments_become_a_module_record() {
+    let records = point_records();
+    let module = find(&records, "crate::point");
+    assert_eq!(module.payload.kind, "module");
+    assert_eq!(module.vector_fields.doc_comment, "Geometry primitives");
+    assert_eq!(module.vector_fields.signature, "crate::point");
+    assert_eq!(module.payload.text, "//! Geometry primitives");
+    assert_eq!((module.payload.start_line, module.payload.end_line), (1, 1));
+}
+
+#[test]
+fn fn_records_list_the_functions_they_call() {
+    let records = point_records();
+    assert!(find(&records, "crate::point::Point::sum")
+        .payload
+        .references
+        .is_empty());
+    assert_eq!(
+        find(&records, "crate::point::unit_x").payload.references,
+        ["Point::new"]
+    );
+    assert_eq!(
+        find(&records, "crate::point::Distance::is_origin")
+            .payload
+            .references,
+        ["squared"]
+    );
+}
+
+#[test]
+fn impl_method_code_body_is_the_block_source() {
+    let records = point_records();
+    assert_eq!(
+        find(&records, "crate::point::Point::new")
+            .vector_fields
+            .code_body,
+        "{ Self { x, y } }"
+    );
+    assert_eq!(
+        find(&records, "crate::point::Point::sum")
+            .vector_fields
+            .code_body,
+        "{ self.x + self.y }"
+    );
+    // Empty bodies keep their braces, like free functions
+    assert_eq!(
+        find(&records, "crate::point::Bounds::extend")
+            .vector_fields
+            .code_body,
+        "{}"
+    );
+}
+
+#[test]
+fn cli_output_parses_line_by_line() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("point.rs"),
+        include_str!("fixtures/point.rs"),
+    )
+    .unwrap();
+    let run = |format: &str| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+            .args([
+                "--path",
+                dir.path().to_str().unwrap(),
+                "--repo-id",
+                "test/repo",
+            ])
+            .args(["--format", format])
+            .output()
+            .expect("project_analyzer should run");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let ndjson = run("ndjson");
+    let records: Vec<Value> = ndjson
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+        .collect();
+    assert!(records.len() > 1);
+    assert!(records.iter().all(|r| r.is_object()));
+
+    let array: Value = serde_json::from_str(&run("json")).unwrap();
+    assert_eq!(array.as_array().unwrap().len(), records.len());
+}
+
+#[test]
+fn struct_text_comes_from_spans() {
+    let dir = tempfile::tempdir().unwrap();
+    let source = r#"// A struct Point used to live here.
+/* struct Point { old: bool } */
+
+/// Cloud of points
+#[derive(Debug, Clone)]
+pub struct PointCloud<T: Clone> {
+    points: Vec<T>,
+}
+
+/// A single point
+pub struct Point<T> {
+    x: T,
+}
+"#;
+    fs::write(dir.path().join("shapes.rs"), source).unwrap();
+    let records = analyze_project(&AnalyzeConfig::new(dir.path(), "test/repo")).unwrap();
+    let find = |qual: &str| {
+        records
+            .iter()
+            .find(|r| r.payload.qual_symbol == qual)
+            .unwrap_or_else(|| panic!("missing record for {qual}"))
+    };
+
+    let cloud = find("crate::shapes::PointCloud");
+    assert_eq!((cloud.payload.start_line, cloud.payload.end_line), (4, 8));
+    assert_eq!(
+        cloud.payload.text,
+        "/// Cloud of points\n#[derive(Debug, Clone)]\npub struct PointCloud<T: Clone> {\n    points: Vec<T>,\n}"
+    );
+
+    let point = find("crate::shapes::Point");
+    assert_eq!((point.payload.start_line, point.payload.end_line), (10, 13));
+    assert!(point
+        .payload
+        .text
+        .starts_with("/// A single point\npub struct Point<T> {"));
+    assert!(!point.payload.text.contains("old"));
+    assert!(
+        records.iter().all(|r| r.payload.kind != "module"),
+        "files without //! docs get no module record"
+    );
+}
+
+#[test]
+fn records_carry_schema_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("point.rs"),
+        include_str!("fixtures/point.rs"),
+    )
+    .unwrap();
+    let analyze = |commit: Option<&str>| {
+        analyze_project(&AnalyzeConfig {
+            commit: commit.map(str::to_string),
+            detect_git: true,
+            ..AnalyzeConfig::new(dir.path(), "test/repo")
+        })
+        .unwrap()
+    };
+
+    // A temp dir outside any repository has no commit to detect
+    let records = analyze(None);
+    let value = serde_json::to_value(&records[0]).unwrap();
+    assert_eq!(value["schema_version"], SCHEMA_VERSION);
+    assert_eq!(value["payload"]["language"], "rust");
+    assert_eq!(value["payload"]["analyzer_version"], ANALYZER_VERSION);
+    assert!(value["payload"]["commit"].is_null());
+
+    let records = analyze(Some("abc123"));
+    assert!(records
+        .iter()
+        .all(|r| r.payload.commit.as_deref() == Some("abc123")));
+}
+
+#[test]
+fn read_ndjson_reports_the_bad_line() {
+    let err = read_ndjson(&mut "\n{\"id\": 1}\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "Invalid record on line 2");
+}
+
+#[test]
+fn cache_reanalyzes_only_changed_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::write(src.join("point.rs"), include_str!("fixtures/point.rs")).unwrap();
+    fs::write(src.join("a.rs"), "/// A\npub fn a() {}\n").unwrap();
+    fs::write(src.join("b.rs"), "/// B\npub fn b() {}\n").unwrap();
+    let cfg = AnalyzeConfig {
+        cache: Some(dir.path().join("cache/records.json")),
+        ..AnalyzeConfig::new(src.clone(), "test/repo")
+    };
+
+    let (first, stats) = analyze_project_with_stats(&cfg).unwrap();
+    assert_eq!(
+        stats,
+        AnalyzeStats {
+            files: 3,
+            cached: 0,
+            analyzed: 3,
+            removed: 0,
+            skipped: Vec::new(),
+            changed: Vec::new(),
+            deleted: Vec::new(),
+        }
+    );
+
+    let (second, stats) = analyze_project_with_stats(&cfg).unwrap();
+    assert_eq!((stats.cached, stats.analyzed), (3, 0));
+    assert_eq!(second, first);
+
+    fs::write(src.join("a.rs"), "/// A, renamed\npub fn a2() {}\n").unwrap();
+    fs::remove_file(src.join("b.rs")).unwrap();
+    let (third, stats) = analyze_project_with_stats(&cfg).unwrap();
+    assert_eq!(
+        stats,
+        AnalyzeStats {
+            files: 2,
+            cached: 1,
+            analyzed: 1,
+            removed: 1,
+            skipped: Vec::new(),
+            changed: Vec::new(),
+            deleted: Vec::new(),
+        }
+    );
+    let changed: Vec<&str> = third
+        .iter()
+        .filter(|r| !first.contains(r))
+        .map(|r| r.payload.qual_symbol.as_str())
+        .collect();
+    assert_eq!(changed, ["crate::a::a2"]);
+    assert!(third.iter().all(|r| r.payload.path != "b.rs"));
+    assert_eq!(
+        third
+            .iter()
+            .filter(|r| r.payload.path == "point.rs")
+            .count(),
+        first
+            .iter()
+            .filter(|r| r.payload.path == "point.rs")
+            .count()
+    );
+}
+
+#[test]
+fn exclusions_match_path_components() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let write = |path: std::path::PathBuf, name: &str| {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, format!("/// {name}\npub fn {name}() {{}}\n")).unwrap();
+    };
+    write(root.join("lib.rs"), "kept");
+    write(
+        root.join("nested")
+            .join("target")
+            .join("debug")
+            .join("build.rs"),
+        "built",
+    );
+    write(root.join("targets.rs"), "targets");
+    write(
+        root.join("api").join("generated").join("types.rs"),
+        "generated",
+    );
+    write(root.join("api").join("client.generated.rs"), "codegen");
+    write(root.join("vendor").join("dep.rs"), "vendored");
+    write(root.join("ignored.rs"), "ignored");
+    fs::write(root.join(".gitignore"), "ignored.rs\n").unwrap();
+
+    let names = |exclude_globs: &[&str], respect_gitignore: bool| {
+        let mut names: Vec<String> = analyze_project(&AnalyzeConfig {
+            exclude_globs: exclude_globs.iter().map(|g| g.to_string()).collect(),
+            respect_gitignore,
+            ..AnalyzeConfig::new(root, "test/repo")
+        })
+        .unwrap()
+        .into_iter()
+        .map(|r| {
+            r.payload
+                .qual_symbol
+                .rsplit("::")
+                .next()
+                .unwrap()
+                .to_string()
+        })
+        .collect();
+        names.sort();
+        names
+    };
+
+    assert_eq!(
+        names(&["**/generated/*.rs", "vendor"], true),
+        ["kept", "targets"]
+    );
+    assert_eq!(
+        names(&[], false),
+        ["generated", "ignored", "kept", "targets", "vendored"]
+    );
+}
+
+#[test]
+fn parallel_output_is_reproducible() {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..200 {
+        let module = dir.path().join(format!("m{}", i % 7));
+        fs::create_dir_all(&module).unwrap();
+        fs::write(
+            module.join(format!("f{i}.rs")),
+            format!(
+                "/// Item {i}\npub struct S{i};\n\n/// Fn {i}\npub fn f{i}() -> S{i} {{ S{i} }}\n"
+            ),
+        )
+        .unwrap();
+    }
+    let run = |jobs: &str| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+            .args([
+                "--path",
+                dir.path().to_str().unwrap(),
+                "--repo-id",
+                "test/repo",
+            ])
+            .args(["--jobs", jobs])
+            .output()
+            .expect("project_analyzer should run");
+        assert!(output.status.success());
+        output.stdout
+    };
+
+    let first = run("8");
+    assert_eq!(String::from_utf8_lossy(&first).lines().count(), 400);
+    assert_eq!(run("8"), first);
+    assert_eq!(run("1"), first);
+}
+
+#[test]
+fn test_code_is_skipped_unless_requested() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir_all(&src).unwrap();
+    fs::create_dir_all(dir.path().join("tests")).unwrap();
+    fs::write(src.join("point.rs"), include_str!("fixtures/point.rs")).unwrap();
+    fs::write(
+        src.join("checks.rs"),
+        r#"/// Real code
+pub fn real() {}
+
+/// Helper kept out of release builds
+#[cfg(test)]
+mod tests {
+    /// Test helper
+    fn helper() {}
+
+    /// A unit test
+    #[test]
+    fn unit() {
+        assert_eq!(1, 1);
+    }
+}
+
+/// Async test outside a test module
+#[tokio::test]
+async fn async_check() {}
+
+/// Methods with a test-only helper
+impl Probe {
+    /// Real method
+    pub fn run(&self) {}
+
+    /// Only compiled for tests
+    #[cfg(test)]
+    fn fake() -> Self {
+        Probe
+    }
+}
+"#,
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("tests").join("it.rs"),
+        "/// Integration test\n#[test]\nfn it() {}\n/// Shared helper\npub fn setup() {}\n",
+    )
+    .unwrap();
+
+    let run = |include_tests: bool| {
+        let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"));
+        cmd.args([
+            "--path",
+            dir.path().to_str().unwrap(),
+            "--repo-id",
+            "test/repo",
+        ]);
+        if include_tests {
+            cmd.arg("--include-tests");
+        }
+        let output = cmd.output().expect("project_analyzer should run");
+        assert!(output.status.success());
+        let mut symbols: Vec<String> = String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let v: Value = serde_json::from_str(line).unwrap();
+                v["payload"]["qual_symbol"].as_str().unwrap().to_string()
+            })
+            .filter(|s| !s.contains("point"))
+            .collect();
+        symbols.sort();
+        symbols
+    };
+
+    assert_eq!(
+        run(false),
+        [
+            "crate::checks::Probe#impl",
+            "crate::checks::Probe::run",
+            "crate::checks::real"
+        ]
+    );
+    assert_eq!(
+        run(true),
+        [
+            "crate::checks::Probe#impl",
+            "crate::checks::Probe::fake",
+            "crate::checks::Probe::run",
+            "crate::checks::async_check",
+            "crate::checks::real",
+            "crate::checks::tests::helper",
+            "crate::checks::tests::unit",
+            "crate::tests::it::it",
+            "crate::tests::it::setup"
+        ]
+    );
+}
+
+#[test]
+fn cfg_not_test_items_are_kept_by_default() {
+    let src = r#"/// Release-only clock
+#[cfg(not(test))]
+pub fn now() -> u64 {
+    1
+}
+
+/// Fake clock for tests
+#[cfg(test)]
+pub fn now() -> u64 {
+    0
+}
+
+/// Built for tests or with the `fake` feature
+#[cfg(any(test, feature = "fake"))]
+pub fn fake() {}
+"#;
+    let records =
+        analyze_source(std::path::Path::new("src"), "clock.rs", src, "test/repo").unwrap();
+    let symbols: Vec<&str> = records
+        .iter()
+        .map(|r| r.payload.qual_symbol.as_str())
+        .collect();
+    assert_eq!(symbols, ["crate::clock::now", "crate::clock::fake"]);
+    assert!(records[0].payload.text.contains("#[cfg(not(test))]"));
+}
+
+#[test]
+fn uses_record_flattens_imports() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("imports.rs"),
+        r#"use std::collections::{HashMap, btree_map::{self, Entry as BEntry}};
+use std::io::{self, Write as _};
+use serde::*;
+pub use crate::model::{Output as Record, VectorFields};
+pub(crate) use ::regex::Regex;
+
+/// Something to attach the imports to
+pub fn run() {}
+"#,
+    )
+    .unwrap();
+    let records = analyze_project(&AnalyzeConfig::new(dir.path(), "test/repo")).unwrap();
+    let uses: Vec<_> = records
+        .iter()
+        .filter(|r| r.payload.kind == "uses")
+        .collect();
+    assert_eq!(uses.len(), 1);
+    let uses = uses[0];
+
+    assert_eq!(uses.payload.qual_symbol, "crate::imports::use");
+    assert_eq!(
+        uses.payload.edges,
+        [
+            "std::collections::HashMap",
+            "std::collections::btree_map",
+            "std::collections::btree_map::Entry",
+            "std::io",
+            "std::io::Write",
+            "serde::*",
+            "crate::model::Output",
+            "crate::model::VectorFields",
+            "::regex::Regex",
+        ]
+    );
+    assert_eq!(
+        uses.payload.reexports,
+        [
+            "crate::model::Output",
+            "crate::model::VectorFields",
+            "::regex::Regex"
+        ]
+    );
+    assert!(uses.vector_fields.code_body.contains("serde::*"));
+    assert_eq!((uses.payload.start_line, uses.payload.end_line), (1, 5));
+
+    // Only the uses record carries edges
+    let value = serde_json::to_value(&records).unwrap();
+    let with_edges = value
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|r| r["payload"].get("edges").is_some())
+        .count();
+    assert_eq!(with_edges, 1);
+}
+
+#[test]
+fn impl_blocks_get_distinct_ids() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("shape.rs"),
+        r#"/// A shape
+pub struct Shape;
+
+/// Constructors
+impl Shape {
+    /// New shape
+    pub fn new() -> Self { Shape }
+}
+
+/// More inherent methods
+impl Shape {
+    /// Area
+    pub fn area(&self) -> f64 { 0.0 }
+}
+
+/// Display
+impl std::fmt::Display for Shape {
+    /// Format for users
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { write!(f, "shape") }
+}
+
+/// Debug
+impl std::fmt::Debug for Shape {
+    /// Format for developers
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result { write!(f, "Shape") }
+}
+
+/// Borrowed view of a shape
+pub struct View<'a, T>(&'a T);
+
+/// Generic methods
+impl<'a, T: Clone> View<'a, T> {
+    /// Copy out the viewed value
+    pub fn get(&self) -> T { self.0.clone() }
+}
+
+/// Conversion from a borrowed shape
+impl<'a> From<&'a Shape> for View<'a, Shape> {
+    /// Wrap the reference
+    fn from(shape: &'a Shape) -> Self { View(shape) }
+}
+
+/// Platform-specific twins share a path
+#[cfg(unix)]
+pub fn separator() -> char { '/' }
+
+/// Platform-specific twins share a path
+#[cfg(windows)]
+pub fn separator() -> char { '\\' }
+"#,
+    )
+    .unwrap();
+    let analyze = || analyze_project(&AnalyzeConfig::new(dir.path(), "test/repo")).unwrap();
+    let records = analyze();
+
+    assert!(records
+        .iter()
+        .any(|r| r.payload.qual_symbol == "crate::shape::View<T>::get"));
+    let ids: std::collections::HashSet<&str> = records.iter().map(|r| r.id.as_str()).collect();
+    assert_eq!(ids.len(), records.len(), "ids must be unique");
+    let quals: Vec<&str> = records
+        .iter()
+        .filter(|r| r.payload.qual_symbol.contains('#'))
+        .map(|r| r.payload.qual_symbol.as_str())
+        .collect();
+    assert_eq!(
+        quals,
+        [
+            "crate::shape::Shape#impl",
+            "crate::shape::Shape#impl-2",
+            "crate::shape::Shape#impl-std::fmt::Display",
+            "crate::shape::Shape#impl-std::fmt::Display::fmt",
+            "crate::shape::Shape#impl-std::fmt::Debug",
+            "crate::shape::Shape#impl-std::fmt::Debug::fmt",
+            "crate::shape::View<T>#impl",
+            "crate::shape::View<Shape>#impl-From<&Shape>",
+            "crate::shape::View<Shape>#impl-From<&Shape>::from",
+            "crate::shape::separator#2",
+        ]
+    );
+
+    let again: Vec<String> = analyze().into_iter().map(|r| r.id).collect();
+    assert_eq!(
+        again,
+        records.iter().map(|r| r.id.clone()).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn oversized_records_are_chunked_with_overlap() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut source = String::from("/// Long\npub fn long() {\n");
+    for i in 0..996 {
+        source.push_str(&format!("    let v{i:03} = {i};\n"));
+    }
+    source.push_str("}\n\n/// Short\npub fn short() {}\n");
+    assert_eq!(source.lines().count(), 1002);
+    fs::write(dir.path().join("big.rs"), &source).unwrap();
+
+    let records = analyze_project(&AnalyzeConfig {
+        max_chars: Some(2000),
+        chunk_overlap: 3,
+        ..AnalyzeConfig::new(dir.path(), "test/repo")
+    })
+    .unwrap();
+
+    let whole = source.lines().take(999).collect::<Vec<_>>().join("\n");
+    let chunks: Vec<_> = records
+        .iter()
+        .filter(|r| {
+            r.payload
+                .qual_symbol
+                .starts_with("crate::big::long::chunk-")
+        })
+        .collect();
+    // 20-char lines: 95 fit in 2000 chars, each chunk adds 92 new ones
+    assert_eq!(chunks.len(), 11);
+    assert!(chunks
+        .iter()
+        .all(|c| c.payload.text.chars().count() <= 2000));
+    let parent = chunks[0].payload.parent_id.clone().unwrap();
+    assert!(chunks
+        .iter()
+        .all(|c| c.payload.parent_id.as_ref() == Some(&parent)));
+    assert!(records.iter().all(|r| r.id != parent));
+
+    let mut rebuilt: Vec<&str> = Vec::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        assert_eq!(
+            chunk.payload.qual_symbol,
+            format!("crate::big::long::chunk-{}", i + 1)
+        );
+        let lines: Vec<&str> = chunk.payload.text.lines().collect();
+        if i > 0 {
+            assert_eq!(lines[..3], rebuilt[rebuilt.len() - 3..], "overlap");
+            assert_eq!(chunk.payload.start_line, chunks[i - 1].payload.end_line - 2);
+        }
+        rebuilt.extend(&lines[if i == 0 { 0 } else { 3 }..]);
+    }
+    assert_eq!(rebuilt.join("\n"), whole);
+    assert_eq!(chunks.last().unwrap().payload.end_line, 999);
+
+    let short = records
+        .iter()
+        .find(|r| r.payload.qual_symbol == "crate::big::short")
+        .unwrap();
+    assert!(short.payload.parent_id.is_none());
+}
+
+#[test]
+fn token_summary_sums_record_estimates() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("point.rs"),
+        include_str!("fixtures/point.rs"),
+    )
+    .unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+        .args([
+            "--path",
+            dir.path().to_str().unwrap(),
+            "--repo-id",
+            "test/repo",
+        ])
+        .args(["--max-tokens-per-record", "40"])
+        .output()
+        .expect("project_analyzer should run");
+    assert!(output.status.success());
+
+    let records: Vec<Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let tokens = |r: &Value| r["payload"]["approx_tokens"].as_u64().unwrap();
+    assert!(records.iter().all(|r| tokens(r) > 0));
+    let total: u64 = records.iter().map(tokens).sum();
+
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains(&format!("{} records, ~{} tokens", records.len(), total)),
+        "{stderr}"
+    );
+    let oversized = records.iter().filter(|r| tokens(r) > 40).count();
+    assert!(oversized > 0);
+    assert_eq!(stderr.matches("(limit 40)").count(), oversized);
+}
+
+#[test]
+fn workspace_members_are_analyzed_as_separate_crates() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    let write = |path: &str, content: &str| {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    };
+    write(
+        "Cargo.toml",
+        "[workspace]\nmembers = [\"crates/*\", \"tools/cli\"]\nexclude = [\"crates/old\"]\n",
+    );
+    write("crates/geo/Cargo.toml", "[package]\nname = \"geo\"\n");
+    write(
+        "crates/geo/src/lib.rs",
+        "/// Library root\npub fn area() {}\n",
+    );
+    write(
+        "crates/geo/src/shapes/mod.rs",
+        "/// A circle\npub struct Circle;\n",
+    );
+    write("crates/old/Cargo.toml", "[package]\nname = \"old\"\n");
+    write("crates/old/src/lib.rs", "/// Excluded\npub fn old() {}\n");
+    write("tools/cli/Cargo.toml", "[package]\nname = \"geo-cli\"\n");
+    write("tools/cli/src/main.rs", "/// Entry point\nfn main() {}\n");
+    write(
+        "tools/cli/vendor/dep/Cargo.toml",
+        "[package]\nname = \"dep\"\n",
+    );
+    write(
+        "tools/cli/vendor/dep/src/lib.rs",
+        "/// Vendored\npub fn dep() {}\n",
+    );
+
+    let analyze = |members: &[&str]| {
+        analyze_project(&AnalyzeConfig {
+            members: members.iter().map(|m| m.to_string()).collect(),
+            ..AnalyzeConfig::new(root, "test/repo")
+        })
+    };
+
+    let records = analyze(&[]).unwrap();
+    let mut seen: Vec<(&str, &str, &str)> = records
+        .iter()
+        .map(|r| {
+            (
+                r.payload.crate_name.as_str(),
+                r.payload.qual_symbol.as_str(),
+                r.payload.path.as_str(),
+            )
+        })
+        .collect();
+    seen.sort();
+    assert_eq!(
+        seen,
+        [
+            ("geo", "crate::area", "crates/geo/src/lib.rs"),
+            (
+                "geo",
+                "crate::shapes::Circle",
+                "crates/geo/src/shapes/mod.rs"
+            ),
+            ("geo-cli", "crate::main", "tools/cli/src/main.rs"),
+        ]
+    );
+
+    let only_cli = analyze(&["geo-cli"]).unwrap();
+    assert!(only_cli.iter().all(|r| r.payload.crate_name == "geo-cli"));
+    assert_eq!(only_cli.len(), 1);
+
+    let err = analyze(&["nope"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Unknown workspace member `nope`, expected one of: geo, geo-cli"
+    );
+}
+
+#[test]
+fn kind_filter_limits_emitted_records() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("point.rs"),
+        include_str!("fixtures/point.rs"),
+    )
+    .unwrap();
+    let run = |kinds: &[&str]| {
+        let mut cmd = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"));
+        cmd.args([
+            "--path",
+            dir.path().to_str().unwrap(),
+            "--repo-id",
+            "test/repo",
+        ]);
+        for kind in kinds {
+            cmd.args(["--kind", kind]);
+        }
+        cmd.output().expect("project_analyzer should run")
+    };
+
+    let output = run(&["fn"]);
+    assert!(output.status.success());
+    let kinds: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let v: Value = serde_json::from_str(line).unwrap();
+            v["payload"]["kind"].as_str().unwrap().to_string()
+        })
+        .collect();
+    // Point::new, Point::sum, origin, unit_x, Distance::squared,
+    // Distance::is_origin, defaults::settings, clamp_coord, Bounds::extend
+    assert_eq!(kinds.len(), 9);
+    assert!(kinds.iter().all(|k| k == "fn"));
+
+    let output = run(&["fn", "function"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("function"), "{stderr}");
+    assert!(stderr.contains("type_alias"), "{stderr}");
+
+    let err = analyze_project(&AnalyzeConfig {
+        kinds: Some(["struct".to_string(), "structs".to_string()].into()),
+        ..AnalyzeConfig::new(dir.path(), "test/repo")
+    })
+    .unwrap_err();
+    assert!(err
+        .to_string()
+        .starts_with("Unknown record kind `structs`, expected one of: module, uses, struct"));
+}
+
+#[test]
+fn streaming_hands_records_over_before_later_files_are_read() {
+    let dir = tempfile::tempdir().unwrap();
+    for i in 0..20 {
+        fs::write(
+            dir.path().join(format!("f{i:02}.rs")),
+            format!("/// Item {i}\npub struct S{i};\n"),
+        )
+        .unwrap();
+    }
+    let cfg = AnalyzeConfig {
+        threads: Some(1),
+        ..AnalyzeConfig::new(dir.path(), "test/repo")
+    };
+
+    // Removing the last file from the first callback only takes effect if
+    // that file has not been read yet
+    let last = dir.path().join("f19.rs");
+    let mut paths = Vec::new();
+    let stats = analyze_project_streaming(&cfg, |record| {
+        if paths.is_empty() {
+            fs::remove_file(&last)?;
+        }
+        paths.push(record.payload.path);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(paths.len(), 19);
+    assert_eq!(paths.first().map(String::as_str), Some("f00.rs"));
+    assert!(!paths.iter().any(|p| p == "f19.rs"));
+    assert_eq!(stats.analyzed, 19);
+    assert_eq!(stats.skipped[0].path, "f19.rs");
+
+    // The Vec-returning wrapper sees the same records in the same order
+    let records = analyze_project(&cfg).unwrap();
+    let wrapped: Vec<String> = records.into_iter().map(|r| r.payload.path).collect();
+    assert_eq!(wrapped, paths);
+
+    let err = analyze_project_streaming(&cfg, |_| anyhow::bail!("sink full")).unwrap_err();
+    assert_eq!(err.to_string(), "sink full");
+}
+
+#[test]
+fn compressed_output_decompresses_to_ndjson() {
+    use flate2::read::GzDecoder;
+    use std::io::Read as _;
+
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir(&src).unwrap();
+    fs::write(src.join("point.rs"), include_str!("fixtures/point.rs")).unwrap();
+    let run = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+            .args(["--path", src.to_str().unwrap(), "--repo-id", "test/repo"])
+            .args(args)
+            .output()
+            .expect("project_analyzer should run");
+        assert!(output.status.success());
+        output.stdout
+    };
+    let plain = String::from_utf8(run(&[])).unwrap();
+
+    // A .gz suffix implies --compress
+    let out = dir.path().join("records.ndjson.gz");
+    run(&["--out", out.to_str().unwrap()]);
+    let mut ndjson = String::new();
+    GzDecoder::new(fs::File::open(&out).unwrap())
+        .read_to_string(&mut ndjson)
+        .unwrap();
+    for line in ndjson.lines() {
+        let record: Value = serde_json::from_str(line).expect("each line is a JSON object");
+        assert!(record.is_object());
+    }
+    assert_eq!(ndjson, plain);
+    let records = read_ndjson(&mut fs::File::open(&out).unwrap()).unwrap();
+    assert_eq!(records.len(), plain.lines().count());
+
+    let piped = run(&["--compress"]);
+    assert_eq!(piped[..2], [0x1f, 0x8b]);
+    let mut ndjson = String::new();
+    GzDecoder::new(piped.as_slice())
+        .read_to_string(&mut ndjson)
+        .unwrap();
+    assert_eq!(ndjson, plain);
+}
+
+#[test]
+fn summary_json_stays_off_stdout() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("point.rs"),
+        include_str!("fixtures/point.rs"),
+    )
+    .unwrap();
+    fs::write(dir.path().join("empty.rs"), "").unwrap();
+    let run = |args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+            .args([
+                "--path",
+                dir.path().to_str().unwrap(),
+                "--repo-id",
+                "test/repo",
+            ])
+            .args(args)
+            .output()
+            .expect("project_analyzer should run");
+        assert!(output.status.success());
+        (
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+
+    let (stdout, stderr) = run(&["--quiet", "--summary-json"]);
+    for line in stdout.lines() {
+        let record: Value = serde_json::from_str(line).expect("each line is a JSON object");
+        assert!(record.is_object());
+    }
+    let records = stdout.lines().count();
+    assert_eq!(stderr.lines().count(), 1, "{stderr}");
+    let summary: Value = serde_json::from_str(&stderr).unwrap();
+    assert_eq!(summary["files"], 2);
+    assert_eq!(summary["analyzed"], 2);
+    assert_eq!(summary["cached"], 0);
+    assert_eq!(summary["skipped"], 0);
+    assert_eq!(summary["records"], records);
+    assert!(summary["approx_tokens"].as_u64().unwrap() > 0);
+    assert!(summary["elapsed_ms"].is_u64());
+
+    let (quiet_stdout, stderr) = run(&["--quiet"]);
+    assert_eq!(quiet_stdout, stdout);
+    assert_eq!(stderr, "");
+
+    let (_, stderr) = run(&[]);
+    assert!(stderr.starts_with(&format!("[project_analyzer] 2 files, {records} records, ~")));
+}
+
+#[test]
+fn broken_files_are_reported_as_skipped() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir(&src).unwrap();
+    fs::write(src.join("point.rs"), include_str!("fixtures/point.rs")).unwrap();
+    fs::write(
+        src.join("broken.rs"),
+        "/// Nightly only\npub fn f() -> ! { do yeet }\n",
+    )
+    .unwrap();
+    let cfg = AnalyzeConfig::new(src.clone(), "test/repo");
+
+    let (records, stats) = analyze_project_with_stats(&cfg).unwrap();
+    assert!(!records.is_empty());
+    assert!(records.iter().all(|r| r.payload.path == "point.rs"));
+    assert_eq!((stats.files, stats.analyzed), (2, 1));
+    assert_eq!(stats.skipped.len(), 1);
+    assert_eq!(stats.skipped[0].path, "broken.rs");
+    assert!(
+        stats.skipped[0].reason.starts_with("Failed to parse"),
+        "{}",
+        stats.skipped[0].reason
+    );
+
+    let errors = dir.path().join("errors.json");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+        .args(["--path", src.to_str().unwrap(), "--repo-id", "test/repo"])
+        .args(["--errors-out", errors.to_str().unwrap()])
+        .output()
+        .expect("project_analyzer should run");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap().lines().count(),
+        records.len()
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("Skipping broken.rs: Failed to parse"),
+        "{stderr}"
+    );
+    assert!(stderr.contains(", 1 skipped in "), "{stderr}");
+    let skipped: Vec<SkippedFile> =
+        serde_json::from_str(&fs::read_to_string(&errors).unwrap()).unwrap();
+    assert_eq!(skipped, stats.skipped);
+}
+
+#[test]
+fn output_order_ignores_file_creation_order() {
+    let names: Vec<String> = (0..30)
+        .map(|i| format!("{}/m{}.rs", ["a", "b/c", "b", "z"][i % 4], i))
+        .collect();
+    let project = |order: &[usize]| {
+        let dir = tempfile::tempdir().unwrap();
+        for &i in order {
+            let file = dir.path().join(&names[i]);
+            fs::create_dir_all(file.parent().unwrap()).unwrap();
+            fs::write(
+                file,
+                format!("/// Fn {i}\npub fn f{i}() {{}}\n\n/// Item {i}\npub struct S{i};\n"),
+            )
+            .unwrap();
+        }
+        dir
+    };
+    let run = |dir: &std::path::Path, args: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+            .args(["--path", dir.to_str().unwrap(), "--repo-id", "test/repo"])
+            .arg("--quiet")
+            .args(args)
+            .output()
+            .expect("project_analyzer should run");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let forward: Vec<usize> = (0..30).collect();
+    let shuffled: Vec<usize> = (0..30).map(|i| (i * 7 + 3) % 30).collect();
+    let first = project(&forward);
+    let second = project(&shuffled);
+    let output = run(first.path(), &[]);
+    assert_eq!(run(second.path(), &[]), output);
+
+    // Unsorted output has the same records, in whatever order the walk gave
+    let unsorted = run(second.path(), &["--no-sort"]);
+    let mut lines: Vec<&str> = unsorted.lines().collect();
+    lines.sort_unstable();
+    let mut expected: Vec<&str> = output.lines().collect();
+    expected.sort_unstable();
+    assert_eq!(lines, expected);
+
+    let keys: Vec<(String, u64)> = output
+        .lines()
+        .map(|line| {
+            let v: Value = serde_json::from_str(line).unwrap();
+            (
+                v["payload"]["path"].as_str().unwrap().to_string(),
+                v["payload"]["start_line"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(keys.len(), 60);
+    assert!(keys.windows(2).all(|w| w[0] < w[1]));
+}
+
+#[test]
+fn single_source_matches_directory_analysis() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir_all(src.join("geometry")).unwrap();
+    let file = src.join("geometry/point.rs");
+    fs::write(&file, include_str!("fixtures/point.rs")).unwrap();
+    let records = analyze_project(&AnalyzeConfig::new(src.clone(), "test/repo")).unwrap();
+    assert!(records
+        .iter()
+        .any(|r| r.payload.qual_symbol == "crate::geometry::point::Point"));
+
+    let source = analyze_source(
+        &src,
+        "geometry/point.rs",
+        include_str!("fixtures/point.rs"),
+        "test/repo",
+    )
+    .unwrap();
+    assert_eq!(source, records);
+    assert_eq!(analyze_file(&src, &file, "test/repo").unwrap(), records);
+
+    let err = analyze_source(&src, "broken.rs", "fn (", "test/repo").unwrap_err();
+    assert_eq!(err.to_string(), "Failed to parse");
+}
+
+#[test]
+fn visibility_and_attributes_are_recorded() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("point.rs"),
+        include_str!("fixtures/point.rs"),
+    )
+    .unwrap();
+    let records = analyze_source(
+        dir.path(),
+        "point.rs",
+        include_str!("fixtures/point.rs"),
+        "test/repo",
+    )
+    .unwrap();
+    let payload = |qual: &str| {
+        &records
+            .iter()
+            .find(|r| r.payload.qual_symbol == qual)
+            .unwrap_or_else(|| panic!("no record for {qual}"))
+            .payload
+    };
+
+    assert_eq!(payload("crate::point::Point").visibility, "pub");
+    assert_eq!(payload("crate::point::Point::new").visibility, "pub");
+    assert_eq!(payload("crate::point::Placement").visibility, "pub");
+    let helper = payload("crate::point::clamp_coord");
+    assert_eq!(helper.visibility, "private");
+    assert_eq!(helper.attributes, ["#[inline]"]);
+    let bounds = payload("crate::point::Bounds");
+    assert_eq!(bounds.visibility, "pub(crate)");
+    assert_eq!(
+        bounds.attributes,
+        [
+            "#[derive(Debug, Clone, Copy)]",
+            "#[cfg_attr(feature = \"serde\", derive(serde::Serialize))]"
+        ]
+    );
+    assert_eq!(payload("crate::point::Point#impl").visibility, "");
+    assert_eq!(payload("crate::point::Distance").visibility, "");
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+        .args([
+            "--path",
+            dir.path().to_str().unwrap(),
+            "--repo-id",
+            "test/repo",
+        ])
+        .arg("--public-only")
+        .output()
+        .expect("project_analyzer should run");
+    assert!(output.status.success());
+    let quals: Vec<String> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let v: Value = serde_json::from_str(line).unwrap();
+            v["payload"]["qual_symbol"].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert!(quals.iter().any(|q| q == "crate::point::Point"));
+    assert!(quals.iter().any(|q| q == "crate::point::Point#impl"));
+    assert!(!quals.iter().any(|q| q == "crate::point::clamp_coord"));
+    assert!(!quals.iter().any(|q| q == "crate::point::Bounds"));
+    assert_eq!(quals.len(), records.len() - 2);
+}
+
+#[test]
+fn byte_ranges_slice_the_text_with_any_line_endings() {
+    let lf = include_str!("fixtures/point.rs").replacen(
+        "\n\n",
+        "\n\nuse std::fmt;\nuse std::io::{self, Write};\n\n",
+        1,
+    );
+    let crlf = lf.replace('\n', "\r\n");
+    let dir = tempfile::tempdir().unwrap();
+    let analyze = |content: &str, max_chars: Option<usize>| {
+        fs::write(dir.path().join("point.rs"), content).unwrap();
+        analyze_project(&AnalyzeConfig {
+            max_chars,
+            chunk_overlap: 1,
+            ..AnalyzeConfig::new(dir.path(), "test/repo")
+        })
+        .unwrap()
+    };
+
+    let unix = analyze(&lf, None);
+    let windows = analyze(&crlf, None);
+    assert!(!unix.is_empty());
+    assert_eq!(unix.len(), windows.len());
+    for (u, w) in unix.iter().zip(&windows) {
+        assert_eq!(
+            (u.payload.start_line, u.payload.end_line),
+            (w.payload.start_line, w.payload.end_line),
+            "{}",
+            u.payload.qual_symbol
+        );
+        assert_eq!(w.payload.text.replace("\r\n", "\n"), u.payload.text);
+    }
+    let uses = unix.iter().find(|r| r.payload.kind == "uses").unwrap();
+    assert_eq!(uses.payload.start_line, 3);
+    assert_eq!(
+        uses.payload.text,
+        "use std::fmt;\nuse std::io::{self, Write};"
+    );
+
+    for (content, max_chars) in [
+        (&lf, None),
+        (&crlf, None),
+        (&lf, Some(60)),
+        (&crlf, Some(60)),
+    ] {
+        let records = analyze(content, max_chars);
+        assert!(max_chars.is_none() || records.iter().any(|r| r.payload.parent_id.is_some()));
+        for r in &records {
+            assert_eq!(
+                &content[r.payload.start_byte..r.payload.end_byte],
+                r.payload.text,
+                "{}",
+                r.payload.qual_symbol
+            );
+            assert!(!r.payload.text.ends_with('\r'), "{}", r.payload.qual_symbol);
+        }
+    }
+}
+
+#[test]
+fn oversized_text_is_truncated_with_a_marker() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("point.rs"),
+        include_str!("fixtures/point.rs"),
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("greeting.rs"),
+        "/// Greets in several languages\npub fn greet() -> [&'static str; 3] {\n    [\"¡Hola, señor!\", \"Grüß Gott\", \"こんにちは世界\"]\n}\n",
+    )
+    .unwrap();
+    let run = |args: &[&str]| -> Vec<Value> {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+            .args([
+                "--path",
+                dir.path().to_str().unwrap(),
+                "--repo-id",
+                "test/repo",
+            ])
+            .args(args)
+            .output()
+            .expect("project_analyzer should run");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .expect("output stays valid UTF-8")
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    };
+
+    let full = run(&[]);
+    let limited = run(&["--max-text-bytes", "60"]);
+    assert_eq!(full.len(), limited.len());
+    let text = |r: &Value, field: &str| r["payload"][field].as_str().unwrap().to_string();
+    let code_body = |r: &Value| {
+        r["vector_fields"]["code_body"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    for (before, after) in full.iter().zip(&limited) {
+        let (text_before, body_before) = (text(before, "text"), code_body(before));
+        if text_before.len() <= 60 && body_before.len() <= 60 {
+            assert_eq!(before, after);
+            assert!(after["payload"].get("truncated").is_none());
+            continue;
+        }
+        assert_eq!(after["payload"]["truncated"], true);
+        for (was, now) in [
+            (text_before, text(after, "text")),
+            (body_before, code_body(after)),
+        ] {
+            if was.len() <= 60 {
+                assert_eq!(now, was);
+                continue;
+            }
+            let (kept, marker) = now.split_once("… [truncated ").unwrap();
+            assert!(kept.len() <= 60 && was.starts_with(kept));
+            assert_eq!(marker, format!("{} bytes]", was.len() - kept.len()));
+        }
+    }
+    let greet = limited
+        .iter()
+        .find(|r| text(r, "qual_symbol") == "crate::greeting::greet")
+        .unwrap();
+    assert!(text(greet, "text").contains("… [truncated"));
+}
+
+#[test]
+fn diff_against_previous_export_emits_only_the_delta() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir(&src).unwrap();
+    let point = include_str!("fixtures/point.rs");
+    fs::write(src.join("point.rs"), point).unwrap();
+    fs::write(src.join("old.rs"), "/// Going away\npub fn old() {}\n").unwrap();
+    let cfg = AnalyzeConfig::new(src.clone(), "test/repo");
+    let first = analyze_project(&cfg).unwrap();
+    let previous = dir.path().join("previous.ndjson");
+    write_ndjson(&first, &mut fs::File::create(&previous).unwrap()).unwrap();
+    assert_eq!(diff_exports(&first, &first), Default::default());
+
+    // Same line count, so only the body of `origin` differs
+    fs::write(
+        src.join("point.rs"),
+        point.replace("    Point { x: 0, y: 0 }\n", "    Point { x: 1, y: 1 }\n"),
+    )
+    .unwrap();
+    fs::remove_file(src.join("old.rs")).unwrap();
+    fs::write(src.join("fresh.rs"), "/// Brand new\npub fn fresh() {}\n").unwrap();
+    let second = analyze_project(&cfg).unwrap();
+
+    let diff = diff_exports(&first, &second);
+    let quals = |records: &[rust_copartner::analyzer::OutputRecord]| -> Vec<String> {
+        records
+            .iter()
+            .map(|r| r.payload.qual_symbol.clone())
+            .collect()
+    };
+    assert_eq!(quals(&diff.changed), ["crate::point::origin"]);
+    assert_eq!(quals(&diff.added), ["crate::fresh::fresh"]);
+    let old_id = first
+        .iter()
+        .find(|r| r.payload.qual_symbol == "crate::old::old")
+        .map(|r| r.id.clone())
+        .unwrap();
+    assert_eq!(diff.removed, std::slice::from_ref(&old_id));
+
+    let deletions = dir.path().join("deleted.json");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+        .args(["--path", src.to_str().unwrap(), "--repo-id", "test/repo"])
+        .args(["--diff-against", previous.to_str().unwrap()])
+        .args(["--deletions-out", deletions.to_str().unwrap()])
+        .output()
+        .expect("project_analyzer should run");
+    assert!(output.status.success());
+    let delta = read_ndjson(&mut output.stdout.as_slice()).unwrap();
+    assert_eq!(
+        quals(&delta),
+        ["crate::fresh::fresh", "crate::point::origin"]
+    );
+    let deleted: Vec<String> =
+        serde_json::from_str(&fs::read_to_string(&deletions).unwrap()).unwrap();
+    assert_eq!(deleted, [old_id]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("1 added, 1 changed, 1 deleted"), "{stderr}");
+
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+        .args(["--path", src.to_str().unwrap(), "--repo-id", "test/repo"])
+        .args(["--diff-against", previous.to_str().unwrap()])
+        .output()
+        .expect("project_analyzer should run");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn moved_records_count_as_changed() {
+    let analyze = |src: &str| {
+        analyze_source(std::path::Path::new("src"), "moves.rs", src, "test/repo").unwrap()
+    };
+    let before =
+        analyze("/// Stays put\npub fn first() {}\n\n/// Moves down\npub fn second() {}\n");
+    let after = analyze(
+        "/// Stays put\npub fn first() {}\n\n// New comment\n/// Moves down\npub fn second() {}\n",
+    );
+    let second = |records: &[rust_copartner::analyzer::OutputRecord]| {
+        records
+            .iter()
+            .find(|r| r.payload.qual_symbol == "crate::moves::second")
+            .unwrap()
+            .clone()
+    };
+    // Same text and vector fields, one line further down
+    assert_eq!(second(&before).vector_fields, second(&after).vector_fields);
+    assert_eq!(second(&before).payload.text, second(&after).payload.text);
+    assert_eq!(second(&after).payload.start_line, 5);
+
+    let diff = diff_exports(&before, &after);
+    let changed: Vec<&str> = diff
+        .changed
+        .iter()
+        .map(|r| r.payload.qual_symbol.as_str())
+        .collect();
+    assert_eq!(changed, ["crate::moves::second"]);
+    assert!(diff.added.is_empty() && diff.removed.is_empty());
+}
+
+#[test]
+fn dedupe_by_content_drops_copies_in_other_files() {
+    let dir = tempfile::tempdir().unwrap();
+    for name in ["a.rs", "b.rs"] {
+        fs::write(dir.path().join(name), include_str!("fixtures/point.rs")).unwrap();
+    }
+    let run = |args: &[&str]| -> Vec<Value> {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+            .args([
+                "--path",
+                dir.path().to_str().unwrap(),
+                "--repo-id",
+                "test/repo",
+            ])
+            .args(args)
+            .output()
+            .expect("project_analyzer should run");
+        assert!(output.status.success());
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    };
+    let fns = |records: &[Value]| -> Vec<(String, String)> {
+        records
+            .iter()
+            .filter(|r| r["payload"]["kind"] == "fn")
+            .map(|r| {
+                (
+                    r["payload"]["path"].as_str().unwrap().to_string(),
+                    r["payload"]["content_hash"].as_str().unwrap().to_string(),
+                )
+            })
+            .collect()
+    };
+
+    let all = fns(&run(&[]));
+    let deduped = fns(&run(&["--dedupe-by-content"]));
+    assert_eq!(all.len(), 18);
+    assert_eq!(deduped.len(), all.len() / 2);
+    assert!(deduped.iter().all(|(path, _)| path == "a.rs"));
+    // Same code at another path hashes the same, different code doesn't
+    let hashes = |path: &str| -> Vec<&String> {
+        all.iter()
+            .filter(|(p, _)| p == path)
+            .map(|(_, h)| h)
+            .collect()
+    };
+    assert_eq!(hashes("a.rs"), hashes("b.rs"));
+    let distinct: std::collections::HashSet<&String> = hashes("a.rs").into_iter().collect();
+    assert_eq!(distinct.len(), 9);
+}
+
+#[test]
+fn pub_use_items_become_reexport_records() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir(&src).unwrap();
+    fs::write(src.join("lib.rs"), include_str!("fixtures/lib.rs")).unwrap();
+    fs::write(src.join("point.rs"), include_str!("fixtures/point.rs")).unwrap();
+    let records = analyze_file(&src, &src.join("lib.rs"), "test/repo").unwrap();
+    let reexports: Vec<(&str, &str, &str, &str)> = records
+        .iter()
+        .filter(|r| r.payload.kind == "reexport")
+        .map(|r| {
+            (
+                r.payload.qual_symbol.as_str(),
+                r.payload.target.as_str(),
+                r.payload.visibility.as_str(),
+                r.vector_fields.signature.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        reexports,
+        [
+            (
+                "crate::Point",
+                "point::Point",
+                "pub",
+                "pub use point::Point"
+            ),
+            // Same line, so ordered by qual_symbol
+            (
+                "crate::prelude::Placement",
+                "crate::point::Placement",
+                "pub(crate)",
+                "pub(crate) use crate::point::Placement"
+            ),
+            (
+                "crate::prelude::zero",
+                "crate::point::origin",
+                "pub(crate)",
+                "pub(crate) use crate::point::origin as zero"
+            ),
+            (
+                "crate::prelude::*",
+                "crate::point::*",
+                "pub",
+                "pub use crate::point::*"
+            ),
+        ]
+    );
+    let point = records
+        .iter()
+        .find(|r| r.payload.qual_symbol == "crate::Point")
+        .unwrap();
+    assert_eq!(
+        point.payload.text,
+        "/// The point type, at the crate root\npub use point::Point;"
+    );
+    assert_eq!(point.vector_fields.code_body, "point::Point");
+    assert_eq!(
+        point.vector_fields.doc_comment,
+        "The point type, at the crate root"
+    );
+    // Private imports still only show up in the uses record
+    let uses = records.iter().find(|r| r.payload.kind == "uses").unwrap();
+    assert!(uses.payload.edges.contains(&"std::fmt".to_string()));
+    assert!(!reexports
+        .iter()
+        .any(|(_, target, _, _)| *target == "std::fmt"));
+}
+
+#[test]
+fn impl_methods_record_their_receiver() {
+    let records = analyze_source(
+        std::path::Path::new("src"),
+        "point.rs",
+        include_str!("fixtures/point.rs"),
+        "test/repo",
+    )
+    .unwrap();
+    let method = |qual: &str| {
+        records
+            .iter()
+            .find(|r| r.payload.qual_symbol == qual)
+            .unwrap_or_else(|| panic!("no record for {qual}"))
+    };
+    let new = method("crate::point::Point::new");
+    assert_eq!(new.payload.receiver, None);
+    let sum = method("crate::point::Point::sum");
+    assert_eq!(sum.payload.receiver.as_deref(), Some("&self"));
+    assert!(sum.vector_fields.signature.contains("(&self)"));
+    let extend = method("crate::point::Bounds::extend");
+    assert_eq!(extend.payload.receiver.as_deref(), Some("&mut self"));
+    // Only methods carry a receiver
+    assert!(records
+        .iter()
+        .filter(|r| r.payload.kind != "fn")
+        .all(|r| r.payload.receiver.is_none()));
+}
+
+#[test]
+fn vector_fields_render_embedding_templates() {
+    let fields = VectorFields {
+        signature: "fn sum (&self) -> i32".to_string(),
+        identifiers: "sum x y".to_string(),
+        code_body: "{ self.x + self.y }".to_string(),
+        doc_comment: "Adds up the coordinates".to_string(),
+    };
+    assert_eq!(
+        fields.render("{signature}\n{identifiers}\n{code_body}\n{doc_comment}"),
+        "fn sum (&self) -> i32\nsum x y\n{ self.x + self.y }\nAdds up the coordinates"
+    );
+    assert_eq!(
+        fields.render("// {doc_comment}\n{signature} {{...}}"),
+        "// Adds up the coordinates\nfn sum (&self) -> i32 {...}"
+    );
+    assert!(VectorFields::validate_template("{doc_comment}: {signature}").is_ok());
+    let err = VectorFields::validate_template("{signature} {body}").unwrap_err();
+    assert!(err.to_string().contains("{body}"), "{err}");
+    assert!(VectorFields::validate_template("{signature").is_err());
+    assert!(VectorFields::validate_template("signature}").is_err());
+}
+
+#[test]
+fn embedding_template_adds_embedding_text_to_records() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    fs::create_dir(&src).unwrap();
+    fs::write(src.join("point.rs"), include_str!("fixtures/point.rs")).unwrap();
+    let mut cfg = AnalyzeConfig::new(src.clone(), "test/repo");
+    let plain = analyze_project(&cfg).unwrap();
+    assert!(plain.iter().all(|r| r.embedding_text.is_none()));
+    let json = serde_json::to_value(&plain[0]).unwrap();
+    assert!(json.get("embedding_text").is_none());
+
+    cfg.embedding_template = Some("{doc_comment}\n{signature}".to_string());
+    let records = analyze_project(&cfg).unwrap();
+    for r in &records {
+        let fields = &r.vector_fields;
+        let expected = format!("{}\n{}", fields.doc_comment, fields.signature);
+        assert_eq!(r.embedding_text.as_deref(), Some(expected.as_str()));
+    }
+    let sum = records
+        .iter()
+        .find(|r| r.payload.qual_symbol == "crate::point::Point::sum")
+        .unwrap();
+    assert_eq!(
+        sum.embedding_text.as_deref(),
+        Some("Sum coordinates\nfn sum (&self) -> i32")
+    );
+
+    // Rejected before any file is read
+    cfg.embedding_template = Some("{signature} {body}".to_string());
+    cfg.path = dir.path().join("missing");
+    let err = analyze_project(&cfg).unwrap_err();
+    assert!(
+        err.to_string().contains("Unknown placeholder {body}"),
+        "{err}"
+    );
+}
+
+fn git(repo: &std::path::Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .expect("git should run")
+        .status;
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+#[test]
+fn changed_since_analyzes_only_files_changed_in_git() {
+    let dir = tempfile::tempdir().unwrap();
+    let repo = dir.path();
+    let src = repo.join("src");
+    fs::create_dir(&src).unwrap();
+    let point = include_str!("fixtures/point.rs");
+    fs::write(src.join("point.rs"), point).unwrap();
+    fs::write(src.join("greeting.rs"), "/// Says hi\npub fn greet() {}\n").unwrap();
+    fs::write(
+        src.join("old.rs"),
+        "//! Going away\nuse std::fmt;\npub fn old() {}\n",
+    )
+    .unwrap();
+    git(repo, &["init", "-q"]);
+    git(repo, &["add", "."]);
+    git(repo, &["commit", "-qm", "first"]);
+
+    let mut cfg = AnalyzeConfig::new(src.clone(), "test/repo");
+    let full = analyze_project(&cfg).unwrap();
+    let previous = repo.join("previous.ndjson");
+    write_ndjson(&full, &mut fs::File::create(&previous).unwrap()).unwrap();
+    let ids_in = |path: &str| -> Vec<String> {
+        full.iter()
+            .filter(|r| r.payload.path == path)
+            .map(|r| r.id.clone())
+            .collect()
+    };
+
+    fs::write(
+        src.join("point.rs"),
+        point.replace("    Point { x: 0, y: 0 }\n", "    Point { x: 1, y: 1 }\n"),
+    )
+    .unwrap();
+    fs::remove_file(src.join("old.rs")).unwrap();
+    cfg.changed_since = Some("HEAD".to_string());
+    let (records, stats) = analyze_project_with_stats(&cfg).unwrap();
+    assert!(!records.is_empty());
+    assert!(records.iter().all(|r| r.payload.path == "point.rs"));
+    assert_eq!(records.len(), ids_in("point.rs").len());
+    assert_eq!(stats.files, 1);
+    assert_eq!(stats.changed, ["point.rs"]);
+    // The module and uses records are all the path alone gives away
+    let mut old_ids = ids_in("old.rs");
+    assert_eq!(old_ids.len(), 3);
+    let mut expected: Vec<String> = full
+        .iter()
+        .filter(|r| r.payload.path == "old.rs" && r.payload.kind != "fn")
+        .map(|r| r.id.clone())
+        .collect();
+    expected.sort();
+    let [DeletedFile { path, ids }] = stats.deleted.as_slice() else {
+        panic!("expected one deleted file: {:?}", stats.deleted);
+    };
+    assert_eq!(path, "old.rs");
+    let mut ids = ids.clone();
+    ids.sort();
+    assert_eq!(ids, expected);
+
+    // Without an old export only the file-level ids can be listed ...
+    let deletions = repo.join("deleted.json");
+    let run = |extra: &[&str]| {
+        let output = std::process::Command::new(env!("CARGO_BIN_EXE_project_analyzer"))
+            .args(["--path", src.to_str().unwrap(), "--repo-id", "test/repo"])
+            .args(["--changed-since", "HEAD", "--quiet"])
+            .args(["--deletions-out", deletions.to_str().unwrap()])
+            .args(extra)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        let mut listed: Vec<String> =
+            serde_json::from_str(&fs::read_to_string(&deletions).unwrap()).unwrap();
+        listed.sort();
+        (String::from_utf8(output.stdout).unwrap(), listed)
+    };
+    let (stdout, listed) = run(&[]);
+    assert_eq!(listed, expected);
+    assert!(stdout
+        .lines()
+        .all(|line| line.contains("\"path\":\"point.rs\"")));
+
+    // ... while the previous export has every one, and unchanged files keep theirs
+    let (stdout, listed) = run(&["--diff-against", previous.to_str().unwrap()]);
+    old_ids.sort();
+    assert_eq!(listed, old_ids);
+    let emitted: Vec<&str> = stdout.lines().collect();
+    assert_eq!(emitted.len(), 1, "only `origin` changed: {stdout}");
+    assert!(emitted[0].contains("crate::point::origin"));
+}
+
+#[test]
+fn module_record_spans_only_the_inner_doc_block() {
+    let source = "#![allow(dead_code)]\n//! Shapes\n//! and more\n#![cfg_attr(test, allow(unused))]\n\npub fn f() {}\n";
+    let records = analyze_source(
+        std::path::Path::new("src"),
+        "shapes.rs",
+        source,
+        "test/repo",
+    )
+    .unwrap();
+    let module = records.iter().find(|r| r.payload.kind == "module").unwrap();
+    assert_eq!((module.payload.start_line, module.payload.end_line), (2, 3));
+    assert_eq!(module.payload.text, "//! Shapes\n//! and more");
+    assert_eq!(
+        &source[module.payload.start_byte..module.payload.end_byte],
+        module.payload.text
+    );
+}
+
+#[test]
+fn uses_record_text_holds_only_use_items() {
+    let source = include_str!("fixtures/scattered_uses.rs");
+    let records = analyze_source(
+        std::path::Path::new("src"),
+        "registry.rs",
+        source,
+        "test/repo",
+    )
+    .unwrap();
+    let uses = records.iter().find(|r| r.payload.kind == "uses").unwrap();
+    // A blank line between imports is kept, the struct after them is not
+    assert_eq!(
+        uses.payload.text,
+        "use std::collections::HashMap;\n\nuse std::fmt;\npub use std::io::Write;"
+    );
+    assert!(!uses.payload.text.contains("Registry"));
+    assert_eq!((uses.payload.start_line, uses.payload.end_line), (2, 11));
+    assert_eq!(
+        uses.payload.edges,
+        ["std::collections::HashMap", "std::fmt", "std::io::Write"]
+    );
+}
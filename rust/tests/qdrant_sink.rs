@@ -0,0 +1,212 @@
+use rust_copartner::analyzer::sink::point_id;
+use rust_copartner::analyzer::{analyze_source, OutputRecord, QdrantSink, SinkStats};
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+use wiremock::matchers::{header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const POINTS_PATH: &str = "/collections/code_items/points";
+
+fn point_records() -> Vec<OutputRecord> {
+    analyze_source(
+        Path::new("src"),
+        "point.rs",
+        include_str!("fixtures/point.rs"),
+        "test/repo",
+    )
+    .unwrap()
+}
+
+/// Pushes every record through a sink for `server` on a blocking thread, as
+/// the sink uses a blocking client
+async fn upload(
+    server: &MockServer,
+    records: Vec<OutputRecord>,
+    configure: impl FnOnce(&mut QdrantSink) + Send + 'static,
+) -> SinkStats {
+    let url = server.uri();
+    tokio::task::spawn_blocking(move || {
+        let mut sink = QdrantSink::new(&url, "code_items").unwrap();
+        sink.retry_delay = Duration::from_millis(1);
+        configure(&mut sink);
+        for record in &records {
+            sink.push(record);
+        }
+        sink.finish()
+    })
+    .await
+    .unwrap()
+}
+
+async fn sent_batches(server: &MockServer) -> Vec<Value> {
+    server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .map(|request| request.body_json::<Value>().unwrap())
+        .collect()
+}
+
+fn ok() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(json!({"status": "ok", "result": {}}))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn upserts_records_in_batches() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path(POINTS_PATH))
+        .and(query_param("wait", "true"))
+        .and(header("api-key", "secret"))
+        .respond_with(ok())
+        .expect(3)
+        .mount(&server)
+        .await;
+    let records: Vec<OutputRecord> = point_records().into_iter().take(7).collect();
+    assert_eq!(records.len(), 7);
+    let stats = upload(&server, records.clone(), |sink| {
+        sink.batch_size = 3;
+        sink.api_key = Some("secret".to_string());
+    })
+    .await;
+    assert_eq!(
+        stats,
+        SinkStats {
+            upserted: 7,
+            failed: 0
+        }
+    );
+
+    let batches = sent_batches(&server).await;
+    let sizes: Vec<usize> = batches
+        .iter()
+        .map(|b| b["points"].as_array().unwrap().len())
+        .collect();
+    assert_eq!(sizes, [3, 3, 1]);
+    let points: Vec<&Value> = batches
+        .iter()
+        .flat_map(|b| b["points"].as_array().unwrap())
+        .collect();
+    for (point, record) in points.iter().zip(&records) {
+        assert_eq!(point["id"], point_id(&record.id));
+        assert_eq!(point["payload"]["record_id"], record.id);
+        assert_eq!(
+            point["payload"]["meta"]["qual_symbol"],
+            record.payload.qual_symbol
+        );
+        assert_eq!(point["payload"]["meta"]["kind"], record.payload.kind);
+        assert_eq!(
+            point["payload"]["vector_fields"]["signature"],
+            record.vector_fields.signature
+        );
+        // Left to server-side embedding unless placeholders are asked for
+        assert!(point.get("vector").is_none());
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn placeholder_vectors_are_empty_named_vectors() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path(POINTS_PATH))
+        .respond_with(ok())
+        .mount(&server)
+        .await;
+    let records: Vec<OutputRecord> = point_records().into_iter().take(1).collect();
+    upload(&server, records, |sink| sink.placeholder_vectors = true).await;
+    let batches = sent_batches(&server).await;
+    assert_eq!(
+        batches[0]["points"][0]["vector"],
+        json!({"signature": [], "identifiers": [], "code_body": [], "doc_comment": []})
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn retries_a_batch_after_a_503() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path(POINTS_PATH))
+        .respond_with(ResponseTemplate::new(503))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path(POINTS_PATH))
+        .respond_with(ok())
+        .mount(&server)
+        .await;
+    let records: Vec<OutputRecord> = point_records().into_iter().take(2).collect();
+    let stats = upload(&server, records, |_| {}).await;
+    assert_eq!(
+        stats,
+        SinkStats {
+            upserted: 2,
+            failed: 0
+        }
+    );
+    // The same batch, sent twice
+    let batches = sent_batches(&server).await;
+    assert_eq!(batches.len(), 2);
+    assert_eq!(batches[0], batches[1]);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batches_failing_every_retry_are_counted() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path(POINTS_PATH))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(3)
+        .mount(&server)
+        .await;
+    let records: Vec<OutputRecord> = point_records().into_iter().take(2).collect();
+    let stats = upload(&server, records, |sink| sink.max_retries = 2).await;
+    assert_eq!(
+        stats,
+        SinkStats {
+            upserted: 0,
+            failed: 2
+        }
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn client_errors_are_not_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path(POINTS_PATH))
+        .respond_with(ResponseTemplate::new(400))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let records: Vec<OutputRecord> = point_records().into_iter().take(1).collect();
+    let stats = upload(&server, records, |_| {}).await;
+    assert_eq!(stats.failed, 1);
+}
+
+#[test]
+fn point_ids_are_uuids_from_the_record_id() {
+    let id = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+    assert_eq!(point_id(id), "01234567-89ab-cdef-0123-456789abcdef");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn many_retries_do_not_overflow_the_backoff() {
+    let server = MockServer::start().await;
+    Mock::given(method("PUT"))
+        .and(path(POINTS_PATH))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(41)
+        .mount(&server)
+        .await;
+    let records: Vec<OutputRecord> = point_records().into_iter().take(1).collect();
+    let stats = upload(&server, records, |sink| {
+        sink.max_retries = 40;
+        sink.retry_delay = Duration::ZERO;
+    })
+    .await;
+    assert_eq!(stats.failed, 1);
+}
@@ -0,0 +1,23 @@
+// Report fixture: one busy function and one trivial one
+
+pub fn route(method: &str, path: &str) -> u16 {
+    if method == "GET" {
+        if path == "/" {
+            return 200;
+        }
+        for segment in path.split('/') {
+            if segment.is_empty() {
+                continue;
+            }
+        }
+        404
+    } else if method == "POST" {
+        201
+    } else {
+        405
+    }
+}
+
+pub fn version() -> &'static str {
+    "1.0"
+}
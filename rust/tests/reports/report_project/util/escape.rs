@@ -0,0 +1,15 @@
+// Report fixture: a method whose qualified name contains `<` and `&`
+
+use std::fmt;
+
+pub struct Wrapper(pub u8);
+
+impl fmt::Display for &Wrapper {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 > 9 {
+            write!(f, "big")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
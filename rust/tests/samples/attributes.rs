@@ -0,0 +1,23 @@
+// Test sample file: functions carrying attribute macros
+
+/// Documented, but doc comments are not reported as attributes
+#[inline]
+fn small(x: u32) -> u32 {
+    x + 1
+}
+
+#[tokio::main]
+async fn main() {
+    println!("{}", small(1));
+}
+
+#[route(GET, "/users")]
+#[inline(always)]
+fn list_users() -> Vec<String> {
+    Vec::new()
+}
+
+#[must_use = "check the result"]
+fn plain() -> bool {
+    true
+}
@@ -0,0 +1,31 @@
+// Test sample file: early exits written with let-else
+
+use std::collections::HashMap;
+
+fn parse_pair(input: &str) -> Option<(i32, i32)> {
+    let Some((left, right)) = input.split_once(',') else {
+        return None;
+    };
+    let Ok(x) = left.trim().parse::<i32>() else {
+        return None;
+    };
+    let Ok(y) = right.trim().parse::<i32>() else {
+        return None;
+    };
+    Some((x, y))
+}
+
+fn lookup(map: &HashMap<String, String>, key: &str) -> String {
+    let Some(value) = map.get(key) else {
+        if key.is_empty() {
+            return String::from("<empty>");
+        }
+        return String::new();
+    };
+    value.clone()
+}
+
+fn plain_let(input: &str) -> usize {
+    let len = input.len();
+    len
+}
@@ -0,0 +1,30 @@
+// Test sample file: same-named functions in nested inline modules
+
+fn handle() {}
+
+mod handlers {
+    pub fn handle(code: u16) -> bool {
+        code < 400
+    }
+
+    pub mod admin {
+        pub fn handle(code: u16) -> bool {
+            if code == 403 {
+                return false;
+            }
+            code < 400
+        }
+
+        pub struct Session;
+
+        impl Session {
+            pub fn handle(&self) {}
+        }
+
+        #[cfg(test)]
+        mod tests {
+            #[test]
+            fn handle() {}
+        }
+    }
+}
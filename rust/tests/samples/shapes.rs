@@ -0,0 +1,34 @@
+// Test sample file: several types defining methods with the same name
+
+use std::fmt;
+
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+struct Circle {
+    center: Point,
+    radius: u32,
+}
+
+impl Point {
+    fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl Circle {
+    fn new(center: Point, radius: u32) -> Self {
+        if radius == 0 {
+            return Self { center, radius: 1 };
+        }
+        Self { center, radius }
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
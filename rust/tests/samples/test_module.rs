@@ -0,0 +1,38 @@
+// Test sample file: test-only code
+
+#[cfg(test)]
+mod tests {
+    fn build_fixture(n: usize) -> Vec<usize> {
+        (0..n).collect()
+    }
+
+    #[test]
+    fn fixture_has_expected_length() {
+        assert_eq!(build_fixture(3).len(), 3);
+    }
+}
+
+#[cfg(all(test, feature = "slow"))]
+mod slow_tests {
+    #[test]
+    fn exhaustive() {
+        for i in 0..1000 {
+            assert!(i < 1000);
+        }
+    }
+}
+
+#[tokio::test]
+async fn async_smoke_test() {
+    assert!(true);
+}
+
+#[cfg(not(test))]
+fn production_only() -> bool {
+    true
+}
+
+#[cfg(any(test, feature = "fake-clock"))]
+fn fake_clock() -> u64 {
+    0
+}
@@ -0,0 +1,18 @@
+// Test sample file: error propagation with the ? operator
+
+use std::fs;
+use std::io;
+
+fn load_all(paths: &[&str]) -> io::Result<Vec<String>> {
+    let a = fs::read_to_string(paths[0])?;
+    let b = fs::read_to_string(paths[1])?;
+    let c = fs::read_to_string(paths[2])?;
+    let d = fs::read_to_string(paths[3])?;
+    let e = fs::read_to_string(paths[4])?;
+    let f = fs::read_to_string(paths[5])?;
+    let g = fs::read_to_string(paths[6])?;
+    let h = fs::read_to_string(paths[7])?;
+    let i = fs::read_to_string(paths[8])?;
+    let j = fs::read_to_string(paths[9])?;
+    Ok(vec![a, b, c, d, e, f, g, h, i, j])
+}